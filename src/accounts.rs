@@ -0,0 +1,229 @@
+//! Multi-account mode: assume a role into each listed account and fetch its
+//! events with that account's own credentials.
+//!
+//! Accounts either share one role name (`--accounts` with `--role-name`) or
+//! are described one by one in a TOML file (`--accounts-config`):
+//!
+//! ```toml
+//! [[accounts]]
+//! id = "123456789012"
+//! name = "prod"
+//! role_arn = "arn:aws:iam::123456789012:role/HealthReader"
+//! external_id = "d6f3c0a1"
+//!
+//! [[accounts]]
+//! id = "210987654321"
+//! role_name = "health-read-only"
+//! ```
+
+use crate::filter::FilterArgs;
+use crate::partition::Partition;
+use crate::progress::Progress;
+use crate::toml;
+use crate::{HealthEvent, get_health_events};
+use aws_config::SdkConfig;
+use aws_config::sts::AssumeRoleProvider;
+use aws_sdk_health::Client;
+use aws_types::sdk_config::SharedCredentialsProvider;
+use chrono::{DateTime, Utc};
+use futures_util::{StreamExt, stream};
+use std::cell::RefCell;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const SESSION_NAME: &str = "aws9man";
+
+/// An account to fetch events from, and the role to assume into it.
+#[derive(Debug, Clone)]
+pub struct AccountRole {
+    pub account_id: String,
+    pub role_arn: String,
+    pub external_id: Option<String>,
+    /// A friendly name for the account
+    pub name: Option<String>,
+}
+
+impl AccountRole {
+    pub fn with_role_name(account_id: &str, role_name: &str, partition: Partition) -> Self {
+        AccountRole {
+            account_id: account_id.to_string(),
+            role_arn: role_arn(account_id, role_name, partition),
+            external_id: None,
+            name: None,
+        }
+    }
+}
+
+impl fmt::Display for AccountRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} ({})", name, self.account_id),
+            None => f.write_str(&self.account_id),
+        }
+    }
+}
+
+fn role_arn(account_id: &str, role_name: &str, partition: Partition) -> String {
+    format!(
+        "arn:{}:iam::{}:role/{}",
+        partition.name(),
+        account_id,
+        role_name
+    )
+}
+
+/// Reads account IDs from `path`, one per line. Blank lines and lines
+/// starting with `#` are ignored.
+pub fn read_account_ids(path: &Path) -> io::Result<Vec<String>> {
+    let contents = fs::read_to_string(path)?;
+    let mut accounts = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if !is_account_id(line) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{}:{}: expected a 12-digit account ID, got {:?}",
+                    path.display(),
+                    number + 1,
+                    line
+                ),
+            ));
+        }
+        accounts.push(line.to_string());
+    }
+    Ok(accounts)
+}
+
+/// Reads the `[[accounts]]` tables of a TOML accounts config file. Role names
+/// are turned into ARNs in `partition`.
+pub fn read_accounts_config(
+    path: &Path,
+    partition: Partition,
+) -> Result<Vec<AccountRole>, Box<dyn StdError>> {
+    let config = toml::parse(&fs::read_to_string(path)?)
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    let entries = match config.get("accounts") {
+        Some(entries) => entries
+            .as_array()
+            .ok_or_else(|| format!("{}: `accounts` must be [[accounts]] tables", path.display()))?,
+        None => &[],
+    };
+
+    let mut accounts = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.iter().enumerate() {
+        let error =
+            |message: &str| format!("{}: account #{}: {}", path.display(), index + 1, message);
+        let entry = entry.as_table().ok_or_else(|| error("expected a table"))?;
+        let field = |key: &str| -> Result<Option<String>, String> {
+            match entry.get(key) {
+                Some(value) => match value.as_str() {
+                    Some(value) => Ok(Some(value.to_string())),
+                    None => Err(error(&format!("`{}` must be a string", key))),
+                },
+                None => Ok(None),
+            }
+        };
+
+        let account_id = field("id")?.ok_or_else(|| error("missing `id`"))?;
+        if !is_account_id(&account_id) {
+            return Err(error("`id` must be a 12-digit account ID").into());
+        }
+        let role_arn = match (field("role_arn")?, field("role_name")?) {
+            (Some(arn), None) => arn,
+            (None, Some(name)) => role_arn(&account_id, &name, partition),
+            _ => return Err(error("set exactly one of `role_arn` and `role_name`").into()),
+        };
+        accounts.push(AccountRole {
+            account_id,
+            role_arn,
+            external_id: field("external_id")?,
+            name: field("name")?,
+        });
+    }
+    Ok(accounts)
+}
+
+fn is_account_id(value: &str) -> bool {
+    value.len() == 12 && value.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Fetches events from up to `account_concurrency` accounts at a time,
+/// tagging each event with the account it came from. Events from different
+/// accounts may be interleaved. Each account's client is `client` with the
+/// account's role credentials.
+#[allow(clippy::too_many_arguments)]
+pub async fn get_account_health_events(
+    base_config: &SdkConfig,
+    client: &Client,
+    accounts: &[AccountRole],
+    filter: &FilterArgs,
+    start_window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    concurrency: usize,
+    account_concurrency: usize,
+    progress: &Progress,
+    on_event: impl FnMut(&HealthEvent) -> io::Result<()>,
+) -> Result<(), Box<dyn StdError>> {
+    // Every account's stream runs on this task, and events are handed over
+    // one at a time, so the callback is never borrowed twice
+    let on_event = RefCell::new(on_event);
+    let mut results = stream::iter(accounts)
+        .map(|account| {
+            let on_event = &on_event;
+            async move {
+                let config = assume_role(
+                    base_config,
+                    &account.role_arn,
+                    account.external_id.as_deref(),
+                    SESSION_NAME,
+                )
+                .await;
+                let mut client_config = client.config().to_builder();
+                client_config.set_credentials_provider(config.credentials_provider());
+                let client = Client::from_conf(client_config.build());
+                get_health_events(
+                    &client,
+                    filter,
+                    start_window,
+                    concurrency,
+                    progress,
+                    |event| (on_event.borrow_mut())(&event.in_account(&account.account_id)),
+                )
+                .await
+                .map_err(|err| format!("account {}: {}", account, err))
+            }
+        })
+        .buffer_unordered(account_concurrency);
+
+    while let Some(result) = results.next().await {
+        result?;
+    }
+    Ok(())
+}
+
+/// Returns `base_config` with credentials for `role_arn` in place of the
+/// base credentials.
+pub async fn assume_role(
+    base_config: &SdkConfig,
+    role_arn: &str,
+    external_id: Option<&str>,
+    session_name: &str,
+) -> SdkConfig {
+    let mut builder = AssumeRoleProvider::builder(role_arn)
+        .session_name(session_name)
+        .configure(base_config);
+    if let Some(external_id) = external_id {
+        builder = builder.external_id(external_id);
+    }
+    let provider = builder.build().await;
+    base_config
+        .to_builder()
+        .credentials_provider(SharedCredentialsProvider::new(provider))
+        .build()
+}
@@ -0,0 +1,50 @@
+//! Splitting records into batches for APIs that cap both the number of
+//! records per call and their total size.
+
+/// Splits `records` into batches of at most `max_records` records and
+/// `max_bytes` bytes (as measured by `size`). A record larger than
+/// `max_bytes` gets a batch of its own, for the API to reject.
+pub fn batches<T>(
+    records: Vec<T>,
+    max_records: usize,
+    max_bytes: usize,
+    size: impl Fn(&T) -> usize,
+) -> Vec<Vec<T>> {
+    let mut batches = Vec::new();
+    let mut batch = Vec::new();
+    let mut batch_bytes = 0;
+    for record in records {
+        let record_bytes = size(&record);
+        if !batch.is_empty()
+            && (batch.len() == max_records || batch_bytes + record_bytes > max_bytes)
+        {
+            batches.push(std::mem::take(&mut batch));
+            batch_bytes = 0;
+        }
+        batch_bytes += record_bytes;
+        batch.push(record);
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caps_record_count_and_total_size() {
+        let records = vec!["aaaa", "bb", "cc", "d", "eeeeeeeeee", "f"];
+        assert_eq!(
+            batches(records, 3, 6, |record| record.len()),
+            [
+                vec!["aaaa", "bb"],
+                vec!["cc", "d"],
+                vec!["eeeeeeeeee"],
+                vec!["f"]
+            ]
+        );
+    }
+}
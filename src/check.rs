@@ -0,0 +1,150 @@
+//! Nagios and Icinga plugin output (`--check`): a state from the number of
+//! open issues and upcoming scheduled changes, a one-line status and
+//! performance data.
+
+use crate::HealthEvent;
+use crate::metrics::Summary;
+use crate::notify::title;
+
+/// Titles named in the status line, before "and N more".
+const MAX_TITLES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Ok,
+    Warning,
+    Critical,
+    Unknown,
+}
+
+impl State {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            State::Ok => 0,
+            State::Warning => 1,
+            State::Critical => 2,
+            State::Unknown => 3,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            State::Ok => "OK",
+            State::Warning => "WARNING",
+            State::Critical => "CRITICAL",
+            State::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+/// Checks `events`, returning the state and its status line: CRITICAL from
+/// `critical` open issues, WARNING from `warning` upcoming scheduled changes
+/// or from fewer open issues than `critical`.
+pub fn evaluate(events: &[HealthEvent], warning: u64, critical: u64) -> (State, String) {
+    let issues: Vec<_> = events
+        .iter()
+        .filter(|event| event.status == "open" && event.event_type_category == "issue")
+        .collect();
+    let count = issues.len() as u64;
+    let summary = Summary::of(events);
+    let changes = summary.upcoming_scheduled_changes();
+    let state = if count >= critical {
+        State::Critical
+    } else if count > 0 || changes >= warning {
+        State::Warning
+    } else {
+        State::Ok
+    };
+
+    let mut message = match issues.len() {
+        0 => "no open issues".to_string(),
+        1 => format!("1 open issue: {}", title(issues[0])),
+        n => format!(
+            "{} open issues: {}",
+            n,
+            issues
+                .iter()
+                .take(MAX_TITLES)
+                .map(|event| title(event))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    if issues.len() > MAX_TITLES {
+        message.push_str(&format!(" and {} more", issues.len() - MAX_TITLES));
+    }
+    if changes > 0 {
+        message.push_str(&format!(
+            ", {} upcoming scheduled change{}",
+            changes,
+            if changes == 1 { "" } else { "s" }
+        ));
+    }
+    let line = format!(
+        "{} | open_issues={};;{};0 open_events={};;;0 upcoming_scheduled_changes={};{};;0",
+        status(state, &message),
+        count,
+        critical,
+        summary.open_events(),
+        changes,
+        warning
+    );
+    (state, line)
+}
+
+/// A status line without performance data, e.g. for an error.
+pub fn status(state: State, message: &str) -> String {
+    // Later lines and `|` would be read as performance data
+    let message = message.replace(['\n', '|'], " ");
+    format!("AWS HEALTH {} - {}", state.label(), message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_issues_are_critical_and_scheduled_changes_a_warning() {
+        let event = |category: &str, status: &str| HealthEvent {
+            service: "EC2".to_string(),
+            region: "us-east-1".to_string(),
+            event_type_code: "AWS_EC2_OPERATIONAL_ISSUE".to_string(),
+            event_type_category: category.to_string(),
+            status: status.to_string(),
+            ..Default::default()
+        };
+        let issue = |status: &str| event("issue", status);
+        let change = || event("scheduledChange", "upcoming");
+        assert_eq!(
+            evaluate(&[issue("closed")], 1, 1),
+            (
+                State::Ok,
+                "AWS HEALTH OK - no open issues | open_issues=0;;1;0 open_events=0;;;0 \
+                 upcoming_scheduled_changes=0;1;;0"
+                    .to_string()
+            )
+        );
+        let (state, line) = evaluate(&[issue("open")], 1, 1);
+        assert_eq!(state, State::Critical);
+        assert!(line.starts_with(
+            "AWS HEALTH CRITICAL - 1 open issue: EC2 operational issue (us-east-1) | open_issues=1;;1;0"
+        ));
+        assert_eq!(evaluate(&[issue("open")], 1, 2).0, State::Warning);
+        assert_eq!(
+            evaluate(&[issue("open"), issue("open")], 1, 2).0,
+            State::Critical
+        );
+
+        let (state, line) = evaluate(&[change()], 1, 1);
+        assert_eq!(state, State::Warning);
+        assert!(
+            line.starts_with("AWS HEALTH WARNING - no open issues, 1 upcoming scheduled change |")
+        );
+        assert!(line.ends_with(" upcoming_scheduled_changes=1;1;;0"));
+        assert_eq!(evaluate(&[change()], 2, 1).0, State::Ok);
+        assert_eq!(
+            evaluate(&[change(), issue("open")], 2, 1).0,
+            State::Critical
+        );
+    }
+}
@@ -0,0 +1,2039 @@
+//! The command line tool: its flags, and running them.
+
+use crate::filter::FilterArgs;
+use crate::output::{OutputFormat, SplitBy};
+use crate::partition::Partition;
+#[cfg(feature = "postgres")]
+use crate::postgres;
+use crate::progress::Progress;
+use crate::{
+    HealthEvent, accounts, check, cloudwatch_logs, cloudwatch_metrics, compress, config, datadog,
+    discord, dynamodb, email, eventbridge, filter, firehose, get_health_events, health_config,
+    http_client, loki, metrics, mfa, opensearch, org, organizations, otlp, output, pagerduty,
+    proxy, rate_limit, routes, s3, ses, sigv4, slack, smtp, sns, splunk, sqlite, sqs, sso, state,
+    stats, statsd, teams, telegram, tz, webhook, zabbix,
+};
+use aws_config::BehaviorVersion;
+use aws_config::retry::RetryConfig;
+use aws_config::timeout::TimeoutConfig;
+use aws_sdk_health::Client;
+use aws_smithy_runtime_api::client::http::SharedHttpClient;
+use aws_types::region::Region;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, TimeZone, Utc};
+use clap::builder::RangedU64ValueParser;
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand};
+use std::collections::HashMap;
+use std::env;
+use std::error::Error as StdError;
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// AWS Health only returns events from this many days back
+const RETENTION_DAYS: i64 = 90;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+#[command(group(ArgGroup::new("multi_account").args(["org", "accounts", "accounts_config"])))]
+#[command(group(ArgGroup::new("preset").args(["this_month", "last_week", "yesterday"]).conflicts_with_all(["from_utc", "to_utc", "since", "upcoming"])))]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Read default settings from this TOML file instead of
+    /// ~/.config/aws9man/config.toml
+    #[arg(long, env = "AWS9MAN_CONFIG", value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Also apply the settings in the config file's [profiles.NAME] table
+    #[arg(long, env = "AWS9MAN_RUN_PROFILE", value_name = "NAME")]
+    run_profile: Option<String>,
+
+    /// Start date in UTC (YYYY-MM-DD), or an RFC 3339 timestamp such as
+    /// 2024-05-01T14:30:00Z
+    #[arg(long, env = "AWS9MAN_FROM_UTC")]
+    from_utc: Option<String>,
+
+    /// End date in UTC (YYYY-MM-DD), or an RFC 3339 timestamp
+    #[arg(long, env = "AWS9MAN_TO_UTC")]
+    to_utc: Option<String>,
+
+    /// Start this long before now instead of at --from-utc, e.g. 7d, 48h,
+    /// 90m or 1w2d (units: m, h, d, w)
+    #[arg(long, visible_alias = "last", env = "AWS9MAN_SINCE", value_name = "DURATION", value_parser = parse_duration, conflicts_with = "from_utc")]
+    since: Option<chrono::Duration>,
+
+    /// Report on this calendar month so far (UTC)
+    #[arg(long, env = "AWS9MAN_THIS_MONTH")]
+    this_month: bool,
+
+    /// Report on the previous Monday-to-Sunday week (UTC)
+    #[arg(long, env = "AWS9MAN_LAST_WEEK")]
+    last_week: bool,
+
+    /// Report on the previous calendar day (UTC)
+    #[arg(long, env = "AWS9MAN_YESTERDAY")]
+    yesterday: bool,
+
+    /// Report events scheduled to start in the next DAYS days (default 14):
+    /// upcoming scheduled changes, unless --status/--category say otherwise
+    #[arg(long, env = "AWS9MAN_UPCOMING", value_name = "DAYS", num_args = 0..=1, default_missing_value = "14", value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["from_utc", "to_utc", "since", "incremental"])]
+    upcoming: Option<u32>,
+
+    /// Warn about an unparseable --from-utc/--to-utc and use the default
+    /// instead of failing
+    #[arg(long, env = "AWS9MAN_LENIENT_DATES")]
+    lenient_dates: bool,
+
+    #[command(flatten)]
+    filter: FilterArgs,
+
+    /// Load credentials (and the default region) from this named profile in
+    /// the shared AWS config files
+    #[arg(long, env = "AWS9MAN_PROFILE", value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Assume this role with the loaded credentials and use it for every call
+    #[arg(long, env = "AWS9MAN_ROLE_ARN", value_name = "ARN")]
+    role_arn: Option<String>,
+
+    /// External ID to pass when assuming --role-arn
+    #[arg(
+        long,
+        env = "AWS9MAN_EXTERNAL_ID",
+        value_name = "ID",
+        requires = "role_arn"
+    )]
+    external_id: Option<String>,
+
+    /// Session name to use when assuming --role-arn
+    #[arg(long, env = "AWS9MAN_SESSION_NAME", value_name = "NAME", requires = "role_arn", default_value = accounts::SESSION_NAME)]
+    session_name: String,
+
+    /// Serial number (or ARN) of the MFA device --role-arn requires
+    #[arg(
+        long,
+        env = "AWS9MAN_MFA_SERIAL",
+        value_name = "SERIAL",
+        requires = "role_arn"
+    )]
+    mfa_serial: Option<String>,
+
+    /// MFA code to assume the role with, instead of prompting for one; needs
+    /// --mfa-serial or a --profile with mfa_serial
+    #[arg(long, env = "AWS9MAN_MFA_CODE", value_name = "CODE")]
+    mfa_code: Option<String>,
+
+    /// Fetch events for every account in the organization (run from the
+    /// management or delegated administrator account)
+    #[arg(long, env = "AWS9MAN_ORG", conflicts_with = "availability_zones")]
+    org: bool,
+
+    /// Fetch events from every account listed in this file (one account ID
+    /// per line) by assuming --role-name in each
+    #[arg(
+        long,
+        env = "AWS9MAN_ACCOUNTS",
+        value_name = "PATH",
+        requires = "role_name"
+    )]
+    accounts: Option<PathBuf>,
+
+    /// Name of the role to assume in each account listed in --accounts
+    #[arg(
+        long,
+        env = "AWS9MAN_ROLE_NAME",
+        value_name = "NAME",
+        requires = "accounts"
+    )]
+    role_name: Option<String>,
+
+    /// Fetch events from every account in this TOML file, each with its own
+    /// role ARN, external ID and name
+    #[arg(long, env = "AWS9MAN_ACCOUNTS_CONFIG", value_name = "PATH")]
+    accounts_config: Option<PathBuf>,
+
+    /// Number of event batches (up to 10 events each) to fetch details for in parallel
+    #[arg(long, env = "AWS9MAN_CONCURRENCY", default_value_t = 4, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    concurrency: usize,
+
+    /// Number of accounts to fetch in parallel in multi-account mode, or to
+    /// look up entities for in parallel in org mode
+    #[arg(long, env = "AWS9MAN_ACCOUNT_CONCURRENCY", default_value_t = 4, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    account_concurrency: usize,
+
+    /// Maximum attempts (including the first) for each Health API call;
+    /// throttling and transient errors are retried with jittered backoff
+    #[arg(long, env = "AWS9MAN_MAX_ATTEMPTS", default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    max_attempts: u32,
+
+    /// Upper bound on the backoff between retries, in seconds
+    #[arg(long, env = "AWS9MAN_MAX_BACKOFF_SECS", default_value_t = 20)]
+    max_backoff_secs: u64,
+
+    /// Give up on any single API operation (including its retries) after this
+    /// many seconds
+    #[arg(long, env = "AWS9MAN_TIMEOUT_SECS", value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout_secs: Option<u64>,
+
+    /// Maximum AWS API requests per second, shared by all concurrent workers
+    #[arg(long, env = "AWS9MAN_MAX_RPS", value_name = "RPS", value_parser = parse_max_rps)]
+    max_rps: Option<f64>,
+
+    /// Send AWS API calls to this URL instead of the AWS endpoints, e.g. a
+    /// moto or LocalStack server, or an internal proxy
+    #[arg(long, env = "AWS9MAN_ENDPOINT_URL", value_name = "URL")]
+    endpoint_url: Option<String>,
+
+    /// Send requests through this HTTP proxy (`http://[user:pass@]host:port`)
+    /// instead of the one in HTTPS_PROXY/HTTP_PROXY, if any
+    #[arg(long, env = "AWS9MAN_PROXY", value_name = "URL")]
+    proxy: Option<String>,
+
+    /// Use FIPS endpoints
+    #[arg(long, env = "AWS9MAN_FIPS")]
+    fips: bool,
+
+    /// Only report events updated since the last --incremental run, as
+    /// recorded in --state-file (the first run uses the usual window). This,
+    /// or --append, is what makes an event "new" to the destinations that
+    /// get each new event: without either, every run sends them all again
+    #[arg(long, env = "AWS9MAN_INCREMENTAL", conflicts_with = "updated_after")]
+    incremental: bool,
+
+    /// File --incremental keeps the latest reported event update time in
+    #[arg(long, env = "AWS9MAN_STATE_FILE", value_name = "PATH", default_value = state::DEFAULT_PATH, requires = "incremental")]
+    state_file: PathBuf,
+
+    /// Show timestamps in this time zone (e.g. Asia/Ho_Chi_Minh) on stdout and
+    /// in text output formats; filtering and typed timestamps stay in UTC
+    #[arg(long, env = "AWS9MAN_TZ", value_name = "ZONE", value_parser = tz::Zone::load)]
+    tz: Option<tz::Zone>,
+
+    /// Timestamp format on stdout and in text output formats: iso8601,
+    /// epoch, epoch-millis or a strftime string such as "%Y-%m-%d %H:%M"
+    #[arg(long, env = "AWS9MAN_TIME_FORMAT", value_name = "FORMAT", value_parser = tz::TimeFormat::parse)]
+    time_format: Option<tz::TimeFormat>,
+
+    /// Output file format
+    #[arg(long, env = "AWS9MAN_FORMAT", value_enum, default_value_t = OutputFormat::Csv)]
+    format: OutputFormat,
+
+    /// Write the report here instead of YYYYMMDD_aws_health.<format>, expanding
+    /// strftime placeholders (in UTC) and creating missing directories, e.g.
+    /// %Y/%m/reports-%d.csv; `-` writes it to stdout
+    #[arg(long, env = "AWS9MAN_OUTPUT", value_name = "PATH", value_parser = parse_output_path)]
+    output: Option<String>,
+
+    /// Write the report into a partitioned layout under this directory,
+    /// region=<region>/year=<yyyy>/month=<mm>/YYYYMMDD_aws_health.<format>,
+    /// for Glue crawlers and Athena
+    #[arg(long, env = "AWS9MAN_OUTPUT_DIR", value_name = "DIR", conflicts_with_all = ["output", "append", "compress"])]
+    output_dir: Option<PathBuf>,
+
+    /// Compress the report as it's written, adding .gz or .zst to the default
+    /// file name
+    #[arg(long, env = "AWS9MAN_COMPRESS", value_enum, conflicts_with = "append")]
+    compress: Option<compress::Compression>,
+
+    #[command(flatten)]
+    csv: output::CsvDialect,
+
+    /// Replace output files that already exist instead of stopping
+    #[arg(long, env = "AWS9MAN_FORCE", conflicts_with = "append")]
+    force: bool,
+
+    /// Add rows to an existing CSV report instead of replacing it, skipping
+    /// events it already has (by ARN and last update time)
+    #[arg(long, env = "AWS9MAN_APPEND")]
+    append: bool,
+
+    /// Also write affected entities to this CSV file, one row per entity keyed
+    /// by event ARN, so values containing commas survive a join
+    #[arg(long, env = "AWS9MAN_ENTITIES_CSV", value_name = "PATH")]
+    entities_csv: Option<PathBuf>,
+
+    /// Write one row per affected entity, repeating the event's columns,
+    /// instead of listing every entity in one row
+    #[arg(long, env = "AWS9MAN_EXPLODE_ENTITIES")]
+    explode_entities: bool,
+
+    /// In org or multi-account mode, also write a separate file per account,
+    /// next to the report
+    #[arg(long, env = "AWS9MAN_SPLIT_BY", value_enum, requires = "multi_account")]
+    split_by: Option<SplitBy>,
+
+    /// Write the --split-by files into this directory instead
+    #[arg(
+        long,
+        env = "AWS9MAN_SPLIT_DIR",
+        value_name = "DIR",
+        requires = "split_by"
+    )]
+    split_dir: Option<PathBuf>,
+
+    /// Also upsert events into this SQLite database (requires the sqlite3 CLI)
+    #[arg(long, env = "AWS9MAN_OUTPUT_SQLITE", value_name = "PATH")]
+    output_sqlite: Option<PathBuf>,
+
+    /// Also upsert events into the health_events and affected_entities
+    /// tables of this PostgreSQL database (requires the psql CLI)
+    #[cfg(feature = "postgres")]
+    #[arg(
+        long,
+        env = "AWS9MAN_POSTGRES_URL",
+        value_name = "URL",
+        hide_env_values = true
+    )]
+    postgres_url: Option<String>,
+
+    /// After the run, upload every file written to S3 under this prefix
+    /// (s3://bucket/prefix/), which may contain strftime placeholders (UTC)
+    #[arg(long, env = "AWS9MAN_S3_URI", value_name = "URI", value_parser = s3::S3Uri::parse)]
+    s3_uri: Option<s3::S3Uri>,
+
+    /// Encrypt uploads with SSE-KMS using this key ID, ARN or alias
+    #[arg(
+        long,
+        env = "AWS9MAN_S3_SSE_KMS_KEY_ID",
+        value_name = "KEY",
+        requires = "s3_uri"
+    )]
+    s3_sse_kms_key_id: Option<String>,
+
+    /// Publish each new event to this SNS topic as a JSON message
+    #[arg(long, env = "AWS9MAN_SNS_TOPIC_ARN", value_name = "ARN", value_parser = sns::parse_topic_arn)]
+    sns_topic_arn: Option<String>,
+
+    /// Publish one digest of every new event to SNS instead, split into as
+    /// few messages as fit
+    #[arg(long, env = "AWS9MAN_SNS_DIGEST", requires = "sns_topic_arn")]
+    sns_digest: bool,
+
+    /// Send one message per new event to this SQS queue
+    #[arg(long, env = "AWS9MAN_SQS_QUEUE_URL", value_name = "URL", value_parser = sqs::QueueUrl::parse)]
+    sqs_queue_url: Option<sqs::QueueUrl>,
+
+    /// Put each new event onto this EventBridge bus (a name or ARN), shaped
+    /// like the events Health sends to EventBridge itself
+    #[arg(long, env = "AWS9MAN_EVENTBRIDGE_BUS", value_name = "BUS")]
+    eventbridge_bus: Option<String>,
+
+    /// Source of the events put onto EventBridge (`aws.` sources are
+    /// reserved)
+    #[arg(
+        long,
+        env = "AWS9MAN_EVENTBRIDGE_SOURCE",
+        value_name = "SOURCE",
+        default_value = "aws9man",
+        requires = "eventbridge_bus"
+    )]
+    eventbridge_source: String,
+
+    /// Detail type of the events put onto EventBridge
+    #[arg(
+        long,
+        env = "AWS9MAN_EVENTBRIDGE_DETAIL_TYPE",
+        value_name = "TYPE",
+        default_value = "AWS Health Event",
+        requires = "eventbridge_bus"
+    )]
+    eventbridge_detail_type: String,
+
+    /// Deliver each new event to this Firehose stream (a name or ARN) as a
+    /// JSON line
+    #[arg(long, env = "AWS9MAN_FIREHOSE_STREAM", value_name = "STREAM")]
+    firehose_stream: Option<String>,
+
+    /// Write each new event as a JSON entry to this CloudWatch Logs group,
+    /// creating it if missing
+    #[arg(long, env = "AWS9MAN_CLOUDWATCH_LOG_GROUP", value_name = "GROUP")]
+    cloudwatch_log_group: Option<String>,
+
+    /// Log stream for --cloudwatch-log-group, created if missing
+    #[arg(
+        long,
+        env = "AWS9MAN_CLOUDWATCH_LOG_STREAM",
+        value_name = "STREAM",
+        default_value = "aws9man",
+        requires = "cloudwatch_log_group"
+    )]
+    cloudwatch_log_stream: String,
+
+    /// Put OpenIssueCount and UpcomingScheduledChanges metrics of the run's
+    /// events, per service and in all, into this CloudWatch namespace
+    #[arg(long, env = "AWS9MAN_CLOUDWATCH_METRICS_NAMESPACE", value_name = "NAMESPACE", value_parser = cloudwatch_metrics::parse_namespace)]
+    cloudwatch_metrics_namespace: Option<String>,
+
+    /// Upsert new events into this DynamoDB table (name or ARN), keyed by a
+    /// string partition key named `arn`
+    #[arg(long, env = "AWS9MAN_DYNAMODB_TABLE", value_name = "TABLE")]
+    dynamodb_table: Option<String>,
+
+    /// Bulk-index new events into the OpenSearch or Elasticsearch cluster at
+    /// this URL, signing requests to Amazon OpenSearch endpoints
+    #[arg(long, env = "AWS9MAN_OPENSEARCH_URL", value_name = "URL", value_parser = opensearch::OpenSearchUrl::parse)]
+    opensearch_url: Option<opensearch::OpenSearchUrl>,
+
+    /// Index for --opensearch-url, with strftime placeholders filled in from
+    /// each event's start time (UTC)
+    #[arg(long, env = "AWS9MAN_OPENSEARCH_INDEX", value_name = "TEMPLATE", default_value = "aws-health-%Y.%m", value_parser = opensearch::parse_index_template, requires = "opensearch_url")]
+    opensearch_index: String,
+
+    /// Forward new events to the Splunk HTTP Event Collector at this URL
+    #[arg(long, env = "AWS9MAN_SPLUNK_HEC_URL", value_name = "URL", value_parser = splunk::parse_hec_url, requires = "splunk_hec_token")]
+    splunk_hec_url: Option<String>,
+
+    /// HEC token for --splunk-hec-url
+    #[arg(
+        long,
+        env = "AWS9MAN_SPLUNK_HEC_TOKEN",
+        value_name = "TOKEN",
+        hide_env_values = true,
+        requires = "splunk_hec_url"
+    )]
+    splunk_hec_token: Option<String>,
+
+    /// Splunk index for --splunk-hec-url, instead of the token's default
+    #[arg(
+        long,
+        env = "AWS9MAN_SPLUNK_INDEX",
+        value_name = "INDEX",
+        requires = "splunk_hec_url"
+    )]
+    splunk_index: Option<String>,
+
+    /// Sourcetype for --splunk-hec-url
+    #[arg(
+        long,
+        env = "AWS9MAN_SPLUNK_SOURCETYPE",
+        value_name = "SOURCETYPE",
+        default_value = "aws:health",
+        requires = "splunk_hec_url"
+    )]
+    splunk_sourcetype: String,
+
+    /// Post a Datadog event per new event with this API key, tagged with its
+    /// service, region, status and category
+    #[arg(
+        long,
+        env = "AWS9MAN_DATADOG_API_KEY",
+        value_name = "KEY",
+        hide_env_values = true
+    )]
+    datadog_api_key: Option<String>,
+
+    /// API of the Datadog site for --datadog-api-key, e.g.
+    /// https://api.datadoghq.eu
+    #[arg(
+        long,
+        env = "AWS9MAN_DATADOG_API_URL",
+        value_name = "URL",
+        default_value = "https://api.datadoghq.com",
+        requires = "datadog_api_key"
+    )]
+    datadog_api_url: String,
+
+    /// Push a JSON log line per new event to this Grafana Loki URL, labelled
+    /// with its service, region, status and account; a user and password in
+    /// the URL are sent as basic auth
+    #[arg(long, env = "AWS9MAN_LOKI_URL", value_name = "URL", value_parser = loki::LokiUrl::parse, hide_env_values = true)]
+    loki_url: Option<loki::LokiUrl>,
+
+    /// Tenant for --loki-url, sent as X-Scope-OrgID
+    #[arg(
+        long,
+        env = "AWS9MAN_LOKI_TENANT_ID",
+        value_name = "TENANT",
+        requires = "loki_url"
+    )]
+    loki_tenant_id: Option<String>,
+
+    /// Post a message per new event to this Slack incoming webhook URL. Use
+    /// --incremental, or every run posts every event in the window again
+    #[arg(
+        long,
+        env = "AWS9MAN_SLACK_WEBHOOK",
+        value_name = "URL",
+        hide_env_values = true
+    )]
+    slack_webhook: Option<String>,
+
+    /// Post an Adaptive Card per new event to this Microsoft Teams webhook URL
+    #[arg(
+        long,
+        env = "AWS9MAN_TEAMS_WEBHOOK",
+        value_name = "URL",
+        hide_env_values = true
+    )]
+    teams_webhook: Option<String>,
+
+    /// Post an embed per new event to this Discord webhook URL
+    #[arg(
+        long,
+        env = "AWS9MAN_DISCORD_WEBHOOK",
+        value_name = "URL",
+        hide_env_values = true
+    )]
+    discord_webhook: Option<String>,
+
+    /// Send a message per new open event to --telegram-chat-id through the
+    /// Telegram bot with this token
+    #[arg(
+        long,
+        env = "AWS9MAN_TELEGRAM_BOT_TOKEN",
+        value_name = "TOKEN",
+        hide_env_values = true,
+        requires = "telegram_chat_id"
+    )]
+    telegram_bot_token: Option<String>,
+
+    /// Chat for --telegram-bot-token: a chat ID such as -1001234567890, or a
+    /// channel's @username
+    #[arg(
+        long,
+        env = "AWS9MAN_TELEGRAM_CHAT_ID",
+        value_name = "CHAT",
+        allow_hyphen_values = true,
+        requires = "telegram_bot_token"
+    )]
+    telegram_chat_id: Option<String>,
+
+    /// Bot API server for --telegram-bot-token, e.g. a local one
+    #[arg(
+        long,
+        env = "AWS9MAN_TELEGRAM_API_URL",
+        value_name = "URL",
+        default_value = "https://api.telegram.org",
+        requires = "telegram_bot_token"
+    )]
+    telegram_api_url: String,
+
+    /// Trigger a PagerDuty alert for each open issue, resolving it once the
+    /// issue closes, through an Events API v2 integration with this key
+    #[arg(
+        long,
+        env = "AWS9MAN_PAGERDUTY_ROUTING_KEY",
+        value_name = "KEY",
+        hide_env_values = true
+    )]
+    pagerduty_routing_key: Option<String>,
+
+    /// Events API endpoint for --pagerduty-routing-key, e.g. for the EU service region
+    #[arg(
+        long,
+        env = "AWS9MAN_PAGERDUTY_EVENTS_URL",
+        value_name = "URL",
+        default_value = "https://events.pagerduty.com/v2/enqueue",
+        requires = "pagerduty_routing_key"
+    )]
+    pagerduty_events_url: String,
+
+    #[command(flatten)]
+    email: email::EmailArgs,
+
+    #[command(flatten)]
+    smtp: smtp::SmtpArgs,
+
+    /// Email new events through Amazon SES instead of SMTP, from a verified
+    /// --email-from
+    #[arg(
+        long,
+        env = "AWS9MAN_SES",
+        requires = "email_from",
+        conflicts_with = "smtp_host"
+    )]
+    ses: bool,
+
+    /// POST each new event to this URL, as a JSON object or the payload
+    /// --webhook-template gives
+    #[arg(long, env = "AWS9MAN_WEBHOOK_URL", value_name = "URL")]
+    webhook_url: Option<String>,
+
+    /// File whose contents, with placeholders such as {{title}}, {{detail}}
+    /// or {{{event}}} filled in, are the --webhook-url payload
+    #[arg(
+        long,
+        env = "AWS9MAN_WEBHOOK_TEMPLATE",
+        value_name = "PATH",
+        requires = "webhook_url"
+    )]
+    webhook_template: Option<PathBuf>,
+
+    /// Extra header for --webhook-url requests, e.g. "X-Team: ops" (repeatable)
+    #[arg(
+        long,
+        env = "AWS9MAN_WEBHOOK_HEADER",
+        value_name = "NAME: VALUE",
+        value_parser = webhook::parse_header,
+        requires = "webhook_url"
+    )]
+    webhook_header: Vec<(String, String)>,
+
+    /// Bearer token to authorize --webhook-url requests with
+    #[arg(
+        long,
+        env = "AWS9MAN_WEBHOOK_BEARER_TOKEN",
+        value_name = "TOKEN",
+        hide_env_values = true,
+        requires = "webhook_url"
+    )]
+    webhook_bearer_token: Option<String>,
+
+    /// Send StatsD gauges of the run's events per service and status, and
+    /// counters of them per service, to this HOST:PORT over UDP
+    #[arg(long, env = "AWS9MAN_STATSD_ADDRESS", value_name = "HOST:PORT")]
+    statsd_address: Option<String>,
+
+    /// Prefix for --statsd-address metric names
+    #[arg(
+        long,
+        env = "AWS9MAN_STATSD_PREFIX",
+        value_name = "PREFIX",
+        default_value = "aws_health",
+        requires = "statsd_address"
+    )]
+    statsd_prefix: String,
+
+    /// Send counts of the run's events, in all and per service, and a
+    /// discovery value listing the services, to this Zabbix server or proxy
+    /// (HOST[:PORT]) as trapper items of --zabbix-host
+    #[arg(long, env = "AWS9MAN_ZABBIX_SERVER", value_name = "HOST[:PORT]", value_parser = zabbix::parse_server, requires = "zabbix_host")]
+    zabbix_server: Option<String>,
+
+    /// Host that --zabbix-server items belong to, as named in Zabbix
+    #[arg(
+        long,
+        env = "AWS9MAN_ZABBIX_HOST",
+        value_name = "HOST",
+        requires = "zabbix_server"
+    )]
+    zabbix_host: Option<String>,
+
+    /// Send a trace of the run, with a span per Health API call, to this
+    /// OpenTelemetry collector's OTLP/HTTP endpoint, e.g.
+    /// http://localhost:4318
+    #[arg(
+        long,
+        env = "AWS9MAN_OTLP_ENDPOINT",
+        value_name = "URL",
+        conflicts_with_all = ["serve_metrics", "check"]
+    )]
+    otlp_endpoint: Option<String>,
+
+    /// Extra header for --otlp-endpoint requests, e.g. an API key
+    /// (repeatable)
+    #[arg(
+        long,
+        env = "AWS9MAN_OTLP_HEADER",
+        value_name = "NAME: VALUE",
+        value_parser = webhook::parse_header,
+        hide_env_values = true,
+        requires = "otlp_endpoint"
+    )]
+    otlp_header: Vec<(String, String)>,
+
+    /// Instead of writing a report, serve Prometheus metrics about open and
+    /// upcoming events on this address, e.g. :9898, fetching them again every
+    /// --metrics-interval
+    #[arg(long, env = "AWS9MAN_SERVE_METRICS", value_name = "ADDRESS", value_parser = metrics::parse_listen_address)]
+    serve_metrics: Option<SocketAddr>,
+
+    /// How often --serve-metrics fetches events, e.g. 5m or 1h
+    #[arg(long, env = "AWS9MAN_METRICS_INTERVAL", value_name = "DURATION", value_parser = parse_duration, default_value = "5m", requires = "serve_metrics")]
+    metrics_interval: chrono::Duration,
+
+    /// Instead of writing a report, act as a Nagios or Icinga plugin: report
+    /// the open issues and upcoming scheduled changes in one line with
+    /// performance data, and exit with 0 (OK), 1 (WARNING), 2 (CRITICAL) or
+    /// 3 (UNKNOWN)
+    #[arg(long, env = "AWS9MAN_CHECK", conflicts_with_all = ["serve_metrics", "textfile_dir"])]
+    check: bool,
+
+    /// With --check, WARNING from this many upcoming scheduled changes, or
+    /// from any open issue below --check-critical
+    #[arg(long, env = "AWS9MAN_CHECK_WARNING", value_name = "CHANGES", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..), requires = "check")]
+    check_warning: u64,
+
+    /// With --check, CRITICAL from this many open issues
+    #[arg(long, env = "AWS9MAN_CHECK_CRITICAL", value_name = "ISSUES", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..), requires = "check")]
+    check_critical: u64,
+
+    /// Instead of writing a report, write metrics about open and upcoming
+    /// events to aws9man.prom in this directory, for node_exporter's
+    /// textfile collector
+    #[arg(
+        long,
+        env = "AWS9MAN_TEXTFILE_DIR",
+        value_name = "DIR",
+        conflicts_with = "serve_metrics"
+    )]
+    textfile_dir: Option<PathBuf>,
+
+    /// In org or multi-account mode, don't look up account names with
+    /// organizations:ListAccounts
+    #[arg(long, env = "AWS9MAN_NO_ACCOUNT_NAMES")]
+    no_account_names: bool,
+
+    // The config file's [[routes]], read by parse_args
+    #[arg(skip)]
+    routes: Vec<routes::Route>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage AWS Health access for the organization
+    Org {
+        #[command(subcommand)]
+        command: org::OrgCommand,
+    },
+}
+
+/// Runs the command line tool with the process's arguments.
+pub async fn main() -> Result<(), Box<dyn StdError>> {
+    let args = parse_args()?;
+    let check = args.check;
+    let exporter = match &args.otlp_endpoint {
+        Some(endpoint) => {
+            let proxy = match proxy::Proxies::from_args_or_env(args.proxy.as_deref())? {
+                Some(proxies) => Some(proxy::ProxyHttpClient::new(proxies)?),
+                None => None,
+            };
+            Some(otlp::Exporter::new(
+                http_client::HttpClient::new(proxy.as_ref()),
+                endpoint,
+                args.otlp_header.clone(),
+            ))
+        }
+        None => None,
+    };
+    let result = run(args, exporter.as_ref().map(otlp::Exporter::tracer)).await;
+    // Failed runs are worth a trace too
+    if let Some(exporter) = &exporter
+        && let Err(err) = exporter
+            .export(result.as_ref().err().map(|err| err.as_ref()))
+            .await
+    {
+        eprintln!("Warning: could not export the trace: {}", err);
+    }
+    // A plugin that fails says so in its own format, not with exit code 1
+    // (which would read as WARNING)
+    if let (true, Err(err)) = (check, &result) {
+        // SDK errors only describe themselves in their sources
+        let mut message = err.to_string();
+        let mut source = err.source();
+        while let Some(err) = source {
+            message = format!("{}: {}", message, err);
+            source = err.source();
+        }
+        println!("{}", check::status(check::State::Unknown, &message));
+        std::process::exit(check::State::Unknown.exit_code());
+    }
+    result
+}
+
+async fn run(mut args: Args, tracer: Option<&otlp::Tracer>) -> Result<(), Box<dyn StdError>> {
+    args.csv.time_format = args.time_format.clone().unwrap_or_default();
+    args.csv.zone = args.tz.clone();
+    if args.append && (args.format != OutputFormat::Csv || args.output.as_deref() == Some("-")) {
+        return Err("--append only works with --format csv written to a file".into());
+    }
+    let mails = args.smtp.smtp_host.is_some() || args.ses;
+    if mails
+        && args.email.email_to.is_empty()
+        && args
+            .routes
+            .iter()
+            .all(|route| route.destinations.email_to.is_empty())
+    {
+        return Err("--smtp-host and --ses need --email-to, or a route with email-to".into());
+    }
+    for route in &args.routes {
+        if route.destinations.telegram_chat_id.is_some() && args.telegram_bot_token.is_none() {
+            return Err(format!(
+                "route {}: telegram-chat-id needs --telegram-bot-token",
+                route.name
+            )
+            .into());
+        }
+        if !route.destinations.email_to.is_empty() && !mails {
+            return Err(
+                format!("route {}: email-to needs --smtp-host or --ses", route.name).into(),
+            );
+        }
+    }
+
+    // An incremental run picks up where the last one left off: every event
+    // updated since then, whenever it started
+    let high_water_mark = if args.incremental {
+        state::read_high_water_mark(&args.state_file)?
+    } else {
+        None
+    };
+    if high_water_mark.is_some() {
+        args.filter.updated_after = high_water_mark;
+    }
+
+    // Upcoming mode looks for planned maintenance, unless other statuses or
+    // categories are asked for
+    if args.upcoming.is_some() {
+        if args.filter.statuses.is_empty() {
+            args.filter.statuses = vec![filter::Status::Upcoming];
+        }
+        if args.filter.categories.is_empty() {
+            args.filter.categories = vec![filter::Category::ScheduledChange];
+        }
+    }
+
+    // The default start window is dropped when only end/update ranges are
+    // given, so long-running events that started earlier aren't missed
+    let use_start_window = args.from_utc.is_some()
+        || args.to_utc.is_some()
+        || args.since.is_some()
+        || args.upcoming.is_some()
+        || args.this_month
+        || args.last_week
+        || args.yesterday
+        || !args.filter.has_time_ranges();
+
+    // Calculate default dates (10 days ago to now, a calendar preset, or now
+    // to --upcoming days ahead)
+    let now = Utc::now();
+    let (start_time, end_time) = match (args.upcoming, preset_window(&args, now)) {
+        (_, Some(window)) => window,
+        (Some(days), None) => (now, now + chrono::Duration::days(days.into())),
+        (None, None) => (now - args.since.unwrap_or(chrono::Duration::days(10)), now),
+    };
+
+    // Parse command-line dates if provided
+    let start_date = match &args.from_utc {
+        Some(date_str) => {
+            parse_date_string("--from-utc", date_str, start_time, args.lenient_dates)?
+        }
+        None => start_time,
+    };
+
+    let end_date = match &args.to_utc {
+        Some(date_str) => parse_date_string("--to-utc", date_str, end_time, args.lenient_dates)?,
+        None => end_time,
+    };
+    if use_start_window {
+        validate_window(start_date, end_date, now)?;
+    }
+
+    // Create AWS config and client
+    let retry_config = RetryConfig::standard()
+        .with_max_attempts(args.max_attempts)
+        .with_max_backoff(Duration::from_secs(args.max_backoff_secs));
+    let mut timeout_config = TimeoutConfig::builder();
+    timeout_config.set_operation_timeout(args.timeout_secs.map(Duration::from_secs));
+    let mut loader = aws_config::defaults(BehaviorVersion::latest())
+        .retry_config(retry_config)
+        .timeout_config(timeout_config.build());
+    // A profile that assumes a role with MFA is loaded through its source
+    // profile, and the role is assumed below instead
+    let profile_role = mfa::profile_role(args.profile.as_deref()).await;
+    // Not a clap `requires`: a profile's mfa_serial takes --mfa-code too
+    if args.mfa_code.is_some() && args.mfa_serial.is_none() && profile_role.is_none() {
+        return Err("--mfa-code needs --mfa-serial or a --profile with mfa_serial".into());
+    }
+    match &profile_role {
+        Some(role) => {
+            loader = loader.profile_name(&role.source_profile);
+            if let Some(region) = &role.region {
+                loader = loader.region(Region::new(region.clone()));
+            }
+        }
+        None => {
+            if let Some(profile) = &args.profile {
+                loader = loader.profile_name(profile);
+            }
+        }
+    }
+    // Without a region filter, the region comes from the default chain, which
+    // honours --profile
+    if let Some(region) = args.filter.regions.first() {
+        loader = loader.region(Region::new(region.clone()));
+    }
+    if args.fips {
+        loader = loader.use_fips(true);
+    }
+    if let Some(endpoint_url) = &args.endpoint_url {
+        loader = loader.endpoint_url(endpoint_url);
+    }
+    let proxy = match proxy::Proxies::from_args_or_env(args.proxy.as_deref())? {
+        Some(proxies) => Some(proxy::ProxyHttpClient::new(proxies)?),
+        None => None,
+    };
+    match (args.max_rps, &proxy) {
+        (Some(max_rps), Some(proxy)) => {
+            loader = loader.http_client(rate_limit::RateLimitedHttpClient::new(
+                SharedHttpClient::new(proxy.clone()),
+                Arc::new(rate_limit::RateLimiter::new(max_rps)),
+            ));
+        }
+        (Some(max_rps), None) => {
+            loader = loader.http_client(rate_limit::RateLimitedHttpClient::with_default_client(
+                max_rps,
+            ));
+        }
+        (None, Some(proxy)) => loader = loader.http_client(proxy.clone()),
+        (None, None) => {}
+    }
+    let mut config = loader.load().await;
+    let loaded_profile = match &profile_role {
+        Some(role) => Some(role.source_profile.as_str()),
+        None => args.profile.as_deref(),
+    };
+    sso::check_session(&config, loaded_profile).await?;
+    if let Some(role) = &profile_role {
+        let code = mfa::read_code(&role.mfa_serial, args.mfa_code.as_deref())?;
+        config = mfa::assume_role(
+            &config,
+            &role.role_arn,
+            role.external_id.as_deref(),
+            &args.session_name,
+            &role.mfa_serial,
+            &code,
+        )
+        .await?;
+    }
+    if let Some(role_arn) = &args.role_arn {
+        config = match &args.mfa_serial {
+            Some(mfa_serial) => {
+                let code = mfa::read_code(mfa_serial, args.mfa_code.as_deref())?;
+                mfa::assume_role(
+                    &config,
+                    role_arn,
+                    args.external_id.as_deref(),
+                    &args.session_name,
+                    mfa_serial,
+                    &code,
+                )
+                .await?
+            }
+            None => {
+                accounts::assume_role(
+                    &config,
+                    role_arn,
+                    args.external_id.as_deref(),
+                    &args.session_name,
+                )
+                .await
+            }
+        };
+    }
+    let partition = Partition::of_region(config.region());
+    let stats = stats::ApiStats::new();
+    let client = instrumented_health_client(&config, &stats, tracer).await;
+
+    if let Some(Command::Org { command }) = &args.command {
+        return org::run_command(&client, command).await;
+    }
+
+    // With the report on stdout, everything else goes to stderr
+    let report_to_stdout = args.output.as_deref() == Some("-");
+    let status = |message: String| {
+        if report_to_stdout {
+            eprintln!("{}", message);
+        } else {
+            println!("{}", message);
+        }
+    };
+
+    let accounts = match (&args.accounts, &args.role_name, &args.accounts_config) {
+        (Some(path), Some(role_name), _) => Some(
+            accounts::read_account_ids(path)?
+                .iter()
+                .map(|account_id| {
+                    accounts::AccountRole::with_role_name(account_id, role_name, partition)
+                })
+                .collect::<Vec<_>>(),
+        ),
+        (_, _, Some(path)) => Some(accounts::read_accounts_config(path, partition)?),
+        _ => None,
+    };
+
+    if args.serve_metrics.is_some() || args.textfile_dir.is_some() || args.check {
+        // Metrics and checks count what's open or upcoming now, whenever it
+        // started
+        if args.filter.statuses.is_empty() {
+            args.filter.statuses = vec![filter::Status::Open, filter::Status::Upcoming];
+        }
+        let args = &args;
+        let refresh = async || {
+            let mut events = Vec::new();
+            fetch_events(
+                args,
+                &config,
+                &client,
+                accounts.as_deref(),
+                None,
+                &Progress::new(false),
+                |event| {
+                    events.push(event.clone());
+                    Ok(())
+                },
+            )
+            .await?;
+            Ok(events)
+        };
+        if args.check {
+            let (state, line) =
+                check::evaluate(&refresh().await?, args.check_warning, args.check_critical);
+            println!("{}", line);
+            std::process::exit(state.exit_code());
+        }
+        if let Some(dir) = &args.textfile_dir {
+            let summary = metrics::Summary::of(&refresh().await?);
+            let path = metrics::write_textfile(dir, &summary)?;
+            status(format!("Metrics written to {}", path.display()));
+            return Ok(());
+        }
+        if let Some(address) = args.serve_metrics {
+            let interval = args.metrics_interval.to_std()?;
+            return metrics::serve(address, interval, refresh).await;
+        }
+    }
+
+    let start_window = if use_start_window {
+        status(format!(
+            "Fetching AWS Health events from {} to {}",
+            display_time(start_date, args.tz.as_ref()),
+            display_time(end_date, args.tz.as_ref())
+        ));
+        Some((start_date, end_date))
+    } else {
+        status("Fetching AWS Health events in the requested end/update ranges".to_string());
+        None
+    };
+
+    let account_names = if args.no_account_names || !(args.org || accounts.is_some()) {
+        HashMap::new()
+    } else {
+        account_names(&config, proxy.as_ref(), accounts.as_deref()).await
+    };
+
+    // Create output filename based on current date
+    let filename = match &args.output {
+        Some(path) => Utc::now().format(path).to_string(),
+        None => {
+            let mut filename = format!(
+                "{}_aws_health.{}",
+                Utc::now().format("%Y%m%d"),
+                args.format.extension()
+            );
+            if let Some(compression) = args.compress {
+                filename = format!("{}.{}", filename, compression.extension());
+            }
+            filename
+        }
+    };
+
+    // Create output writer
+    let file_path = Path::new(&filename);
+    let mut existing_rows = match args.append {
+        true => output::read_csv_keys(file_path, &args.csv)?,
+        false => None,
+    };
+    let (mut writer, compressor) = match args.output_dir {
+        Some(_) => (None, None),
+        None => {
+            let (writer, compressor) =
+                open_report(&args, file_path, existing_rows.is_some(), report_to_stdout)?;
+            (Some(writer), compressor)
+        }
+    };
+    let mut partitions = args.output_dir.clone().map(|dir| {
+        output::PartitionedWriter::new(
+            dir,
+            format!(
+                "{}_aws_health.{}",
+                Utc::now().format("%Y%m%d"),
+                args.format.extension()
+            ),
+            args.format,
+            args.csv.clone(),
+            args.explode_entities,
+            args.force,
+        )
+    });
+    let mut split = match args.split_by {
+        Some(SplitBy::Account) => Some(output::AccountSplitWriter::new(
+            args.split_dir(file_path)?,
+            args.format,
+            args.csv.clone(),
+            args.explode_entities,
+            args.force,
+        )),
+        None => None,
+    };
+    let mut entities = match &args.entities_csv {
+        Some(path) => Some(output::EntityCsvWriter::new(
+            BufWriter::new(output::create_file(path, args.force)?),
+            &args.csv,
+        )?),
+        None => None,
+    };
+    // Load the template now, rather than fail after the whole run
+    let webhook_template = match &args.webhook_template {
+        Some(path) => Some(webhook::Template::load(path)?),
+        None => None,
+    };
+    let mut db = match &args.output_sqlite {
+        Some(path) => Some(sqlite::SqliteWriter::open(path)?),
+        None => None,
+    };
+    #[cfg(feature = "postgres")]
+    let mut pg = match &args.postgres_url {
+        Some(url) => Some(postgres::PostgresWriter::open(url)?),
+        None => None,
+    };
+
+    // Get health events, writing each one as soon as it is fetched
+    let progress = Progress::for_stderr();
+    let terminal = io::stdout().is_terminal();
+    let mut latest_update = high_water_mark;
+    // Events for the sinks that publish them after the run
+    let publish_events = args.sns_topic_arn.is_some()
+        || args.sqs_queue_url.is_some()
+        || args.eventbridge_bus.is_some()
+        || args.firehose_stream.is_some()
+        || args.cloudwatch_log_group.is_some()
+        || args.cloudwatch_metrics_namespace.is_some()
+        || args.dynamodb_table.is_some()
+        || args.opensearch_url.is_some()
+        || args.splunk_hec_url.is_some()
+        || args.datadog_api_key.is_some()
+        || args.loki_url.is_some()
+        || args.slack_webhook.is_some()
+        || args.teams_webhook.is_some()
+        || args.discord_webhook.is_some()
+        || args.telegram_bot_token.is_some()
+        || args.pagerduty_routing_key.is_some()
+        || args.smtp.smtp_host.is_some()
+        || args.ses
+        || args.webhook_url.is_some()
+        || !args.routes.is_empty()
+        || args.statsd_address.is_some()
+        || args.zabbix_server.is_some();
+    let mut new_events = Vec::new();
+    let on_event = |event: &HealthEvent| {
+        // Events updated exactly at the mark were reported last time
+        if let (Some(mark), Some(updated)) = (high_water_mark, event.last_updated_time)
+            && updated <= mark
+        {
+            return Ok(());
+        }
+        if let Some(rows) = &mut existing_rows
+            && !rows.insert(output::csv_row_key(event, &args.csv))
+        {
+            return Ok(());
+        }
+        latest_update = latest_update.max(event.last_updated_time);
+        let mut event = event.with_account_names(&account_names);
+        if args.tz.is_some() || args.time_format.is_some() {
+            let format = args
+                .time_format
+                .as_ref()
+                .unwrap_or(&tz::TimeFormat::Iso8601);
+            event = event.with_time_format(format, args.tz.as_ref());
+        }
+        let event = &event;
+        if !report_to_stdout {
+            progress.suspend(|| print_event(event, terminal.then(Utc::now)));
+        }
+        if let Some(db) = &mut db {
+            db.write(event)?;
+        }
+        #[cfg(feature = "postgres")]
+        if let Some(pg) = &mut pg {
+            pg.write(event)?;
+        }
+        if let Some(entities) = &mut entities {
+            entities.write(event)?;
+        }
+        if publish_events {
+            new_events.push(event.clone());
+        }
+        if let Some(split) = &mut split {
+            split.write(event)?;
+        }
+        if let Some(partitions) = &mut partitions {
+            partitions.write(event)?;
+        }
+        if let Some(writer) = &mut writer {
+            if args.explode_entities {
+                for row in event.per_entity() {
+                    writer.write(&row)?;
+                }
+            } else {
+                writer.write(event)?;
+            }
+        }
+        Ok(())
+    };
+    fetch_events(
+        &args,
+        &config,
+        &client,
+        accounts.as_deref(),
+        start_window,
+        &progress,
+        on_event,
+    )
+    .await?;
+    progress.finish();
+
+    // Files written, with the names to upload them as
+    let mut written = Vec::new();
+    let mut report_file = None;
+    if let Some(writer) = writer {
+        writer.finish()?;
+        if let Some(compressor) = compressor {
+            compressor.finish()?;
+        }
+        if !report_to_stdout {
+            status(format!("Events written to {}", filename));
+            written.push((file_path.to_path_buf(), file_name(file_path)));
+            report_file = Some(file_path.to_path_buf());
+        }
+    }
+    if let (Some(partitions), Some(dir)) = (partitions, &args.output_dir) {
+        let paths = partitions.finish()?;
+        status(format!(
+            "Events written to {} partitions under {}",
+            paths.len(),
+            dir.display()
+        ));
+        for path in paths {
+            let name = path.strip_prefix(dir).unwrap_or(&path).to_string_lossy();
+            written.push((path.clone(), name.into_owned()));
+        }
+    }
+    if let Some(split) = split {
+        let paths = split.finish()?;
+        status(format!(
+            "Per-account events written to {} files",
+            paths.len()
+        ));
+        for path in paths {
+            let name = file_name(&path);
+            written.push((path, name));
+        }
+    }
+    if let (Some(entities), Some(path)) = (entities, &args.entities_csv) {
+        entities.finish()?;
+        status(format!("Affected entities written to {}", path.display()));
+        written.push((path.clone(), file_name(path)));
+    }
+    if let Some(uri) = &args.s3_uri {
+        let client = sigv4::SignedClient::new(&config, proxy.as_ref()).await?;
+        let uploader = s3::Uploader::new(client, uri.clone(), args.s3_sse_kms_key_id.clone());
+        for (path, name) in &written {
+            let object = uploader.upload(path, name).await?;
+            status(format!("Uploaded {} to {}", path.display(), object));
+        }
+    }
+    if let Some(topic_arn) = &args.sns_topic_arn {
+        let client = sigv4::SignedClient::new(&config, proxy.as_ref()).await?;
+        let publisher = sns::SnsPublisher::new(client, topic_arn.clone());
+        if !args.sns_digest {
+            publisher.publish_events(&new_events).await?;
+            status(format!(
+                "Published {} events to {}",
+                new_events.len(),
+                topic_arn
+            ));
+        } else if !new_events.is_empty() {
+            let messages = publisher.publish_digest(&new_events).await?;
+            status(format!(
+                "Published a digest of {} events to {} in {} messages",
+                new_events.len(),
+                topic_arn,
+                messages
+            ));
+        }
+    }
+    if let Some(queue) = &args.sqs_queue_url {
+        let client = sigv4::SignedClient::new(&config, proxy.as_ref()).await?;
+        sqs::SqsSender::new(client, queue.clone())
+            .send_events(&new_events)
+            .await?;
+        status(format!("Sent {} events to SQS", new_events.len()));
+    }
+    if let Some(bus) = &args.eventbridge_bus {
+        let client = sigv4::SignedClient::new(&config, proxy.as_ref()).await?;
+        let publisher = eventbridge::EventBridgePublisher::new(
+            client,
+            bus.clone(),
+            args.eventbridge_source.clone(),
+            args.eventbridge_detail_type.clone(),
+        );
+        let count = publisher.put_events(&new_events).await?;
+        status(format!("Put {} events onto EventBridge bus {}", count, bus));
+    }
+    if let Some(stream) = &args.firehose_stream {
+        let client = sigv4::SignedClient::new(&config, proxy.as_ref()).await?;
+        let sender = firehose::FirehoseSender::new(client, stream);
+        sender.send_events(&new_events).await?;
+        status(format!(
+            "Delivered {} events to Firehose stream {}",
+            new_events.len(),
+            sender.stream()
+        ));
+    }
+    if let Some(group) = &args.cloudwatch_log_group {
+        let client = sigv4::SignedClient::new(&config, proxy.as_ref()).await?;
+        cloudwatch_logs::LogsWriter::new(client, group.clone(), args.cloudwatch_log_stream.clone())
+            .write_events(&new_events)
+            .await?;
+        status(format!(
+            "Wrote {} events to CloudWatch Logs {}/{}",
+            new_events.len(),
+            group,
+            args.cloudwatch_log_stream
+        ));
+    }
+    if let Some(namespace) = &args.cloudwatch_metrics_namespace {
+        let client = sigv4::SignedClient::new(&config, proxy.as_ref()).await?;
+        let count = cloudwatch_metrics::MetricsPublisher::new(client, namespace.clone())
+            .publish(&new_events)
+            .await?;
+        status(format!(
+            "Put {} metrics into CloudWatch namespace {}",
+            count, namespace
+        ));
+    }
+    if let Some(table) = &args.dynamodb_table {
+        let client = sigv4::SignedClient::new(&config, proxy.as_ref()).await?;
+        let count = dynamodb::DynamoDbWriter::new(client, table.clone())
+            .upsert_events(&new_events)
+            .await?;
+        status(format!(
+            "Upserted {} events into DynamoDB table {}",
+            count, table
+        ));
+    }
+    if let Some(url) = &args.opensearch_url {
+        let signer = match url.is_aws() {
+            true => Some(sigv4::SignedClient::new(&config, proxy.as_ref()).await?),
+            false => None,
+        };
+        opensearch::OpenSearchIndexer::new(
+            http_client::HttpClient::new(proxy.as_ref()),
+            signer,
+            url.clone(),
+            args.opensearch_index.clone(),
+        )
+        .index_events(&new_events)
+        .await?;
+        status(format!(
+            "Indexed {} events into OpenSearch",
+            new_events.len()
+        ));
+    }
+    if let (Some(url), Some(token)) = (&args.splunk_hec_url, &args.splunk_hec_token) {
+        splunk::SplunkHec::new(
+            http_client::HttpClient::new(proxy.as_ref()),
+            url.clone(),
+            token,
+            args.splunk_index.clone(),
+            args.splunk_sourcetype.clone(),
+        )
+        .send_events(&new_events)
+        .await?;
+        status(format!("Sent {} events to Splunk", new_events.len()));
+    }
+    if let Some(api_key) = &args.datadog_api_key {
+        datadog::DatadogNotifier::new(
+            http_client::HttpClient::new(proxy.as_ref()),
+            &args.datadog_api_url,
+            api_key.clone(),
+        )
+        .notify(&new_events)
+        .await?;
+        status(format!("Posted {} events to Datadog", new_events.len()));
+    }
+    if let Some(url) = &args.loki_url {
+        let count = loki::LokiPusher::new(
+            http_client::HttpClient::new(proxy.as_ref()),
+            url.clone(),
+            args.loki_tenant_id.clone(),
+        )
+        .push(&new_events)
+        .await?;
+        status(format!("Pushed {} lines to Loki", count));
+    }
+    let notifier = Notifier {
+        args: &args,
+        config: &config,
+        proxy: proxy.as_ref(),
+        webhook_template: webhook_template.as_ref(),
+        report_file: report_file.as_deref(),
+        status: &status,
+    };
+    notifier
+        .notify(&args.destinations(), &new_events, None)
+        .await?;
+    for route in &args.routes {
+        let events: Vec<_> = new_events
+            .iter()
+            .filter(|event| route.matches(event))
+            .cloned()
+            .collect();
+        notifier
+            .notify(&route.destinations, &events, Some(&route.name))
+            .await?;
+    }
+    if let Some(address) = &args.statsd_address {
+        let count = statsd::StatsdSender::new(address.clone(), args.statsd_prefix.clone())
+            .send(&new_events)
+            .await?;
+        status(format!("Sent {} metrics to StatsD at {}", count, address));
+    }
+    if let (Some(server), Some(host)) = (&args.zabbix_server, &args.zabbix_host) {
+        let info = zabbix::ZabbixSender::new(server.clone(), host.clone())
+            .send(&new_events)
+            .await?;
+        status(format!(
+            "Sent event counts to Zabbix at {}: {}",
+            server, info
+        ));
+    }
+    if let (Some(db), Some(path)) = (db, &args.output_sqlite) {
+        db.finish()?;
+        status(format!("Events upserted into {}", path.display()));
+    }
+    #[cfg(feature = "postgres")]
+    if let Some(pg) = pg {
+        pg.finish()?;
+        status("Events upserted into PostgreSQL".to_string());
+    }
+    if let (true, Some(latest_update)) = (args.incremental, latest_update) {
+        state::write_high_water_mark(&args.state_file, latest_update)?;
+    }
+    stats.print_summary();
+
+    Ok(())
+}
+
+impl Args {
+    /// Where --split-by writes its files: --split-dir, or the report's
+    /// directory (the current one when the report goes to stdout), which is
+    /// created if missing.
+    fn split_dir(&self, report: &Path) -> io::Result<PathBuf> {
+        let dir = match &self.split_dir {
+            Some(dir) => dir.clone(),
+            None if self.output.as_deref() == Some("-") => PathBuf::new(),
+            None => report.parent().map(Path::to_path_buf).unwrap_or_default(),
+        };
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(&dir)?;
+        }
+        Ok(dir)
+    }
+
+    /// The chat, paging, email and webhook destinations the flags give.
+    fn destinations(&self) -> routes::Destinations {
+        routes::Destinations {
+            slack_webhook: self.slack_webhook.clone(),
+            teams_webhook: self.teams_webhook.clone(),
+            discord_webhook: self.discord_webhook.clone(),
+            telegram_chat_id: self.telegram_chat_id.clone(),
+            pagerduty_routing_key: self.pagerduty_routing_key.clone(),
+            webhook_url: self.webhook_url.clone(),
+            email_to: match self.smtp.smtp_host.is_some() || self.ses {
+                true => self.email.email_to.clone(),
+                false => Vec::new(),
+            },
+        }
+    }
+}
+
+/// Sends new events to chat, paging, email and webhook destinations, with
+/// the settings the flags give.
+struct Notifier<'a> {
+    args: &'a Args,
+    config: &'a aws_config::SdkConfig,
+    proxy: Option<&'a proxy::ProxyHttpClient>,
+    webhook_template: Option<&'a webhook::Template>,
+    report_file: Option<&'a Path>,
+    status: &'a dyn Fn(String),
+}
+
+impl Notifier<'_> {
+    /// Sends `events` to `destinations`, naming the `route` they came from
+    /// in status lines.
+    async fn notify(
+        &self,
+        destinations: &routes::Destinations,
+        events: &[HealthEvent],
+        route: Option<&str>,
+    ) -> Result<(), Box<dyn StdError>> {
+        let args = self.args;
+        let status = |message: String| match route {
+            Some(route) => (self.status)(format!("{} (route {})", message, route)),
+            None => (self.status)(message),
+        };
+        let http = || http_client::HttpClient::new(self.proxy);
+        if let Some(webhook) = &destinations.slack_webhook {
+            slack::SlackNotifier::new(http(), webhook.clone())
+                .notify(events)
+                .await?;
+            status(format!("Posted {} events to Slack", events.len()));
+        }
+        if let Some(webhook) = &destinations.teams_webhook {
+            teams::TeamsNotifier::new(http(), webhook.clone())
+                .notify(events)
+                .await?;
+            status(format!("Posted {} events to Teams", events.len()));
+        }
+        if let Some(webhook) = &destinations.discord_webhook {
+            discord::DiscordNotifier::new(http(), webhook.clone())
+                .notify(events)
+                .await?;
+            status(format!("Posted {} events to Discord", events.len()));
+        }
+        if let (Some(token), Some(chat_id)) =
+            (&args.telegram_bot_token, &destinations.telegram_chat_id)
+        {
+            let sent = telegram::TelegramNotifier::new(
+                http(),
+                &args.telegram_api_url,
+                token,
+                chat_id.clone(),
+            )
+            .notify(events)
+            .await?;
+            status(format!("Sent {} open events to Telegram", sent));
+        }
+        if let Some(routing_key) = &destinations.pagerduty_routing_key {
+            let (triggered, resolved) = pagerduty::PagerDutyNotifier::new(
+                http(),
+                args.pagerduty_events_url.clone(),
+                routing_key.clone(),
+            )
+            .notify(events)
+            .await?;
+            status(format!(
+                "Triggered {} and resolved {} PagerDuty alerts",
+                triggered, resolved
+            ));
+        }
+        // An empty digest isn't worth a mail
+        if !destinations.email_to.is_empty() && !events.is_empty() {
+            let mut messages = args.email.messages(&destinations.email_to, events);
+            if args.email.email_attach_report
+                && let Some(path) = self.report_file
+            {
+                let name = file_name(path);
+                messages[0].attachments.push(email::Attachment {
+                    content_type: s3::content_type(&name).to_string(),
+                    data: fs::read(path).map_err(|err| format!("{}: {}", path.display(), err))?,
+                    name,
+                });
+            }
+            match args.smtp.server() {
+                Some(server) => server.send(&messages).await?,
+                None => {
+                    let client = sigv4::SignedClient::new(self.config, self.proxy).await?;
+                    ses::SesSender::new(client).send(&messages).await?
+                }
+            }
+            status(format!(
+                "Emailed {} events to {}",
+                events.len(),
+                destinations.email_to.join(", ")
+            ));
+        }
+        if let Some(url) = &destinations.webhook_url {
+            webhook::WebhookSender::new(
+                http(),
+                url.clone(),
+                args.webhook_header.clone(),
+                args.webhook_bearer_token.as_deref(),
+                self.webhook_template.cloned(),
+            )
+            .send_events(events)
+            .await?;
+            status(format!("Posted {} events to the webhook", events.len()));
+        }
+        Ok(())
+    }
+}
+
+/// The last component of `path`, to name its upload after.
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+type ReportWriter = output::EventWriter<Box<dyn Write>>;
+
+/// Opens the report at `file_path`, or on stdout, returning its writer and
+/// the compressor it goes through, if any. `append` adds to an existing CSV
+/// report.
+fn open_report(
+    args: &Args,
+    file_path: &Path,
+    append: bool,
+    to_stdout: bool,
+) -> io::Result<(ReportWriter, Option<compress::Compressor>)> {
+    let mut compressor = None;
+    let out: Box<dyn Write> = if let Some(compression) = args.compress {
+        let target = if to_stdout {
+            Stdio::inherit()
+        } else {
+            if let Some(dir) = file_path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            output::create_file(file_path, args.force)?.into()
+        };
+        let (started, stdin) = compress::Compressor::start(compression, target)?;
+        compressor = Some(started);
+        Box::new(BufWriter::new(stdin))
+    } else if to_stdout {
+        Box::new(BufWriter::new(io::stdout()))
+    } else if append {
+        Box::new(BufWriter::new(
+            OpenOptions::new().append(true).open(file_path)?,
+        ))
+    } else {
+        if let Some(dir) = file_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // An empty report can be appended to, as if it was missing
+        let replace = args.force || args.append;
+        Box::new(BufWriter::new(output::create_file(file_path, replace)?))
+    };
+    let writer = match append {
+        true => output::EventWriter::append_csv(out, &args.csv),
+        false => output::EventWriter::new(args.format, out, &args.csv)?,
+    };
+    Ok((writer, compressor))
+}
+
+/// Names for the accounts being reported on. Names set in --accounts-config
+/// win; the rest come from organizations:ListAccounts, which is only worth a
+/// warning when it fails since IDs still identify every account.
+async fn account_names(
+    config: &aws_config::SdkConfig,
+    proxy: Option<&proxy::ProxyHttpClient>,
+    accounts: Option<&[accounts::AccountRole]>,
+) -> HashMap<String, String> {
+    let accounts = accounts.unwrap_or_default();
+    let mut names = HashMap::new();
+    if accounts.is_empty() || accounts.iter().any(|account| account.name.is_none()) {
+        match organizations::list_account_names(config, proxy).await {
+            Ok(listed) => names = listed,
+            Err(err) => eprintln!("Warning: could not look up account names: {}", err),
+        }
+    }
+    for account in accounts {
+        if let Some(name) = &account.name {
+            names.insert(account.account_id.clone(), name.clone());
+        }
+    }
+    names
+}
+
+/// Parses the command line, filling in flags it doesn't give from the config
+/// file.
+fn parse_args() -> Result<Args, Box<dyn StdError>> {
+    let argv: Vec<OsString> = env::args_os().collect();
+    let command = Args::command();
+    // A lenient first pass finds the config file; the full parse below
+    // reports any errors (and handles --help)
+    let Ok(matches) = command
+        .clone()
+        .ignore_errors(true)
+        .try_get_matches_from(&argv)
+    else {
+        return Ok(Args::parse_from(argv));
+    };
+    let (path, explicit_path) = match matches.get_one::<PathBuf>("config") {
+        Some(path) => (path.clone(), true),
+        None => match config::default_path() {
+            Some(path) => (path, false),
+            None => return Ok(Args::parse_from(argv)),
+        },
+    };
+    let run_profile = matches.get_one::<String>("run_profile");
+    let mut settings =
+        config::read_settings(&path, explicit_path, run_profile.map(String::as_str))?;
+    let routes = match settings.iter().position(|(key, _)| key == "routes") {
+        Some(index) => routes::parse(&settings.remove(index).1)
+            .map_err(|err| format!("{}: {}", path.display(), err))?,
+        None => Vec::new(),
+    };
+    let settings = config::to_args(&settings, &command, &matches)?;
+
+    let mut argv = argv.into_iter();
+    let args: Vec<OsString> = argv
+        .next()
+        .into_iter()
+        .chain(settings)
+        .chain(argv)
+        .collect();
+    let mut args = Args::parse_from(args);
+    args.routes = routes;
+    Ok(args)
+}
+
+/// Creates a Health client for `config` that reports its calls to `stats`,
+/// and to `tracer` if given.
+async fn instrumented_health_client(
+    config: &aws_config::SdkConfig,
+    stats: &stats::ApiStats,
+    tracer: Option<&otlp::Tracer>,
+) -> Client {
+    let mut health_config = health_config(config).await.interceptor(stats.clone());
+    if let Some(tracer) = tracer {
+        health_config = health_config.interceptor(tracer.clone());
+    }
+    Client::from_conf(health_config.build())
+}
+
+/// Prints `event`, with its start time relative to `now` if given.
+fn print_event(event: &HealthEvent, now: Option<DateTime<Utc>>) {
+    println!("=====");
+    match (event.start_time, now) {
+        (Some(start), Some(now)) => println!(
+            "Timestamp: {} ({})",
+            event.timestamp,
+            relative_time(start, now)
+        ),
+        _ => println!("Timestamp: {}", event.timestamp),
+    }
+    println!("ARN: {}", event.arn);
+    println!("Detail: {}", event.detail);
+    println!("Affected Entities:");
+    for entity in &event.affected_entities {
+        println!("- {}", entity);
+    }
+    if !event.affected_accounts.is_empty() {
+        println!("Affected Accounts: {}", event.accounts_text());
+    }
+    println!();
+}
+
+fn display_time(time: DateTime<Utc>, zone: Option<&tz::Zone>) -> String {
+    match zone {
+        Some(zone) => time
+            .with_timezone(&zone.offset_at(time))
+            .format("%Y-%m-%d %H:%M:%S %:z")
+            .to_string(),
+        None => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+    }
+}
+
+/// The window picked by --this-month, --last-week or --yesterday, if any.
+fn preset_window(args: &Args, now: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let today = now.date_naive().and_time(NaiveTime::MIN).and_utc();
+    if args.this_month {
+        Some((today.with_day(1)?, now))
+    } else if args.last_week {
+        let monday = today - chrono::Duration::days(now.weekday().num_days_from_monday().into());
+        Some((monday - chrono::Duration::weeks(1), monday))
+    } else if args.yesterday {
+        Some((today - chrono::Duration::days(1), today))
+    } else {
+        None
+    }
+}
+
+/// `time` relative to `now`, e.g. "3 days ago" or "in 6 hours".
+fn relative_time(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let delta = time - now;
+    let minutes = delta.num_minutes().abs();
+    let (amount, unit) = if minutes < 1 {
+        return "just now".to_string();
+    } else if minutes < 60 {
+        (minutes, "minute")
+    } else if minutes < 48 * 60 {
+        (minutes / 60, "hour")
+    } else {
+        (minutes / (24 * 60), "day")
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    if delta < chrono::Duration::zero() {
+        format!("{} {}{} ago", amount, unit, plural)
+    } else {
+        format!("in {} {}{}", amount, unit, plural)
+    }
+}
+
+/// Rejects start windows that can't contain any event Health still has.
+fn validate_window(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    if end < start {
+        return Err(format!(
+            "the window ends ({}) before it starts ({})",
+            end.to_rfc3339(),
+            start.to_rfc3339()
+        ));
+    }
+    if start > now {
+        return Err(format!(
+            "the window starts in the future ({})",
+            start.to_rfc3339()
+        ));
+    }
+    if start < now - chrono::Duration::days(RETENTION_DAYS) {
+        return Err(format!(
+            "the window starts {} days ago, but AWS Health only keeps events for {} days",
+            (now - start).num_days(),
+            RETENTION_DAYS
+        ));
+    }
+    Ok(())
+}
+
+/// Parses the value of `flag`. Unless `lenient`, a value that isn't a date is
+/// an error; otherwise it's replaced by `default` with a warning.
+fn parse_date_string(
+    flag: &str,
+    date_str: &str,
+    default: DateTime<Utc>,
+    lenient: bool,
+) -> Result<DateTime<Utc>, Box<dyn StdError>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(date_str) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
+        Ok(date) => Ok(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap())),
+        Err(_) if lenient => {
+            eprintln!(
+                "Warning: Could not parse date '{}'. Using default.",
+                date_str
+            );
+            Ok(default)
+        }
+        Err(_) => Err(format!(
+            "invalid {} {:?}: expected YYYY-MM-DD or an RFC 3339 timestamp (pass --lenient-dates to fall back to the default)",
+            flag, date_str
+        )
+        .into()),
+    }
+}
+
+fn parse_output_path(value: &str) -> Result<String, String> {
+    if StrftimeItems::new(value).any(|item| item == Item::Error) {
+        return Err("invalid strftime placeholder (write a literal % as %%)".to_string());
+    }
+    Ok(value.to_string())
+}
+
+fn parse_max_rps(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rps) if rps > 0.0 && rps.is_finite() => Ok(rps),
+        _ => Err("expected a positive number of requests per second".to_string()),
+    }
+}
+
+/// Parses a duration such as `7d`, `48h` or `1w2d`.
+fn parse_duration(value: &str) -> Result<chrono::Duration, String> {
+    let error = || {
+        format!(
+            "expected a duration such as 7d, 48h or 90m, got {:?}",
+            value
+        )
+    };
+    let mut total = chrono::Duration::zero();
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return Err(error());
+    }
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).ok_or_else(error)?;
+        let amount: i64 = rest[..digits].parse().map_err(|_| error())?;
+        let unit = rest[digits..].chars().next().ok_or_else(error)?;
+        let part = match unit {
+            'm' => chrono::Duration::try_minutes(amount),
+            'h' => chrono::Duration::try_hours(amount),
+            'd' => chrono::Duration::try_days(amount),
+            'w' => chrono::Duration::try_weeks(amount),
+            _ => None,
+        };
+        total = total
+            .checked_add(&part.ok_or_else(error)?)
+            .ok_or_else(error)?;
+        rest = &rest[digits + unit.len_utf8()..];
+    }
+    Ok(total)
+}
+
+/// Fetches events from the accounts in `accounts`, the organization or this
+/// account, as `args` asks, passing each to `on_event`.
+async fn fetch_events(
+    args: &Args,
+    config: &aws_config::SdkConfig,
+    client: &Client,
+    accounts: Option<&[accounts::AccountRole]>,
+    start_window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    progress: &Progress,
+    on_event: impl FnMut(&HealthEvent) -> io::Result<()>,
+) -> Result<(), Box<dyn StdError>> {
+    if let Some(accounts) = accounts {
+        accounts::get_account_health_events(
+            config,
+            client,
+            accounts,
+            &args.filter,
+            start_window,
+            args.concurrency,
+            args.account_concurrency,
+            progress,
+            on_event,
+        )
+        .await
+    } else if args.org {
+        org::get_org_health_events(
+            client,
+            &args.filter,
+            start_window,
+            args.concurrency,
+            args.account_concurrency,
+            progress,
+            on_event,
+        )
+        .await?;
+        Ok(())
+    } else {
+        get_health_events(
+            client,
+            &args.filter,
+            start_window,
+            args.concurrency,
+            progress,
+            on_event,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_relative_durations() {
+        assert_eq!(parse_duration("48h"), Ok(chrono::Duration::hours(48)));
+        assert_eq!(parse_duration("1w2d"), Ok(chrono::Duration::days(9)));
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("3y").is_err());
+    }
+
+    #[test]
+    fn default_csv_dialect_matches_the_flags() {
+        let parsed = Args::parse_from(["aws9man"]).csv;
+        let default = output::CsvDialect::default();
+        assert_eq!(parsed.delimiter, default.delimiter);
+        assert_eq!(parsed.quote, default.quote);
+        assert_eq!(parsed.terminator, default.terminator);
+        assert_eq!(parsed.excel_safe, default.excel_safe);
+    }
+
+    #[test]
+    fn splits_next_to_the_report() {
+        let dir = std::env::temp_dir().join(format!("aws9man-split-dir-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let report = dir.join("reports").join("health.csv");
+        let args = |extra: &[&str]| {
+            let mut argv = vec!["aws9man", "--org", "--split-by", "account"];
+            argv.extend_from_slice(extra);
+            Args::parse_from(argv)
+        };
+        let output = report.to_str().unwrap();
+        assert_eq!(
+            args(&["--output", output]).split_dir(&report).unwrap(),
+            dir.join("reports")
+        );
+        assert!(dir.join("reports").is_dir());
+
+        let split_dir = dir.join("accounts");
+        let explicit = args(&[
+            "--output",
+            output,
+            "--split-dir",
+            split_dir.to_str().unwrap(),
+        ]);
+        assert_eq!(explicit.split_dir(&report).unwrap(), split_dir);
+        assert!(split_dir.is_dir());
+
+        let to_stdout = args(&["--output", "-"]);
+        assert_eq!(to_stdout.split_dir(Path::new("-")).unwrap(), PathBuf::new());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn expands_calendar_presets_to_utc_boundaries() {
+        // A Wednesday
+        let now = Utc.with_ymd_and_hms(2024, 3, 6, 15, 30, 0).unwrap();
+        let window = |flag| preset_window(&Args::parse_from(["aws9man", flag]), now);
+
+        assert_eq!(
+            window("--this-month"),
+            Some((Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap(), now))
+        );
+        assert_eq!(
+            window("--last-week"),
+            Some((
+                Utc.with_ymd_and_hms(2024, 2, 26, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 3, 4, 0, 0, 0).unwrap()
+            ))
+        );
+        assert_eq!(
+            window("--yesterday"),
+            Some((
+                Utc.with_ymd_and_hms(2024, 3, 5, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 3, 6, 0, 0, 0).unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn describes_times_relative_to_now() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let hours = chrono::Duration::hours;
+
+        assert_eq!(relative_time(now - hours(72), now), "3 days ago");
+        assert_eq!(relative_time(now + hours(6), now), "in 6 hours");
+        assert_eq!(
+            relative_time(now - chrono::Duration::minutes(1), now),
+            "1 minute ago"
+        );
+        assert_eq!(relative_time(now, now), "just now");
+    }
+
+    #[test]
+    fn rejects_windows_health_has_no_events_for() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let days_ago = |days| now - chrono::Duration::days(days);
+
+        assert!(validate_window(days_ago(10), now, now).is_ok());
+        assert!(validate_window(days_ago(1), days_ago(2), now).is_err());
+        let later = |hours| now + chrono::Duration::hours(hours);
+        assert!(validate_window(later(1), later(2), now).is_err());
+        assert_eq!(
+            validate_window(days_ago(91), now, now),
+            Err(
+                "the window starts 91 days ago, but AWS Health only keeps events for 90 days"
+                    .to_string()
+            )
+        );
+    }
+}
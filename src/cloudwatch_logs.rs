@@ -0,0 +1,193 @@
+//! Writes events to CloudWatch Logs (`--cloudwatch-log-group`), one JSON log
+//! entry per event, for metric filters and Logs Insights.
+//!
+//! Every entry is timestamped with the time of the run: PutLogEvents
+//! rejects entries more than 14 days old, and Health events are often
+//! older than that.
+
+use crate::HealthEvent;
+use crate::batch::batches;
+use crate::output::event_to_json;
+use crate::sigv4::{SignedClient, is_api_error};
+use aws_smithy_json::deserialize::json_token_iter;
+use aws_smithy_json::deserialize::token::expect_document;
+use aws_smithy_json::serialize::JsonObjectWriter;
+use aws_smithy_types::{Document, Number};
+use chrono::Utc;
+use std::error::Error as StdError;
+
+/// `PutLogEvents` takes at most 10,000 entries, of at most 1 MiB in all,
+/// counting 26 bytes of overhead per entry.
+const MAX_BATCH_ENTRIES: usize = 10_000;
+const MAX_BATCH_BYTES: usize = 1024 * 1024;
+const ENTRY_OVERHEAD_BYTES: usize = 26;
+
+pub struct LogsWriter {
+    client: SignedClient,
+    group: String,
+    stream: String,
+}
+
+impl LogsWriter {
+    pub fn new(client: SignedClient, group: String, stream: String) -> Self {
+        LogsWriter {
+            client,
+            group,
+            stream,
+        }
+    }
+
+    /// Writes one entry per event, creating the log group and stream first
+    /// if they don't exist.
+    pub async fn write_events(&self, events: &[HealthEvent]) -> Result<(), Box<dyn StdError>> {
+        let timestamp = Utc::now().timestamp_millis() as u64;
+        let messages = events.iter().map(event_to_json).collect();
+        for batch in batches(messages, MAX_BATCH_ENTRIES, MAX_BATCH_BYTES, |message| {
+            message.len() + ENTRY_OVERHEAD_BYTES
+        }) {
+            match self.put_log_events(&batch, timestamp).await {
+                Err(err) if is_api_error(err.as_ref(), "ResourceNotFoundException") => {
+                    self.create_group_and_stream().await?;
+                    self.put_log_events(&batch, timestamp).await?;
+                }
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    async fn put_log_events(
+        &self,
+        messages: &[String],
+        timestamp: u64,
+    ) -> Result<(), Box<dyn StdError>> {
+        let body = put_log_events_body(&self.group, &self.stream, messages, timestamp);
+        let response = self.call("PutLogEvents", body).await?;
+        if let Some(rejected) = rejected_entries(&response)? {
+            return Err(format!("PutLogEvents rejected entries: {}", rejected).into());
+        }
+        Ok(())
+    }
+
+    async fn create_group_and_stream(&self) -> Result<(), Box<dyn StdError>> {
+        let mut body = String::new();
+        let mut request = JsonObjectWriter::new(&mut body);
+        request.key("logGroupName").string(&self.group);
+        request.finish();
+        match self.call("CreateLogGroup", body).await {
+            Err(err) if is_api_error(err.as_ref(), "ResourceAlreadyExistsException") => {}
+            result => {
+                result?;
+            }
+        }
+
+        let mut body = String::new();
+        let mut request = JsonObjectWriter::new(&mut body);
+        request.key("logGroupName").string(&self.group);
+        request.key("logStreamName").string(&self.stream);
+        request.finish();
+        match self.call("CreateLogStream", body).await {
+            Err(err) if is_api_error(err.as_ref(), "ResourceAlreadyExistsException") => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+
+    async fn call(&self, action: &str, body: String) -> Result<Vec<u8>, Box<dyn StdError>> {
+        let region = self.client.region();
+        self.client
+            .json(
+                &self.client.endpoint("logs", region),
+                "logs",
+                region,
+                "1.1",
+                &format!("Logs_20140328.{}", action),
+                body,
+            )
+            .await
+    }
+}
+
+/// A `PutLogEvents` request writing `messages` at `timestamp`.
+fn put_log_events_body(group: &str, stream: &str, messages: &[String], timestamp: u64) -> String {
+    let mut body = String::new();
+    let mut request = JsonObjectWriter::new(&mut body);
+    request.key("logGroupName").string(group);
+    request.key("logStreamName").string(stream);
+    let mut entries = request.key("logEvents").start_array();
+    for message in messages {
+        let mut entry = entries.value().start_object();
+        entry.key("timestamp").number(Number::PosInt(timestamp));
+        entry.key("message").string(message);
+        entry.finish();
+    }
+    entries.finish();
+    request.finish();
+    body
+}
+
+/// Describes the `rejectedLogEventsInfo` of a response, if it has any.
+fn rejected_entries(response: &[u8]) -> Result<Option<String>, Box<dyn StdError>> {
+    let document = expect_document(&mut json_token_iter(response).peekable())?;
+    let Document::Object(response) = document else {
+        return Err("PutLogEvents returned an unexpected response".into());
+    };
+    let Some(Document::Object(info)) = response.get("rejectedLogEventsInfo") else {
+        return Ok(None);
+    };
+    let reasons: Vec<String> = info
+        .iter()
+        .map(|(reason, index)| match index {
+            Document::Number(index) => format!("{} from entry {}", reason, index.to_f64_lossy()),
+            _ => reason.clone(),
+        })
+        .collect();
+    Ok(Some(reasons.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_rejected_entries() {
+        assert_eq!(
+            rejected_entries(br#"{"nextSequenceToken":"1","rejectedLogEventsInfo":{"tooOldLogEventEndIndex":3}}"#)
+                .unwrap(),
+            Some("tooOldLogEventEndIndex from entry 3".to_string())
+        );
+        assert_eq!(
+            rejected_entries(br#"{"nextSequenceToken":"1"}"#).unwrap(),
+            None
+        );
+
+        // Metric filters such as { $.status = "open" && $.service = "EC2" }
+        // match on the message's fields
+        let event = HealthEvent {
+            service: "EC2".to_string(),
+            status: "open".to_string(),
+            event_type_category: "issue".to_string(),
+            ..Default::default()
+        };
+        let body = put_log_events_body("health", "events", &[event_to_json(&event)], 1);
+        let document = expect_document(&mut json_token_iter(body.as_bytes()).peekable()).unwrap();
+        let message = document.as_object().unwrap()["logEvents"]
+            .as_array()
+            .unwrap()[0]
+            .as_object()
+            .unwrap()["message"]
+            .as_string()
+            .unwrap();
+        for field in [
+            r#""service":"EC2""#,
+            r#""status":"open""#,
+            r#""event_type_category":"issue""#,
+        ] {
+            assert!(
+                message.contains(field),
+                "{} missing from {}",
+                field,
+                message
+            );
+        }
+    }
+}
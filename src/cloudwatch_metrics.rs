@@ -0,0 +1,162 @@
+//! Puts summary metrics of the run's events into a custom CloudWatch
+//! namespace (`--cloudwatch-metrics-namespace`), for alarms: `OpenIssueCount`
+//! and `UpcomingScheduledChanges`, per service and in all.
+
+use crate::HealthEvent;
+use crate::sigv4::SignedClient;
+use chrono::{SecondsFormat, Utc};
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+
+const API_VERSION: &str = "2010-08-01";
+/// `PutMetricData` takes at most 1,000 metrics per call.
+const MAX_BATCH_METRICS: usize = 1000;
+
+/// Checks that `value` can be a custom namespace: `AWS/` is reserved.
+pub fn parse_namespace(value: &str) -> Result<String, String> {
+    if value.is_empty() || value.len() > 255 {
+        return Err("expected 1 to 255 characters".to_string());
+    }
+    if value.starts_with("AWS/") {
+        return Err("namespaces starting with AWS/ are reserved".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// A metric value, with the service it's for or `None` for all of them.
+struct Datum {
+    name: &'static str,
+    service: Option<String>,
+    value: u64,
+}
+
+pub struct MetricsPublisher {
+    client: SignedClient,
+    namespace: String,
+}
+
+impl MetricsPublisher {
+    pub fn new(client: SignedClient, namespace: String) -> Self {
+        MetricsPublisher { client, namespace }
+    }
+
+    /// Puts the metrics for `events`, returning how many were put.
+    pub async fn publish(&self, events: &[HealthEvent]) -> Result<usize, Box<dyn StdError>> {
+        let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let data = data(events);
+        for batch in data.chunks(MAX_BATCH_METRICS) {
+            let mut params = vec![("Namespace".to_string(), self.namespace.clone())];
+            for (index, datum) in batch.iter().enumerate() {
+                let prefix = format!("MetricData.member.{}", index + 1);
+                params.push((format!("{}.MetricName", prefix), datum.name.to_string()));
+                params.push((format!("{}.Value", prefix), datum.value.to_string()));
+                params.push((format!("{}.Unit", prefix), "Count".to_string()));
+                params.push((format!("{}.Timestamp", prefix), timestamp.clone()));
+                if let Some(service) = &datum.service {
+                    params.push((
+                        format!("{}.Dimensions.member.1.Name", prefix),
+                        "Service".to_string(),
+                    ));
+                    params.push((
+                        format!("{}.Dimensions.member.1.Value", prefix),
+                        service.clone(),
+                    ));
+                }
+            }
+            self.client
+                .query(
+                    "monitoring",
+                    self.client.region(),
+                    API_VERSION,
+                    "PutMetricData",
+                    &params,
+                )
+                .await?;
+        }
+        Ok(data.len())
+    }
+}
+
+/// The metrics for `events`. Every service among them gets both metrics,
+/// zero included, so alarms see a value once an issue closes.
+fn data(events: &[HealthEvent]) -> Vec<Datum> {
+    let mut by_service: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+    for event in events {
+        let counts = by_service.entry(&event.service).or_default();
+        match (event.status.as_str(), event.event_type_category.as_str()) {
+            ("open", "issue") => counts.0 += 1,
+            ("upcoming", "scheduledChange") => counts.1 += 1,
+            _ => {}
+        }
+    }
+    let (issues, changes) = by_service
+        .values()
+        .fold((0, 0), |(issues, changes), counts| {
+            (issues + counts.0, changes + counts.1)
+        });
+    let mut data = vec![
+        Datum {
+            name: "OpenIssueCount",
+            service: None,
+            value: issues,
+        },
+        Datum {
+            name: "UpcomingScheduledChanges",
+            service: None,
+            value: changes,
+        },
+    ];
+    for (service, (issues, changes)) in by_service {
+        if service.is_empty() {
+            continue;
+        }
+        data.push(Datum {
+            name: "OpenIssueCount",
+            service: Some(service.to_string()),
+            value: issues,
+        });
+        data.push(Datum {
+            name: "UpcomingScheduledChanges",
+            service: Some(service.to_string()),
+            value: changes,
+        });
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_per_service_and_in_all() {
+        let event = |service: &str, category: &str, status: &str| HealthEvent {
+            service: service.to_string(),
+            event_type_category: category.to_string(),
+            status: status.to_string(),
+            ..Default::default()
+        };
+        let data = data(&[
+            event("EC2", "issue", "open"),
+            event("EC2", "issue", "closed"),
+            event("RDS", "scheduledChange", "upcoming"),
+        ]);
+        let values: Vec<_> = data
+            .iter()
+            .map(|datum| (datum.name, datum.service.as_deref(), datum.value))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                ("OpenIssueCount", None, 1),
+                ("UpcomingScheduledChanges", None, 1),
+                ("OpenIssueCount", Some("EC2"), 1),
+                ("UpcomingScheduledChanges", Some("EC2"), 0),
+                ("OpenIssueCount", Some("RDS"), 0),
+                ("UpcomingScheduledChanges", Some("RDS"), 1),
+            ]
+        );
+        assert!(parse_namespace("AWS/Health").is_err());
+        assert!(parse_namespace("AWS9man/Health").is_ok());
+    }
+}
@@ -0,0 +1,107 @@
+//! Compresses the report as it's written by piping it through the `gzip` or
+//! `zstd` CLI.
+
+use clap::ValueEnum;
+use std::io;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    /// gzip, readable everywhere (requires the gzip CLI)
+    Gzip,
+    /// Zstandard, smaller and faster (requires the zstd CLI)
+    Zstd,
+}
+
+impl Compression {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gz",
+            Compression::Zstd => "zst",
+        }
+    }
+
+    fn program(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+/// A running compressor, writing to its output file.
+pub struct Compressor {
+    compression: Compression,
+    child: Child,
+}
+
+impl Compressor {
+    /// Starts compressing into `out`. Everything written to the returned
+    /// stdin ends up compressed; drop it before calling `finish`.
+    pub fn start(compression: Compression, out: Stdio) -> io::Result<(Self, ChildStdin)> {
+        let program = compression.program();
+        let mut child = Command::new(program)
+            .args(["-c", "-q"])
+            .stdin(Stdio::piped())
+            .stdout(out)
+            .spawn()
+            .map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("could not run {} (is it installed?): {}", program, err),
+                )
+            })?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        Ok((Compressor { compression, child }, stdin))
+    }
+
+    /// Waits for the compressor to write the rest of the file.
+    pub fn finish(mut self) -> io::Result<()> {
+        let status = self.child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "{} exited with {}",
+                self.compression.program(),
+                status
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::io::Write;
+
+    #[test]
+    fn compresses_into_the_output_file() {
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let program = compression.program();
+            if Command::new(program).arg("--version").output().is_err() {
+                eprintln!("skipping {}: it isn't installed", program);
+                continue;
+            }
+            let path = std::env::temp_dir().join(format!(
+                "aws9man-compress-{}.csv.{}",
+                std::process::id(),
+                compression.extension()
+            ));
+            let file = File::create(&path).unwrap();
+            let (compressor, mut stdin) = Compressor::start(compression, file.into()).unwrap();
+            stdin.write_all(b"Timestamp,ARN\r\n").unwrap();
+            drop(stdin);
+            compressor.finish().unwrap();
+
+            let decompressed = Command::new(program)
+                .arg("-dc")
+                .arg(&path)
+                .output()
+                .unwrap();
+            assert!(decompressed.status.success());
+            assert_eq!(decompressed.stdout, b"Timestamp,ARN\r\n");
+            fs::remove_file(&path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Settings from `~/.config/aws9man/config.toml` (or `--config`).
+//!
+//! Top-level keys apply to every run, and `[profiles.NAME]` tables to runs
+//! with `--run-profile NAME`. Keys are the long names of command-line flags
+//! (`service = ["EC2", "RDS"]`, `format = "json"`, `max-rps = "2.5"`), so every
+//! flag can be set in the file. Flags given on the command line or in their
+//! `AWS9MAN_*` environment variables win:
+//!
+//! ```toml
+//! region = ["us-east-1"]
+//!
+//! [profiles.nightly]
+//! org = true
+//! category = ["issue", "scheduledChange"]
+//! format = "jsonl"
+//! ```
+//!
+//! `[[routes]]` tables (see [`crate::routes`]) aren't flags; a profile's
+//! routes replace the top-level ones.
+
+use crate::toml::{self, Value};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command};
+use std::env;
+use std::error::Error as StdError;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// `$XDG_CONFIG_HOME/aws9man/config.toml`, or `~/.config/aws9man/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("aws9man").join("config.toml"))
+}
+
+/// Reads the settings for `run_profile` from `path`: the top-level keys,
+/// overridden by the profile's. A missing file at the default path holds no
+/// settings.
+pub fn read_settings(
+    path: &Path,
+    explicit_path: bool,
+    run_profile: Option<&str>,
+) -> Result<Vec<(String, Value)>, Box<dyn StdError>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound && !explicit_path => {
+            if let Some(run_profile) = run_profile {
+                return Err(format!(
+                    "run profile {:?} not found: {} doesn't exist",
+                    run_profile,
+                    path.display()
+                )
+                .into());
+            }
+            return Ok(Vec::new());
+        }
+        Err(err) => return Err(format!("{}: {}", path.display(), err).into()),
+    };
+    let mut root = toml::parse(&contents).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+    let profiles = match root.remove("profiles") {
+        Some(Value::Table(profiles)) => profiles,
+        Some(_) => return Err(format!("{}: `profiles` must be a table", path.display()).into()),
+        None => Default::default(),
+    };
+    let mut settings: Vec<_> = root.into_iter().collect();
+    if let Some(run_profile) = run_profile {
+        let profile = profiles
+            .get(run_profile)
+            .ok_or_else(|| format!("{}: no [profiles.{}]", path.display(), run_profile))?
+            .as_table()
+            .ok_or_else(|| {
+                format!(
+                    "{}: profiles.{} must be a table",
+                    path.display(),
+                    run_profile
+                )
+            })?;
+        settings.retain(|(key, _)| !profile.contains_key(key));
+        settings.extend(profile.clone());
+    }
+    Ok(settings)
+}
+
+/// Turns `settings` into command-line arguments for `command`, skipping any
+/// flag already given on the command line or in its environment variable
+/// (according to `matches`).
+pub fn to_args(
+    settings: &[(String, Value)],
+    command: &Command,
+    matches: &ArgMatches,
+) -> Result<Vec<OsString>, String> {
+    let mut args = Vec::new();
+    for (key, value) in settings {
+        let long = key.replace('_', "-");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+            .filter(|arg| !matches!(arg.get_id().as_str(), "config" | "run_profile"))
+            .ok_or_else(|| format!("config file: unknown setting `{}`", key))?;
+        if matches!(
+            matches.value_source(arg.get_id().as_str()),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+
+        let flag = OsString::from(format!("--{}", long));
+        if !arg.get_action().takes_values() {
+            match value {
+                Value::Boolean(true) => args.push(flag),
+                Value::Boolean(false) => {}
+                _ => return Err(format!("config file: `{}` must be true or false", key)),
+            }
+            continue;
+        }
+        let values = match value {
+            Value::Array(values) => values.as_slice(),
+            value => std::slice::from_ref(value),
+        };
+        for value in values {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                Value::Integer(value) => value.to_string(),
+                Value::Boolean(value) => value.to_string(),
+                _ => {
+                    return Err(format!(
+                        "config file: `{}` must be a string, integer or array of them",
+                        key
+                    ));
+                }
+            };
+            args.push(flag.clone());
+            args.push(value.into());
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Arg, ArgAction};
+
+    #[test]
+    fn command_line_flags_override_settings() {
+        let command = Command::new("aws9man")
+            .arg(
+                Arg::new("service")
+                    .long("service")
+                    .action(ArgAction::Append),
+            )
+            .arg(Arg::new("format").long("format"))
+            .arg(Arg::new("org").long("org").action(ArgAction::SetTrue));
+        let matches = command
+            .clone()
+            .get_matches_from(["aws9man", "--format", "csv"]);
+        let settings = toml::parse("service = [\"EC2\", \"RDS\"]\nformat = \"json\"\norg = true")
+            .unwrap()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let args = to_args(&settings, &command, &matches).unwrap();
+        assert_eq!(args, ["--org", "--service", "EC2", "--service", "RDS"]);
+
+        let unknown = [("colour".to_string(), Value::Boolean(true))];
+        assert_eq!(
+            to_args(&unknown, &command, &matches).unwrap_err(),
+            "config file: unknown setting `colour`"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Posts each event to the Datadog Events API (`--datadog-api-key`), tagged
+//! so it can be overlaid on dashboards and matched by monitors. Updates of
+//! an event share its ARN as the aggregation key, so they roll up together.
+
+use crate::HealthEvent;
+use crate::http_client::HttpClient;
+use crate::notify::{self, console_url, excerpt, title};
+use aws_smithy_json::serialize::JsonObjectWriter;
+use aws_smithy_types::Number;
+use chrono::{Duration, Utc};
+use std::error::Error as StdError;
+
+/// Datadog's limits on titles, texts and aggregation keys.
+const MAX_TITLE_CHARS: usize = 100;
+const MAX_TEXT_CHARS: usize = 4000;
+const MAX_AGGREGATION_KEY_CHARS: usize = 100;
+
+/// Datadog rejects events dated more than this long ago.
+const MAX_AGE_HOURS: i64 = 18;
+
+pub struct DatadogNotifier {
+    http: HttpClient,
+    events_url: String,
+    api_key: String,
+}
+
+impl DatadogNotifier {
+    /// Posts to the Events API at `api_url`, which depends on the Datadog
+    /// site.
+    pub fn new(http: HttpClient, api_url: &str, api_key: String) -> Self {
+        DatadogNotifier {
+            http,
+            events_url: format!("{}/api/v1/events", api_url.trim_end_matches('/')),
+            api_key,
+        }
+    }
+
+    /// Posts one Datadog event per event, in order.
+    pub async fn notify(&self, events: &[HealthEvent]) -> Result<(), Box<dyn StdError>> {
+        for event in events {
+            notify::post(
+                &self.http,
+                "Datadog",
+                &self.events_url,
+                &[
+                    ("content-type", "application/json"),
+                    ("dd-api-key", &self.api_key),
+                ],
+                body(event),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+fn body(event: &HealthEvent) -> String {
+    let mut body = String::new();
+    let mut object = JsonObjectWriter::new(&mut body);
+    object
+        .key("title")
+        .string(&excerpt(&title(event), MAX_TITLE_CHARS));
+    let text = format!(
+        "%%%\n{}\n\n[View in the AWS Health Dashboard]({})\n%%%",
+        excerpt(&event.detail, MAX_TEXT_CHARS - 200),
+        console_url(event)
+    );
+    object.key("text").string(&text);
+    object
+        .key("aggregation_key")
+        .string(aggregation_key(&event.arn));
+    object.key("alert_type").string(alert_type(event));
+    object.key("source_type_name").string("aws9man");
+    // The time of the update, when Datadog still accepts it
+    if let Some(updated) = event.last_updated_time
+        && updated > Utc::now() - Duration::hours(MAX_AGE_HOURS)
+    {
+        object
+            .key("date_happened")
+            .number(Number::PosInt(updated.timestamp() as u64));
+    }
+
+    let mut tags = object.key("tags").start_array();
+    for (key, value) in [
+        ("aws_service", &event.service),
+        ("region", &event.region),
+        ("event_status", &event.status),
+        ("event_category", &event.event_type_category),
+        ("event_type_code", &event.event_type_code),
+    ] {
+        if !value.is_empty() {
+            tags.value()
+                .string(&format!("{}:{}", key, value.to_lowercase()));
+        }
+    }
+    for account in &event.affected_accounts {
+        tags.value().string(&format!("aws_account:{}", account));
+    }
+    tags.finish();
+    object.finish();
+    body
+}
+
+/// The end of the ARN if it's too long, since its unique ID comes last.
+fn aggregation_key(arn: &str) -> &str {
+    match arn.char_indices().rev().nth(MAX_AGGREGATION_KEY_CHARS - 1) {
+        Some((start, _)) => &arn[start..],
+        None => arn,
+    }
+}
+
+fn alert_type(event: &HealthEvent) -> &'static str {
+    match (event.event_type_category.as_str(), event.status.as_str()) {
+        (_, "closed") => "success",
+        ("issue", _) => "error",
+        ("scheduledChange" | "investigation", _) => "warning",
+        _ => "info",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_and_alert_types() {
+        let arn = format!("arn:aws:health:us-east-1::event/EC2/{}", "X".repeat(100));
+        assert_eq!(aggregation_key(&arn), "X".repeat(100));
+        assert_eq!(aggregation_key("arn:short"), "arn:short");
+        let event = |category: &str, status: &str| HealthEvent {
+            event_type_category: category.to_string(),
+            status: status.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(alert_type(&event("issue", "open")), "error");
+        assert_eq!(alert_type(&event("issue", "closed")), "success");
+        assert_eq!(alert_type(&event("scheduledChange", "upcoming")), "warning");
+    }
+}
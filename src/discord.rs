@@ -0,0 +1,187 @@
+//! Posts a message with an embed per event to a Discord webhook
+//! (`--discord-webhook`).
+
+use crate::HealthEvent;
+use crate::http_client::HttpClient;
+use crate::notify::{self, console_url, excerpt, title};
+use aws_smithy_json::serialize::{JsonArrayWriter, JsonObjectWriter};
+use aws_smithy_types::Number;
+use chrono::SecondsFormat;
+use std::error::Error as StdError;
+
+/// Descriptions are cut to this many characters; the link has the rest.
+const MAX_DESCRIPTION_CHARS: usize = 600;
+
+/// Discord cuts embed titles at 256 characters and field values at 1,024.
+const MAX_TITLE_CHARS: usize = 256;
+const MAX_FIELD_CHARS: usize = 1024;
+
+pub struct DiscordNotifier {
+    http: HttpClient,
+    webhook: String,
+}
+
+impl DiscordNotifier {
+    pub fn new(http: HttpClient, webhook: String) -> Self {
+        DiscordNotifier { http, webhook }
+    }
+
+    /// Posts one message per event, in order.
+    pub async fn notify(&self, events: &[HealthEvent]) -> Result<(), Box<dyn StdError>> {
+        for event in events {
+            notify::post(
+                &self.http,
+                "Discord",
+                &self.webhook,
+                &[("content-type", "application/json")],
+                message(event),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// The embed's side bar: red for issues, orange for scheduled changes and
+/// blue for account notifications.
+fn color(event: &HealthEvent) -> u64 {
+    match event.event_type_category.as_str() {
+        "issue" => 0xd13212,
+        "scheduledChange" => 0xff9900,
+        _ => 0x0073bb,
+    }
+}
+
+/// The event as a message with one embed.
+fn message(event: &HealthEvent) -> String {
+    let mut message = String::new();
+    let mut object = JsonObjectWriter::new(&mut message);
+    object.key("username").string("AWS Health");
+    // Descriptions can't ping anyone
+    let mut allowed_mentions = object.key("allowed_mentions").start_object();
+    allowed_mentions.key("parse").start_array().finish();
+    allowed_mentions.finish();
+
+    let mut embeds = object.key("embeds").start_array();
+    let mut embed = embeds.value().start_object();
+    embed
+        .key("title")
+        .string(&excerpt(&title(event), MAX_TITLE_CHARS));
+    embed.key("url").string(&console_url(event));
+    embed
+        .key("description")
+        .string(&excerpt(&event.detail, MAX_DESCRIPTION_CHARS));
+    embed.key("color").number(Number::PosInt(color(event)));
+    if let Some(start) = event.start_time {
+        embed
+            .key("timestamp")
+            .string(&start.to_rfc3339_opts(SecondsFormat::Secs, true));
+    }
+
+    let mut fields = embed.key("fields").start_array();
+    field(&mut fields, "Service", &event.service);
+    field(&mut fields, "Region", &event.region);
+    field(&mut fields, "Status", &event.status);
+    field(&mut fields, "Category", &event.event_type_category);
+    field(&mut fields, "Start", &event.timestamp);
+    if !event.affected_accounts.is_empty() {
+        field(&mut fields, "Accounts", &event.accounts_text());
+    }
+    fields.finish();
+
+    let mut footer = embed.key("footer").start_object();
+    footer.key("text").string(&event.event_type_code);
+    footer.finish();
+    embed.finish();
+    embeds.finish();
+    object.finish();
+    message
+}
+
+fn field(fields: &mut JsonArrayWriter<'_>, name: &str, value: &str) {
+    let mut object = fields.value().start_object();
+    object.key("name").string(name);
+    // Discord rejects empty values
+    let value = if value.is_empty() { "-" } else { value };
+    object.key("value").string(&excerpt(value, MAX_FIELD_CHARS));
+    object.key("inline").boolean(true);
+    object.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_smithy_json::deserialize::json_token_iter;
+    use aws_smithy_json::deserialize::token::expect_document;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn builds_embeds() {
+        let event = HealthEvent {
+            timestamp: "2024-03-06 09:00:00".to_string(),
+            start_time: Some(Utc.with_ymd_and_hms(2024, 3, 6, 9, 0, 0).unwrap()),
+            arn: "arn:aws:health:us-east-1::event/EC2/AWS_EC2_OPERATIONAL_ISSUE/1".to_string(),
+            service: "EC2".to_string(),
+            region: "us-east-1".to_string(),
+            status: "open".to_string(),
+            event_type_code: "AWS_EC2_OPERATIONAL_ISSUE".to_string(),
+            event_type_category: "issue".to_string(),
+            detail: "Elevated errors @everyone".to_string(),
+            affected_accounts: (0..100).map(|i| format!("{:012}", i)).collect(),
+            ..Default::default()
+        };
+        let message = message(&event);
+        let document =
+            expect_document(&mut json_token_iter(message.as_bytes()).peekable()).unwrap();
+        let object = document.as_object().unwrap();
+        assert_eq!(object["username"].as_string(), Some("AWS Health"));
+        let allowed_mentions = object["allowed_mentions"].as_object().unwrap();
+        assert!(allowed_mentions["parse"].as_array().unwrap().is_empty());
+
+        let embed = object["embeds"].as_array().unwrap()[0].as_object().unwrap();
+        assert_eq!(
+            embed["title"].as_string(),
+            Some("EC2 operational issue (us-east-1)")
+        );
+        assert_eq!(
+            embed["description"].as_string(),
+            Some("Elevated errors @everyone")
+        );
+        assert_eq!(embed["color"].as_number(), Some(&Number::PosInt(0xd13212)));
+        assert_eq!(embed["timestamp"].as_string(), Some("2024-03-06T09:00:00Z"));
+        let footer = embed["footer"].as_object().unwrap();
+        assert_eq!(
+            footer["text"].as_string(),
+            Some("AWS_EC2_OPERATIONAL_ISSUE")
+        );
+
+        let fields: Vec<(&str, &str)> = embed["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| {
+                let field = field.as_object().unwrap();
+                (
+                    field["name"].as_string().unwrap(),
+                    field["value"].as_string().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            fields[..5],
+            [
+                ("Service", "EC2"),
+                ("Region", "us-east-1"),
+                ("Status", "open"),
+                ("Category", "issue"),
+                ("Start", "2024-03-06 09:00:00"),
+            ]
+        );
+        // 100 account IDs are 1,398 characters
+        let (name, accounts) = fields[5];
+        assert_eq!(name, "Accounts");
+        assert!(accounts.starts_with("000000000000, 000000000001"));
+        assert!(accounts.ends_with('…'));
+        assert!(accounts.chars().count() <= MAX_FIELD_CHARS);
+    }
+}
@@ -0,0 +1,176 @@
+//! Active endpoint discovery for the Health API.
+//!
+//! Health runs active-passive across two regions. AWS publishes the active one
+//! as the CNAME of `global.health.amazonaws.com` (e.g.
+//! `health.us-east-1.amazonaws.com`), and recommends resolving it so clients
+//! keep working when the active region fails over. The CNAME is looked up
+//! with a single query to the system resolver; if anything goes wrong the
+//! partition's usual Health region is used instead.
+
+use crate::partition::Partition;
+use aws_types::region::Region;
+use std::fs;
+use std::time::{Duration, SystemTime};
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+const TYPE_CNAME: u16 = 5;
+const CLASS_IN: u16 = 1;
+
+/// The region currently serving `partition`'s Health API.
+pub async fn active_health_region(partition: Partition) -> Region {
+    let global_name = match partition {
+        Partition::Aws => "global.health.amazonaws.com",
+        Partition::AwsCn => "global.health.amazonaws.com.cn",
+        Partition::AwsUsGov => return partition.health_region(),
+    };
+    match lookup_cname(global_name)
+        .await
+        .as_deref()
+        .and_then(region_of)
+    {
+        Some(region) => Region::new(region.to_string()),
+        None => partition.health_region(),
+    }
+}
+
+/// The region in a regional Health endpoint name.
+fn region_of(endpoint: &str) -> Option<&str> {
+    let mut labels = endpoint.split('.');
+    match (labels.next(), labels.next()) {
+        (Some("health"), Some(region)) if !region.is_empty() => Some(region),
+        _ => None,
+    }
+}
+
+async fn lookup_cname(name: &str) -> Option<String> {
+    let nameserver = fs::read_to_string(RESOLV_CONF)
+        .ok()?
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("nameserver"))
+        .map(|address| address.trim().to_string())?;
+
+    let socket = UdpSocket::bind(if nameserver.contains(':') {
+        "[::]:0"
+    } else {
+        "0.0.0.0:0"
+    })
+    .await
+    .ok()?;
+    socket.connect((nameserver.as_str(), 53)).await.ok()?;
+
+    // Not security sensitive: the ID only pairs the answer with the query
+    let id = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .ok()?
+        .subsec_nanos() as u16;
+    socket.send(&query(id, name)).await.ok()?;
+    let mut response = [0; 512];
+    let len = timeout(QUERY_TIMEOUT, socket.recv(&mut response))
+        .await
+        .ok()?
+        .ok()?;
+    parse_cname(id, &response[..len])
+}
+
+/// A recursive DNS query for `name`'s CNAME record.
+fn query(id: u16, name: &str) -> Vec<u8> {
+    let mut message = Vec::with_capacity(name.len() + 18);
+    message.extend_from_slice(&id.to_be_bytes());
+    // Recursion desired, one question
+    message.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.split('.') {
+        message.push(label.len() as u8);
+        message.extend_from_slice(label.as_bytes());
+    }
+    message.push(0);
+    message.extend_from_slice(&TYPE_CNAME.to_be_bytes());
+    message.extend_from_slice(&CLASS_IN.to_be_bytes());
+    message
+}
+
+/// The target of the first CNAME record in the answer to query `id`.
+fn parse_cname(id: u16, message: &[u8]) -> Option<String> {
+    let u16_at = |offset: usize| {
+        Some(u16::from_be_bytes([
+            *message.get(offset)?,
+            *message.get(offset + 1)?,
+        ]))
+    };
+    if u16_at(0)? != id {
+        return None;
+    }
+    let questions = u16_at(4)?;
+    let answers = u16_at(6)?;
+
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = read_name(message, offset)?.1 + 4;
+    }
+    for _ in 0..answers {
+        offset = read_name(message, offset)?.1;
+        let record_type = u16_at(offset)?;
+        let data_len = u16_at(offset + 8)? as usize;
+        let data = offset + 10;
+        if record_type == TYPE_CNAME {
+            return read_name(message, data).map(|(name, _)| name);
+        }
+        offset = data + data_len;
+    }
+    None
+}
+
+/// Reads a possibly compressed domain name at `offset`, returning it and the
+/// offset just past it.
+fn read_name(message: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Bounds the number of compression pointers followed, so a malicious
+    // response can't loop forever
+    for _ in 0..message.len() {
+        let len = *message.get(offset)? as usize;
+        if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(offset + 1)));
+        }
+        if len & 0xc0 == 0xc0 {
+            let pointer = ((len & 0x3f) << 8) | *message.get(offset + 1)? as usize;
+            end.get_or_insert(offset + 2);
+            offset = pointer;
+            continue;
+        }
+        let label = message.get(offset + 1..offset + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        offset += 1 + len;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_active_region_from_a_cname_answer() {
+        let mut response = query(0x1234, "global.health.amazonaws.com");
+        // Response, recursion available, one answer
+        response[2..8].copy_from_slice(&[0x81, 0x80, 0, 1, 0, 1]);
+        // Name: pointer to the question, then CNAME IN with a TTL of 60
+        response.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1, 0, 0, 0, 60]);
+        let mut target = Vec::new();
+        for label in ["health", "us-east-2"] {
+            target.push(label.len() as u8);
+            target.extend_from_slice(label.as_bytes());
+        }
+        // The rest of the name, `amazonaws.com`, is a pointer into the question
+        target.extend_from_slice(&[0xc0, 12 + 14]);
+        response.extend_from_slice(&(target.len() as u16).to_be_bytes());
+        response.extend_from_slice(&target);
+
+        let cname = parse_cname(0x1234, &response).unwrap();
+        assert_eq!(cname, "health.us-east-2.amazonaws.com");
+        assert_eq!(region_of(&cname), Some("us-east-2"));
+        assert_eq!(parse_cname(0x4321, &response), None);
+    }
+}
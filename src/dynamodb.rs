@@ -0,0 +1,255 @@
+//! Upserts events into a DynamoDB table (`--dynamodb-table`) keyed by ARN,
+//! so repeated runs keep one item per event.
+//!
+//! The table needs a string partition key named `arn`. Items also carry
+//! `service`, `status` and `start_time` (RFC 3339, so it sorts) as
+//! top-level strings for indexes such as `service`/`start_time` or
+//! `status`/`start_time`. Attributes that would be empty are left out, as
+//! index keys can't be empty strings.
+
+use crate::HealthEvent;
+use crate::batch::batches;
+use crate::sigv4::SignedClient;
+use aws_smithy_json::deserialize::json_token_iter;
+use aws_smithy_json::deserialize::token::expect_document;
+use aws_smithy_json::serialize::{JsonObjectWriter, JsonValueWriter};
+use aws_smithy_types::Document;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// `BatchWriteItem` takes at most 25 items, of at most 16 MiB in all.
+const MAX_BATCH_ITEMS: usize = 25;
+const MAX_BATCH_BYTES: usize = 16 * 1024 * 1024;
+/// Items DynamoDB leaves unprocessed (when throttled, say) are sent again
+/// this many times.
+const MAX_ATTEMPTS: usize = 5;
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+pub struct DynamoDbWriter {
+    client: SignedClient,
+    table: String,
+    region: String,
+}
+
+impl DynamoDbWriter {
+    /// Writes to `table`, a table name or ARN.
+    pub fn new(client: SignedClient, table: String) -> Self {
+        let region = match table.split(':').collect::<Vec<_>>()[..] {
+            ["arn", _, "dynamodb", region, ..] => region.to_string(),
+            _ => client.region().to_string(),
+        };
+        DynamoDbWriter {
+            client,
+            table,
+            region,
+        }
+    }
+
+    /// Puts one item per event ARN, replacing any item already there. When
+    /// an ARN comes up more than once, the last update wins. Returns the
+    /// number of items written.
+    pub async fn upsert_events(&self, events: &[HealthEvent]) -> Result<usize, Box<dyn StdError>> {
+        // A batch can't hold two writes of the same key
+        let mut latest: BTreeMap<&str, &HealthEvent> = BTreeMap::new();
+        for event in events {
+            match latest.get(event.arn.as_str()) {
+                Some(seen) if seen.last_updated_time > event.last_updated_time => {}
+                _ => {
+                    latest.insert(&event.arn, event);
+                }
+            }
+        }
+        let updated_at = Utc::now();
+        let items: Vec<(String, String)> = latest
+            .into_values()
+            .map(|event| (event.arn.clone(), item(event, updated_at)))
+            .collect();
+        let count = items.len();
+        for batch in batches(items, MAX_BATCH_ITEMS, MAX_BATCH_BYTES, |(_, item)| {
+            item.len()
+        }) {
+            self.write_batch(batch).await?;
+        }
+        Ok(count)
+    }
+
+    /// Writes a batch of `(arn, item)` pairs, retrying unprocessed items.
+    async fn write_batch(&self, mut items: Vec<(String, String)>) -> Result<(), Box<dyn StdError>> {
+        for attempt in 0..MAX_ATTEMPTS {
+            if attempt > 0 {
+                sleep(RETRY_DELAY * attempt as u32).await;
+            }
+            let writes: Vec<String> = items
+                .iter()
+                .map(|(_, item)| format!("{{\"PutRequest\":{{\"Item\":{}}}}}", item))
+                .collect();
+            let mut table = String::new();
+            JsonValueWriter::new(&mut table).string(&self.table);
+            let body = format!("{{\"RequestItems\":{{{}:[{}]}}}}", table, writes.join(","));
+
+            let endpoint = self.client.endpoint("dynamodb", &self.region);
+            let response = self
+                .client
+                .json(
+                    &endpoint,
+                    "dynamodb",
+                    &self.region,
+                    "1.0",
+                    "DynamoDB_20120810.BatchWriteItem",
+                    body,
+                )
+                .await?;
+            let unprocessed = unprocessed_arns(&response)?;
+            if unprocessed.is_empty() {
+                return Ok(());
+            }
+            items.retain(|(arn, _)| unprocessed.contains(arn));
+        }
+        Err(format!(
+            "BatchWriteItem left {} items unprocessed after {} attempts",
+            items.len(),
+            MAX_ATTEMPTS
+        )
+        .into())
+    }
+}
+
+/// The event as a DynamoDB item, in the typed JSON `BatchWriteItem` takes.
+fn item(event: &HealthEvent, updated_at: DateTime<Utc>) -> String {
+    let mut item = String::new();
+    let mut object = JsonObjectWriter::new(&mut item);
+    for (key, value) in [
+        ("arn", event.arn.as_str()),
+        ("service", &event.service),
+        ("status", &event.status),
+        ("region", &event.region),
+        ("event_type_code", &event.event_type_code),
+        ("event_type_category", &event.event_type_category),
+        ("detail", &event.detail),
+    ] {
+        if !value.is_empty() {
+            string_attribute(&mut object, key, value);
+        }
+    }
+    for (key, time) in [
+        ("start_time", event.start_time),
+        ("end_time", event.end_time),
+        ("last_updated_time", event.last_updated_time),
+        ("updated_at", Some(updated_at)),
+    ] {
+        if let Some(time) = time {
+            string_attribute(&mut object, key, &time_string(time));
+        }
+    }
+
+    let mut entities = object.key("affected_entities").start_object();
+    let mut list = entities.key("L").start_array();
+    for entity in &event.affected_entities {
+        let mut value = list.value().start_object();
+        let mut map = value.key("M").start_object();
+        string_attribute(&mut map, "value", &entity.value);
+        for (key, field) in [
+            ("account_id", &entity.account_id),
+            ("arn", &entity.arn),
+            ("status", &entity.status),
+        ] {
+            if let Some(field) = field {
+                string_attribute(&mut map, key, field);
+            }
+        }
+        if let Some(time) = entity.last_updated_time {
+            string_attribute(&mut map, "last_updated_time", &time_string(time));
+        }
+        map.finish();
+        value.finish();
+    }
+    list.finish();
+    entities.finish();
+
+    let mut accounts = object.key("affected_accounts").start_object();
+    let mut list = accounts.key("L").start_array();
+    for account in &event.affected_accounts {
+        let mut value = list.value().start_object();
+        value.key("S").string(account);
+        value.finish();
+    }
+    list.finish();
+    accounts.finish();
+
+    let mut names = object.key("account_names").start_object();
+    let mut map = names.key("M").start_object();
+    for (account, name) in &event.account_names {
+        string_attribute(&mut map, account, name);
+    }
+    map.finish();
+    names.finish();
+
+    object.finish();
+    item
+}
+
+fn string_attribute(object: &mut JsonObjectWriter<'_>, key: &str, value: &str) {
+    let mut attribute = object.key(key).start_object();
+    attribute.key("S").string(value);
+    attribute.finish();
+}
+
+fn time_string(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// The ARNs of the items a `BatchWriteItem` response lists as unprocessed.
+fn unprocessed_arns(response: &[u8]) -> Result<Vec<String>, Box<dyn StdError>> {
+    let document = expect_document(&mut json_token_iter(response).peekable())?;
+    let Document::Object(response) = document else {
+        return Err("BatchWriteItem returned an unexpected response".into());
+    };
+    let Some(Document::Object(tables)) = response.get("UnprocessedItems") else {
+        return Ok(Vec::new());
+    };
+    let mut arns = Vec::new();
+    for writes in tables.values() {
+        let Document::Array(writes) = writes else {
+            continue;
+        };
+        for write in writes {
+            let arn = field(write, "PutRequest")
+                .and_then(|put| field(put, "Item"))
+                .and_then(|item| field(item, "arn"))
+                .and_then(|arn| field(arn, "S"));
+            if let Some(Document::String(arn)) = arn {
+                arns.push(arn.clone());
+            }
+        }
+    }
+    Ok(arns)
+}
+
+fn field<'a>(document: &'a Document, key: &str) -> Option<&'a Document> {
+    match document {
+        Document::Object(object) => object.get(key),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_items_to_retry() {
+        let response = br#"{"UnprocessedItems":{"health":[{"PutRequest":{"Item":{"arn":{"S":"arn:aws:health:us-east-1::event/EC2/X/1"},"service":{"S":"EC2"}}}}]}}"#;
+        assert_eq!(
+            unprocessed_arns(response).unwrap(),
+            ["arn:aws:health:us-east-1::event/EC2/X/1"]
+        );
+        assert!(
+            unprocessed_arns(br#"{"UnprocessedItems":{}}"#)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! Email notifications: a digest of a run's new events, or a mail per
+//! event, as MIME messages with an HTML body and a plain-text alternative.
+
+use crate::HealthEvent;
+use crate::notify::{console_url, excerpt, title};
+use crate::output::escape_xml;
+use aws_smithy_types::base64;
+use chrono::Utc;
+use clap::Args;
+use std::fmt::Write as _;
+
+/// Descriptions in digests are cut to this many characters.
+const MAX_DIGEST_DESCRIPTION_CHARS: usize = 300;
+
+// Who gets notification mail, and how much. (A doc comment here would
+// replace the program description in --help.)
+#[derive(Args, Debug)]
+pub struct EmailArgs {
+    /// Sender of notification mail, e.g. "AWS Health <health@example.com>"
+    #[arg(long, env = "AWS9MAN_EMAIL_FROM", value_name = "ADDRESS")]
+    pub email_from: Option<String>,
+
+    /// Recipients of notification mail (repeatable or comma-separated)
+    #[arg(
+        long,
+        env = "AWS9MAN_EMAIL_TO",
+        value_name = "ADDRESS",
+        value_delimiter = ','
+    )]
+    pub email_to: Vec<String>,
+
+    /// Send a mail per new event instead of one digest per run
+    #[arg(long, env = "AWS9MAN_EMAIL_PER_EVENT")]
+    pub email_per_event: bool,
+
+    /// Attach the report file to the digest
+    #[arg(
+        long,
+        env = "AWS9MAN_EMAIL_ATTACH_REPORT",
+        conflicts_with = "email_per_event"
+    )]
+    pub email_attach_report: bool,
+}
+
+impl EmailArgs {
+    /// The mail to send `to` about `events`: a digest, or one per event.
+    pub fn messages(&self, to: &[String], events: &[HealthEvent]) -> Vec<Message> {
+        let from = self.email_from.as_deref().unwrap_or_default();
+        match self.email_per_event {
+            true => events
+                .iter()
+                .map(|event| Message::single(from, to, event))
+                .collect(),
+            false => vec![Message::digest(from, to, events)],
+        }
+    }
+}
+
+pub struct Attachment {
+    pub name: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+pub struct Message {
+    pub from: String,
+    pub to: Vec<String>,
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+    pub attachments: Vec<Attachment>,
+}
+
+impl Message {
+    /// A digest of `events`, one row each.
+    pub fn digest(from: &str, to: &[String], events: &[HealthEvent]) -> Self {
+        let subject = match events {
+            [event] => format!("AWS Health: {}", title(event)),
+            _ => format!("AWS Health: {} new or updated events", events.len()),
+        };
+        let mut text = String::new();
+        let mut html = String::from(
+            "<table border=\"1\" cellpadding=\"4\" cellspacing=\"0\">\n\
+             <tr><th>Start</th><th>Service</th><th>Region</th><th>Status</th>\
+             <th>Category</th><th>Event</th><th>Description</th></tr>\n",
+        );
+        for event in events {
+            let description = excerpt(&event.detail, MAX_DIGEST_DESCRIPTION_CHARS);
+            let _ = writeln!(
+                text,
+                "{}\n  Start: {}\n  Status: {}\n  {}\n  {}\n",
+                title(event),
+                event.timestamp,
+                event.status,
+                description,
+                console_url(event)
+            );
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+                 <td><a href=\"{}\">{}</a></td><td>{}</td></tr>",
+                escape_xml(&event.timestamp),
+                escape_xml(&event.service),
+                escape_xml(&event.region),
+                escape_xml(&event.status),
+                escape_xml(&event.event_type_category),
+                escape_xml(&console_url(event)),
+                escape_xml(&event.event_type_code),
+                escape_xml(&description)
+            );
+        }
+        html.push_str("</table>\n");
+        Message {
+            from: from.to_string(),
+            to: to.to_vec(),
+            subject,
+            text,
+            html: page(&html),
+            attachments: Vec::new(),
+        }
+    }
+
+    /// A mail about one event, with its whole description.
+    pub fn single(from: &str, to: &[String], event: &HealthEvent) -> Self {
+        let mut facts = vec![
+            ("Service", event.service.clone()),
+            ("Region", event.region.clone()),
+            ("Status", event.status.clone()),
+            ("Category", event.event_type_category.clone()),
+            ("Start", event.timestamp.clone()),
+            ("Event ARN", event.arn.clone()),
+        ];
+        if !event.affected_accounts.is_empty() {
+            facts.push(("Accounts", event.accounts_text()));
+        }
+        if !event.affected_entities.is_empty() {
+            facts.push(("Entities", event.entities_text()));
+        }
+
+        let mut text = String::new();
+        let mut html = format!("<h2>{}</h2>\n<table>\n", escape_xml(&title(event)));
+        for (label, value) in &facts {
+            let _ = writeln!(text, "{}: {}", label, value);
+            let _ = writeln!(
+                html,
+                "<tr><th align=\"left\">{}</th><td>{}</td></tr>",
+                label,
+                escape_xml(value)
+            );
+        }
+        let _ = write!(text, "\n{}\n\n{}\n", event.detail, console_url(event));
+        let _ = write!(
+            html,
+            "</table>\n<p style=\"white-space: pre-wrap\">{}</p>\n\
+             <p><a href=\"{}\">View in the AWS Health Dashboard</a></p>\n",
+            escape_xml(&event.detail),
+            escape_xml(&console_url(event))
+        );
+        Message {
+            from: from.to_string(),
+            to: to.to_vec(),
+            subject: format!("AWS Health: {}", title(event)),
+            text,
+            html: page(&html),
+            attachments: Vec::new(),
+        }
+    }
+
+    /// The message in MIME format, with CRLF line endings.
+    pub fn to_mime(&self) -> String {
+        let boundary = format!("aws9man-{}", Utc::now().timestamp_nanos_opt().unwrap_or(0));
+        let mut mime = String::new();
+        let _ = write!(
+            mime,
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\n\
+             Message-ID: <{}@aws9man>\r\nMIME-Version: 1.0\r\n",
+            self.from,
+            self.to.join(", "),
+            encode_header(&self.subject),
+            Utc::now().to_rfc2822(),
+            boundary
+        );
+
+        let alternative = format!("{}-alt", boundary);
+        let mut body = format!(
+            "Content-Type: multipart/alternative; boundary=\"{}\"\r\n\r\n",
+            alternative
+        );
+        for (content_type, content) in [
+            ("text/plain; charset=utf-8", &self.text),
+            ("text/html; charset=utf-8", &self.html),
+        ] {
+            let _ = write!(
+                body,
+                "--{}\r\n{}",
+                alternative,
+                part(content_type, content.as_bytes())
+            );
+        }
+        let _ = write!(body, "--{}--\r\n", alternative);
+
+        if self.attachments.is_empty() {
+            mime.push_str(&body);
+            return mime;
+        }
+        let _ = write!(
+            mime,
+            "Content-Type: multipart/mixed; boundary=\"{}\"\r\n\r\n--{}\r\n{}",
+            boundary, boundary, body
+        );
+        for attachment in &self.attachments {
+            let _ = write!(
+                mime,
+                "--{}\r\nContent-Disposition: attachment; filename=\"{}\"\r\n{}",
+                boundary,
+                attachment.name.replace(['"', '\\', '\r', '\n'], "_"),
+                part(&attachment.content_type, &attachment.data)
+            );
+        }
+        let _ = write!(mime, "--{}--\r\n", boundary);
+        mime
+    }
+}
+
+/// A base64-encoded MIME part, so long lines and any characters survive.
+fn part(content_type: &str, data: &[u8]) -> String {
+    let encoded = base64::encode(data);
+    let mut part = format!(
+        "Content-Type: {}\r\nContent-Transfer-Encoding: base64\r\n\r\n",
+        content_type
+    );
+    for line in encoded.as_bytes().chunks(76) {
+        part.push_str(std::str::from_utf8(line).expect("base64 is ASCII"));
+        part.push_str("\r\n");
+    }
+    part
+}
+
+/// Encodes a header value as an RFC 2047 encoded word if it isn't plain ASCII.
+fn encode_header(value: &str) -> String {
+    if value.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+        return value.to_string();
+    }
+    format!("=?UTF-8?B?{}?=", base64::encode(value))
+}
+
+fn page(body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html><body style=\"font-family: sans-serif\">\n{}</body></html>\n",
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_subjects_and_escapes_html() {
+        assert_eq!(encode_header("AWS Health: EC2"), "AWS Health: EC2");
+        assert_eq!(encode_header("Zürich"), "=?UTF-8?B?WsO8cmljaA==?=");
+        assert_eq!(
+            escape_xml("<a href=\"x\" title='y'>R&D</a>"),
+            "&lt;a href=&quot;x&quot; title=&#39;y&#39;&gt;R&amp;D&lt;/a&gt;"
+        );
+    }
+}
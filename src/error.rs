@@ -0,0 +1,82 @@
+//! The error fetching events can fail with.
+
+use aws_sdk_health::error::{BuildError, SdkError};
+use std::error::Error as StdError;
+use std::{fmt, io};
+
+/// Why fetching events failed. It displays as, and has the same sources as,
+/// the error it wraps, so messages read the same whichever it is.
+#[non_exhaustive]
+pub enum Error {
+    /// A Health API call failed
+    Api(Box<dyn StdError + Send + Sync>),
+    /// A request couldn't be built, e.g. from an invalid filter
+    Build(BuildError),
+    /// Handling an event failed, e.g. writing it out
+    Io(io::Error),
+}
+
+impl Error {
+    fn inner(&self) -> &(dyn StdError + 'static) {
+        match self {
+            Error::Api(err) => err.as_ref(),
+            Error::Build(err) => err,
+            Error::Io(err) => err,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.inner(), f)
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.inner(), f)
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.inner().source()
+    }
+}
+
+impl<E, R> From<SdkError<E, R>> for Error
+where
+    E: StdError + Send + Sync + 'static,
+    R: fmt::Debug + Send + Sync + 'static,
+{
+    fn from(err: SdkError<E, R>) -> Self {
+        Error::Api(Box::new(err))
+    }
+}
+
+impl From<BuildError> for Error {
+    fn from(err: BuildError) -> Self {
+        Error::Build(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_boxable<E: StdError + Send + Sync + 'static>(_: &E) {}
+
+    #[test]
+    fn reads_as_the_wrapped_error() {
+        let err = Error::from(io::Error::other("disk full"));
+        assert_boxable(&err);
+        assert_eq!(err.to_string(), "disk full");
+        assert!(err.source().is_none());
+    }
+}
@@ -0,0 +1,195 @@
+//! Re-emits events onto an EventBridge bus (`--eventbridge-bus`).
+//!
+//! The detail has the shape of the `aws.health` events Health delivers to
+//! EventBridge itself, so rules written for those (matching on
+//! `detail.service` or `detail.eventTypeCategory`, say) match these too.
+//! Only the source differs, since `aws.*` sources are reserved for AWS.
+
+use crate::HealthEvent;
+use crate::batch::batches;
+use crate::sigv4::SignedClient;
+use aws_smithy_json::deserialize::json_token_iter;
+use aws_smithy_json::deserialize::token::expect_document;
+use aws_smithy_json::serialize::JsonObjectWriter;
+use aws_smithy_types::{Document, Number};
+use chrono::{DateTime, Utc};
+use std::error::Error as StdError;
+
+/// `PutEvents` takes at most 10 entries, of at most 256 KiB in all.
+const MAX_BATCH_ENTRIES: usize = 10;
+const MAX_BATCH_BYTES: usize = 256 * 1024;
+
+pub struct EventBridgePublisher {
+    client: SignedClient,
+    bus: String,
+    source: String,
+    detail_type: String,
+}
+
+impl EventBridgePublisher {
+    /// Publishes to `bus`, a bus name or ARN.
+    pub fn new(client: SignedClient, bus: String, source: String, detail_type: String) -> Self {
+        EventBridgePublisher {
+            client,
+            bus,
+            source,
+            detail_type,
+        }
+    }
+
+    /// Puts one entry per event, or in org mode one per event and affected
+    /// account as Health does.
+    pub async fn put_events(&self, events: &[HealthEvent]) -> Result<usize, Box<dyn StdError>> {
+        let mut entries = Vec::new();
+        for event in events {
+            if event.affected_accounts.is_empty() {
+                entries.push(self.entry(event, None));
+            }
+            for account in &event.affected_accounts {
+                entries.push(self.entry(&event.for_account(account), Some(account)));
+            }
+        }
+        let count = entries.len();
+        for batch in batches(entries, MAX_BATCH_ENTRIES, MAX_BATCH_BYTES, String::len) {
+            self.put_batch(&batch).await?;
+        }
+        Ok(count)
+    }
+
+    /// A `PutEvents` entry as JSON.
+    fn entry(&self, event: &HealthEvent, account: Option<&str>) -> String {
+        let mut entry = String::new();
+        let mut object = JsonObjectWriter::new(&mut entry);
+        object.key("EventBusName").string(&self.bus);
+        object.key("Source").string(&self.source);
+        object.key("DetailType").string(&self.detail_type);
+        object.key("Detail").string(&detail(event, account));
+        let mut resources = object.key("Resources").start_array();
+        resources.value().string(&event.arn);
+        resources.finish();
+        if let Some(time) = event.last_updated_time.or(event.start_time) {
+            object
+                .key("Time")
+                .number(Number::PosInt(time.timestamp().max(0) as u64));
+        }
+        object.finish();
+        entry
+    }
+
+    async fn put_batch(&self, entries: &[String]) -> Result<(), Box<dyn StdError>> {
+        let body = format!("{{\"Entries\":[{}]}}", entries.join(","));
+        // A bus ARN names its region; a bus name is in the configured one
+        let region = match self.bus.split(':').collect::<Vec<_>>()[..] {
+            ["arn", _, "events", region, ..] => region,
+            _ => self.client.region(),
+        };
+        let endpoint = self.client.endpoint("events", region);
+        let response = self
+            .client
+            .json(
+                &endpoint,
+                "events",
+                region,
+                "1.1",
+                "AWSEvents.PutEvents",
+                body,
+            )
+            .await?;
+        match first_failure(&response)? {
+            Some(failure) => Err(format!("PutEvents failed: {}", failure).into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The event as the detail of an `aws.health` event.
+fn detail(event: &HealthEvent, account: Option<&str>) -> String {
+    let mut detail = String::new();
+    let mut object = JsonObjectWriter::new(&mut detail);
+    object.key("eventArn").string(&event.arn);
+    object.key("service").string(&event.service);
+    object.key("eventTypeCode").string(&event.event_type_code);
+    object
+        .key("eventTypeCategory")
+        .string(&event.event_type_category);
+    object.key("eventRegion").string(&event.region);
+    for (key, time) in [
+        ("startTime", event.start_time),
+        ("endTime", event.end_time),
+        ("lastUpdatedTime", event.last_updated_time),
+    ] {
+        if let Some(time) = time {
+            object.key(key).string(&health_time(time));
+        }
+    }
+    let mut descriptions = object.key("eventDescription").start_array();
+    let mut description = descriptions.value().start_object();
+    description.key("language").string("en_US");
+    description.key("latestDescription").string(&event.detail);
+    description.finish();
+    descriptions.finish();
+    let mut entities = object.key("affectedEntities").start_array();
+    for entity in &event.affected_entities {
+        let mut object = entities.value().start_object();
+        object.key("entityValue").string(&entity.value);
+        if let Some(status) = &entity.status {
+            object.key("status").string(status);
+        }
+        object.finish();
+    }
+    entities.finish();
+    if let Some(account) = account {
+        object.key("affectedAccount").string(account);
+    }
+    object.finish();
+    detail
+}
+
+/// Times as Health writes them in events, e.g. `Wed, 05 Jun 2024 12:00:00 GMT`.
+fn health_time(time: DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Describes the first entry EventBridge didn't accept, if any.
+fn first_failure(response: &[u8]) -> Result<Option<String>, Box<dyn StdError>> {
+    let document = expect_document(&mut json_token_iter(response).peekable())?;
+    let Document::Object(response) = document else {
+        return Err("PutEvents returned an unexpected response".into());
+    };
+    let Some(Document::Array(entries)) = response.get("Entries") else {
+        return Ok(None);
+    };
+    Ok(entries.iter().find_map(|entry| {
+        let Document::Object(entry) = entry else {
+            return None;
+        };
+        let Some(Document::String(code)) = entry.get("ErrorCode") else {
+            return None;
+        };
+        match entry.get("ErrorMessage") {
+            Some(Document::String(message)) => Some(format!("{}: {}", code, message)),
+            _ => Some(code.clone()),
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn formats_times_and_failures_like_health() {
+        let time = Utc.with_ymd_and_hms(2024, 6, 5, 12, 0, 0).unwrap();
+        assert_eq!(health_time(time), "Wed, 05 Jun 2024 12:00:00 GMT");
+        assert_eq!(
+            first_failure(br#"{"FailedEntryCount":1,"Entries":[{"EventId":"1"},{"ErrorCode":"InternalFailure","ErrorMessage":"try again"}]}"#)
+                .unwrap(),
+            Some("InternalFailure: try again".to_string())
+        );
+        assert_eq!(
+            first_failure(br#"{"FailedEntryCount":0,"Entries":[{"EventId":"1"}]}"#).unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,415 @@
+use crate::HealthEvent;
+use aws_sdk_health::types::{
+    DateTimeRange, Event, EventFilter, EventScopeCode, EventStatusCode, EventTypeCategory,
+    OrganizationEvent, OrganizationEventFilter,
+};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use clap::{Args, ValueEnum};
+use regex_lite::Regex;
+
+// Flags that narrow down which events are fetched. (A doc comment here would
+// replace the program description in --help.)
+#[derive(Args, Debug, Default)]
+pub struct FilterArgs {
+    /// AWS Region to report on (repeatable or comma-separated); the first one
+    /// also picks the partition (commercial, GovCloud or China) to call
+    #[arg(
+        long = "region",
+        env = "AWS9MAN_REGION",
+        value_name = "REGION",
+        value_delimiter = ','
+    )]
+    pub regions: Vec<String>,
+
+    /// Only include events for this AWS service, e.g. EC2 (repeatable or
+    /// comma-separated)
+    #[arg(
+        long = "service",
+        env = "AWS9MAN_SERVICE",
+        value_name = "SERVICE",
+        value_delimiter = ','
+    )]
+    pub services: Vec<String>,
+
+    /// Only include events whose type code matches, e.g. AWS_EC2_*_SCHEDULED
+    /// (repeatable or comma-separated, `*` and `?` globs are matched
+    /// client-side)
+    #[arg(
+        long = "event-type-code",
+        env = "AWS9MAN_EVENT_TYPE_CODE",
+        value_name = "CODE",
+        value_delimiter = ','
+    )]
+    pub event_type_codes: Vec<String>,
+
+    /// Only include events with this status (repeatable or comma-separated)
+    #[arg(
+        long = "status",
+        env = "AWS9MAN_STATUS",
+        value_enum,
+        value_name = "STATUS",
+        value_delimiter = ','
+    )]
+    pub statuses: Vec<Status>,
+
+    /// Only include events in this category (repeatable or comma-separated)
+    #[arg(
+        long = "category",
+        env = "AWS9MAN_CATEGORY",
+        value_enum,
+        value_name = "CATEGORY",
+        value_delimiter = ','
+    )]
+    pub categories: Vec<Category>,
+
+    /// Only include events in this availability zone, e.g. us-east-1a
+    /// (repeatable or comma-separated)
+    #[arg(
+        long = "az",
+        env = "AWS9MAN_AZ",
+        value_name = "AZ",
+        value_delimiter = ','
+    )]
+    pub availability_zones: Vec<String>,
+
+    /// Only include events affecting this entity ARN (repeatable or
+    /// comma-separated)
+    #[arg(
+        long = "entity-arn",
+        env = "AWS9MAN_ENTITY_ARN",
+        value_name = "ARN",
+        value_delimiter = ','
+    )]
+    pub entity_arns: Vec<String>,
+
+    /// Only include events affecting this entity, e.g. an instance ID (repeatable)
+    #[arg(
+        long = "entity-value",
+        env = "AWS9MAN_ENTITY_VALUE",
+        value_name = "VALUE"
+    )]
+    pub entity_values: Vec<String>,
+
+    /// Only include events with this scope (matched client-side)
+    #[arg(long, env = "AWS9MAN_SCOPE", value_enum)]
+    pub scope: Option<Scope>,
+
+    /// Only include events that ended on or after this date in UTC
+    /// (YYYY-MM-DD), or an RFC 3339 timestamp
+    #[arg(long, env = "AWS9MAN_ENDED_AFTER", value_name = "DATE", value_parser = parse_utc_date)]
+    pub ended_after: Option<DateTime<Utc>>,
+
+    /// Only include events that ended before this date in UTC
+    /// (YYYY-MM-DD), or an RFC 3339 timestamp
+    #[arg(long, env = "AWS9MAN_ENDED_BEFORE", value_name = "DATE", value_parser = parse_utc_date)]
+    pub ended_before: Option<DateTime<Utc>>,
+
+    /// Only include events last updated on or after this date in UTC
+    /// (YYYY-MM-DD), or an RFC 3339 timestamp
+    #[arg(long, env = "AWS9MAN_UPDATED_AFTER", value_name = "DATE", value_parser = parse_utc_date)]
+    pub updated_after: Option<DateTime<Utc>>,
+
+    /// Only include events last updated before this date in UTC
+    /// (YYYY-MM-DD), or an RFC 3339 timestamp
+    #[arg(long, env = "AWS9MAN_UPDATED_BEFORE", value_name = "DATE", value_parser = parse_utc_date)]
+    pub updated_before: Option<DateTime<Utc>>,
+
+    /// Only include events whose description or affected entities match this
+    /// regular expression
+    #[arg(long, env = "AWS9MAN_GREP", value_name = "REGEX", value_parser = Regex::new)]
+    pub grep: Option<Regex>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Status {
+    Open,
+    Upcoming,
+    Closed,
+}
+
+impl From<Status> for EventStatusCode {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Open => EventStatusCode::Open,
+            Status::Upcoming => EventStatusCode::Upcoming,
+            Status::Closed => EventStatusCode::Closed,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Category {
+    #[value(name = "issue")]
+    Issue,
+    #[value(name = "scheduledChange")]
+    ScheduledChange,
+    #[value(name = "accountNotification")]
+    AccountNotification,
+    #[value(name = "investigation")]
+    Investigation,
+}
+
+impl From<Category> for EventTypeCategory {
+    fn from(category: Category) -> Self {
+        match category {
+            Category::Issue => EventTypeCategory::Issue,
+            Category::ScheduledChange => EventTypeCategory::ScheduledChange,
+            Category::AccountNotification => EventTypeCategory::AccountNotification,
+            Category::Investigation => EventTypeCategory::Investigation,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Scope {
+    /// Events that affect resources in this account
+    AccountSpecific,
+    /// Service events that aren't tied to any account's resources
+    Public,
+}
+
+impl From<Scope> for EventScopeCode {
+    fn from(scope: Scope) -> Self {
+        match scope {
+            Scope::AccountSpecific => EventScopeCode::AccountSpecific,
+            Scope::Public => EventScopeCode::Public,
+        }
+    }
+}
+
+impl FilterArgs {
+    /// Whether any end or last-updated time range was requested.
+    pub fn has_time_ranges(&self) -> bool {
+        self.ended_after.is_some()
+            || self.ended_before.is_some()
+            || self.updated_after.is_some()
+            || self.updated_before.is_some()
+    }
+
+    /// Builds the `describe_events` filter, optionally restricted to events
+    /// starting in `start_window`.
+    pub fn event_filter(
+        &self,
+        start_window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    ) -> EventFilter {
+        let start_times = start_window.map(|(from, to)| vec![date_range(Some(from), Some(to))]);
+        let end_times = (self.ended_after.is_some() || self.ended_before.is_some())
+            .then(|| vec![date_range(self.ended_after, self.ended_before)]);
+        let last_updated_times = (self.updated_after.is_some() || self.updated_before.is_some())
+            .then(|| vec![date_range(self.updated_after, self.updated_before)]);
+
+        EventFilter::builder()
+            .set_start_times(start_times)
+            .set_end_times(end_times)
+            .set_last_updated_times(last_updated_times)
+            .set_regions(non_empty(&self.regions))
+            .set_services(non_empty(&self.services))
+            .set_event_type_codes(self.exact_event_type_codes())
+            .set_event_status_codes(non_empty(&self.statuses))
+            .set_event_type_categories(non_empty(&self.categories))
+            .set_availability_zones(non_empty(&self.availability_zones))
+            .set_entity_arns(non_empty(&self.entity_arns))
+            .set_entity_values(non_empty(&self.entity_values))
+            .build()
+    }
+
+    /// Builds the `describe_events_for_organization` filter. Unlike
+    /// `describe_events`, it takes a single range per time field and has no
+    /// availability zone filter.
+    pub fn org_event_filter(
+        &self,
+        start_window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    ) -> OrganizationEventFilter {
+        let start_time = start_window.map(|(from, to)| date_range(Some(from), Some(to)));
+        let end_time = (self.ended_after.is_some() || self.ended_before.is_some())
+            .then(|| date_range(self.ended_after, self.ended_before));
+        let last_updated_time = (self.updated_after.is_some() || self.updated_before.is_some())
+            .then(|| date_range(self.updated_after, self.updated_before));
+
+        OrganizationEventFilter::builder()
+            .set_start_time(start_time)
+            .set_end_time(end_time)
+            .set_last_updated_time(last_updated_time)
+            .set_regions(non_empty(&self.regions))
+            .set_services(non_empty(&self.services))
+            .set_event_type_codes(self.exact_event_type_codes())
+            .set_event_status_codes(non_empty(&self.statuses))
+            .set_event_type_categories(non_empty(&self.categories))
+            .set_entity_arns(non_empty(&self.entity_arns))
+            .set_entity_values(non_empty(&self.entity_values))
+            .build()
+    }
+
+    /// Checks the filters the API can't apply for us, before any details are
+    /// fetched for `event`.
+    pub fn matches(&self, event: &Event) -> bool {
+        self.matches_summary(event.event_type_code(), event.event_scope_code())
+    }
+
+    /// Same as [`FilterArgs::matches`], for organization events.
+    pub fn matches_org(&self, event: &OrganizationEvent) -> bool {
+        self.matches_summary(event.event_type_code(), event.event_scope_code())
+    }
+
+    fn matches_summary(&self, code: Option<&str>, scope: Option<&EventScopeCode>) -> bool {
+        let code = code.unwrap_or_default();
+        let code_matches = self.event_type_codes.is_empty()
+            || self
+                .event_type_codes
+                .iter()
+                .any(|pattern| glob_match(pattern, code));
+        let scope_matches = match self.scope {
+            Some(wanted) => scope == Some(&wanted.into()),
+            None => true,
+        };
+        code_matches && scope_matches
+    }
+
+    /// Checks the filters that need an event's details and entities.
+    pub fn matches_details(&self, event: &HealthEvent) -> bool {
+        match &self.grep {
+            Some(regex) => {
+                regex.is_match(&event.detail)
+                    || event
+                        .affected_entities
+                        .iter()
+                        .any(|entity| regex.is_match(&entity.value))
+            }
+            None => true,
+        }
+    }
+
+    /// Event type codes can only be sent to the API when none of them are globs,
+    /// otherwise every event has to be fetched and matched client-side.
+    fn exact_event_type_codes(&self) -> Option<Vec<String>> {
+        if self.event_type_codes.iter().any(|code| is_glob(code)) {
+            None
+        } else {
+            non_empty(&self.event_type_codes)
+        }
+    }
+}
+
+fn date_range(from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> DateTimeRange {
+    let to_smithy =
+        |time: DateTime<Utc>| aws_smithy_types::DateTime::from_millis(time.timestamp_millis());
+    DateTimeRange::builder()
+        .set_from(from.map(to_smithy))
+        .set_to(to.map(to_smithy))
+        .build()
+}
+
+/// Parses an RFC 3339 timestamp, or a `YYYY-MM-DD` flag value as midnight
+/// UTC, as --from-utc does.
+pub fn parse_utc_date(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+        .map_err(|err| {
+            format!(
+                "expected a YYYY-MM-DD date or an RFC 3339 timestamp: {}",
+                err
+            )
+        })
+}
+
+/// Converts a repeatable flag into an optional filter value.
+fn non_empty<T: Clone + Into<U>, U>(values: &[T]) -> Option<Vec<U>> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().cloned().map(Into::into).collect())
+    }
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Matches `value` against a glob `pattern` where `*` matches any run of
+/// characters and `?` matches exactly one.
+fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+    let (mut p, mut v) = (0, 0);
+    // Position of the last `*` seen and the value position it was tried at
+    let mut backtrack = None;
+
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, v));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match backtrack {
+                Some((star, tried)) => {
+                    p = star + 1;
+                    v = tried + 1;
+                    backtrack = Some((star, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dates_and_timestamps() {
+        assert_eq!(
+            parse_utc_date("2024-05-01").unwrap().to_rfc3339(),
+            "2024-05-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_utc_date("2024-05-01T16:30:00+02:00")
+                .unwrap()
+                .to_rfc3339(),
+            "2024-05-01T14:30:00+00:00"
+        );
+        assert!(parse_utc_date("2024-05-01 14:30").is_err());
+    }
+
+    #[test]
+    fn matches_globs() {
+        assert!(glob_match("AWS_EC2_*", "AWS_EC2_OPERATIONAL_ISSUE"));
+        assert!(glob_match("*_ISSUE", "AWS_EC2_OPERATIONAL_ISSUE"));
+        assert!(glob_match(
+            "AWS_*_MAINTENANCE_*",
+            "AWS_RDS_MAINTENANCE_SCHEDULED"
+        ));
+        assert!(glob_match("AWS_EC?_*", "AWS_EC2_OPERATIONAL_ISSUE"));
+        assert!(!glob_match("AWS_EC?", "AWS_EC"));
+        assert!(glob_match("AWS_**_ISSUE", "AWS_EC2_OPERATIONAL_ISSUE"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "AWS_EC2_OPERATIONAL_ISSUE"));
+        assert!(!glob_match("?", ""));
+        // The first `_ISSUE` candidate fails, the `*` has to take more
+        assert!(glob_match("*_ISSUE", "AWS_ISSUE_X_ISSUE"));
+        assert!(!glob_match("*_ISSUE", "AWS_ISSUE_X_ISSUES"));
+        assert!(!glob_match("AWS_*_ISSUE", "AWS_EC2_OPERATIONAL_ISSUE_"));
+    }
+
+    #[test]
+    fn sends_only_exact_event_type_codes() {
+        let filter = |codes: &[&str]| FilterArgs {
+            event_type_codes: codes.iter().map(|code| code.to_string()).collect(),
+            ..Default::default()
+        };
+        let exact = filter(&["AWS_EC2_OPERATIONAL_ISSUE"]).event_filter(None);
+        assert_eq!(exact.event_type_codes(), ["AWS_EC2_OPERATIONAL_ISSUE"]);
+        // One glob and the API can't narrow by code, so it's left to `matches`
+        let globbed = filter(&["AWS_EC2_OPERATIONAL_ISSUE", "AWS_RDS_*"]).event_filter(None);
+        assert!(globbed.event_type_codes().is_empty());
+    }
+}
@@ -0,0 +1,182 @@
+//! Delivers events to a Firehose stream (`--firehose-stream`), one
+//! newline-terminated JSON record per event so the objects Firehose writes
+//! to S3 are JSON Lines.
+
+use crate::HealthEvent;
+use crate::batch::batches;
+use crate::output::event_to_json;
+use crate::sigv4::SignedClient;
+use aws_smithy_json::deserialize::json_token_iter;
+use aws_smithy_json::deserialize::token::expect_document;
+use aws_smithy_json::serialize::JsonObjectWriter;
+use aws_smithy_types::{Document, base64};
+use std::error::Error as StdError;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// `PutRecordBatch` takes at most 500 records, of at most 4 MiB in all.
+const MAX_BATCH_RECORDS: usize = 500;
+const MAX_BATCH_BYTES: usize = 4 * 1024 * 1024;
+/// Records Firehose fails to take (when throttled, say) are sent again this
+/// many times.
+const MAX_ATTEMPTS: usize = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+pub struct FirehoseSender {
+    client: SignedClient,
+    stream: String,
+    region: String,
+}
+
+impl FirehoseSender {
+    /// Sends to `stream`, a stream name or ARN.
+    pub fn new(client: SignedClient, stream: &str) -> Self {
+        let (stream, region) = match stream.split(':').collect::<Vec<_>>()[..] {
+            ["arn", _, "firehose", region, _, resource] => (
+                resource.trim_start_matches("deliverystream/").to_string(),
+                region.to_string(),
+            ),
+            _ => (stream.to_string(), client.region().to_string()),
+        };
+        FirehoseSender {
+            client,
+            stream,
+            region,
+        }
+    }
+
+    pub fn stream(&self) -> &str {
+        &self.stream
+    }
+
+    pub async fn send_events(&self, events: &[HealthEvent]) -> Result<(), Box<dyn StdError>> {
+        let records = events
+            .iter()
+            .map(|event| event_to_json(event) + "\n")
+            .collect();
+        for batch in batches(records, MAX_BATCH_RECORDS, MAX_BATCH_BYTES, String::len) {
+            self.send_batch(batch).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends a batch, retrying the records Firehose reports as failed.
+    async fn send_batch(&self, mut records: Vec<String>) -> Result<(), Box<dyn StdError>> {
+        let mut last_failure = String::new();
+        for attempt in 0..MAX_ATTEMPTS {
+            if attempt > 0 {
+                sleep(RETRY_DELAY * attempt as u32).await;
+            }
+            let body = request_body(&self.stream, &records);
+            let endpoint = self.client.endpoint("firehose", &self.region);
+            let response = self
+                .client
+                .json(
+                    &endpoint,
+                    "firehose",
+                    &self.region,
+                    "1.1",
+                    "Firehose_20150804.PutRecordBatch",
+                    body,
+                )
+                .await?;
+            let failures = failures(&response)?;
+            if failures.is_empty() {
+                return Ok(());
+            }
+            let failed: Vec<usize> = failures.iter().map(|(index, _)| *index).collect();
+            last_failure = failures.into_iter().next_back().unwrap_or_default().1;
+            records = records
+                .into_iter()
+                .enumerate()
+                .filter(|(index, _)| failed.contains(index))
+                .map(|(_, record)| record)
+                .collect();
+        }
+        Err(format!(
+            "PutRecordBatch failed for {} records after {} attempts: {}",
+            records.len(),
+            MAX_ATTEMPTS,
+            last_failure
+        )
+        .into())
+    }
+}
+
+/// A `PutRecordBatch` request putting `records` into `stream`.
+fn request_body(stream: &str, records: &[String]) -> String {
+    let mut body = String::new();
+    let mut request = JsonObjectWriter::new(&mut body);
+    request.key("DeliveryStreamName").string(stream);
+    let mut list = request.key("Records").start_array();
+    for record in records {
+        let mut object = list.value().start_object();
+        object.key("Data").string(&base64::encode(record));
+        object.finish();
+    }
+    list.finish();
+    request.finish();
+    body
+}
+
+/// The positions of the records Firehose didn't take, with why.
+fn failures(response: &[u8]) -> Result<Vec<(usize, String)>, Box<dyn StdError>> {
+    let document = expect_document(&mut json_token_iter(response).peekable())?;
+    let Document::Object(response) = document else {
+        return Err("PutRecordBatch returned an unexpected response".into());
+    };
+    let Some(Document::Array(responses)) = response.get("RequestResponses") else {
+        return Ok(Vec::new());
+    };
+    let mut failures = Vec::new();
+    for (index, response) in responses.iter().enumerate() {
+        if let Document::Object(response) = response
+            && let Some(Document::String(code)) = response.get("ErrorCode")
+        {
+            let message = match response.get("ErrorMessage") {
+                Some(Document::String(message)) => format!("{}: {}", code, message),
+                _ => code.clone(),
+            };
+            failures.push((index, message));
+        }
+    }
+    Ok(failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_records_to_retry() {
+        let response = br#"{"FailedPutCount":1,"Encrypted":false,"RequestResponses":[{"RecordId":"a"},{"ErrorCode":"ServiceUnavailableException","ErrorMessage":"Slow down."},{"RecordId":"c"}]}"#;
+        assert_eq!(
+            failures(response).unwrap(),
+            [(1, "ServiceUnavailableException: Slow down.".to_string())]
+        );
+
+        let event = HealthEvent {
+            service: "RDS".to_string(),
+            status: "upcoming".to_string(),
+            event_type_category: "scheduledChange".to_string(),
+            ..Default::default()
+        };
+        let body = request_body("health", &[event_to_json(&event) + "\n"]);
+        let document = expect_document(&mut json_token_iter(body.as_bytes()).peekable()).unwrap();
+        let data = document.as_object().unwrap()["Records"].as_array().unwrap()[0]
+            .as_object()
+            .unwrap()["Data"]
+            .as_string()
+            .unwrap();
+        let record = String::from_utf8(base64::decode(data).unwrap()).unwrap();
+        assert!(record.ends_with("}\n"));
+        for field in [
+            r#""service":"RDS""#,
+            r#""status":"upcoming""#,
+            r#""event_type_category":"scheduledChange""#,
+            r#""last_updated_time":null"#,
+        ] {
+            assert!(record.contains(field), "{} missing from {}", field, record);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Plain HTTP(S) requests, through the configured proxy, for the sinks that
+//! post to non-AWS services.
+
+use crate::proxy::ProxyHttpClient;
+use aws_smithy_http_client::{Connector, tls};
+use aws_smithy_runtime_api::client::http::{HttpConnector, SharedHttpConnector};
+use aws_smithy_runtime_api::http::Request;
+use aws_smithy_types::body::SdkBody;
+use aws_smithy_types::byte_stream::ByteStream;
+use aws_smithy_types::error::display::DisplayErrorContext;
+use std::error::Error as StdError;
+
+pub struct HttpClient {
+    connector: SharedHttpConnector,
+}
+
+impl HttpClient {
+    pub fn new(proxy: Option<&ProxyHttpClient>) -> Self {
+        let connector = match proxy {
+            Some(proxy) => SharedHttpConnector::new(proxy.clone()),
+            None => SharedHttpConnector::new(
+                Connector::builder()
+                    .tls_provider(tls::Provider::Rustls(
+                        tls::rustls_provider::CryptoMode::AwsLc,
+                    ))
+                    .build(),
+            ),
+        };
+        HttpClient { connector }
+    }
+
+    /// Sends a request, returning the response whatever its status.
+    pub async fn send(
+        &self,
+        method: &str,
+        uri: &str,
+        headers: &[(&str, &str)],
+        body: Vec<u8>,
+    ) -> Result<http::Response<Vec<u8>>, Box<dyn StdError>> {
+        let mut request = http::Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let request = Request::try_from(request.body(SdkBody::from(body))?)?;
+
+        // Connector errors only describe themselves in their sources
+        let response = self
+            .connector
+            .call(request)
+            .await
+            .map_err(|err| DisplayErrorContext(err).to_string())?;
+        let mut builder = http::Response::builder().status(response.status().as_u16());
+        for (name, value) in response.headers() {
+            builder = builder.header(name, value);
+        }
+        let body = ByteStream::new(response.into_body())
+            .collect()
+            .await?
+            .to_vec();
+        Ok(builder.body(body)?)
+    }
+}
@@ -0,0 +1,709 @@
+//! Fetches AWS Health events for an account or an organization.
+//!
+//! The `aws9man` command line tool is a thin wrapper around [`cli::main`].
+//! To fetch events from another program, create a client with
+//! [`health_client`], fetch events with [`query_events`] or
+//! [`query_org_events`], or as a stream with [`fetch_events`] or
+//! [`fetch_org_events`], and write them out with [`output::EventWriter`].
+//! Fetching fails with [`Error`], which is `Send` and `Sync` like the
+//! errors of other libraries.
+//! Events implement `serde`'s `Serialize` and `Deserialize`, for formats
+//! the tool doesn't write itself.
+
+use aws_config::SdkConfig;
+use aws_sdk_health::Client;
+use aws_sdk_health::config::http::HttpResponse;
+use aws_sdk_health::error::SdkError;
+use aws_sdk_health::operation::describe_events::DescribeEventsError;
+use aws_sdk_health::types::{EntityFilter, Event};
+use chrono::{DateTime, Utc};
+use filter::FilterArgs;
+use futures_util::{Stream, StreamExt, TryStreamExt, future, stream};
+use partition::Partition;
+use progress::Progress;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::pin::pin;
+use std::sync::LazyLock;
+
+mod accounts;
+mod batch;
+mod check;
+pub mod cli;
+mod cloudwatch_logs;
+mod cloudwatch_metrics;
+mod compress;
+mod config;
+mod datadog;
+mod discord;
+mod discovery;
+mod dynamodb;
+mod email;
+mod error;
+mod eventbridge;
+pub mod filter;
+mod firehose;
+mod http_client;
+mod loki;
+mod metrics;
+mod mfa;
+mod notify;
+mod opensearch;
+mod org;
+mod organizations;
+mod otlp;
+pub mod output;
+mod pagerduty;
+mod partition;
+#[cfg(feature = "postgres")]
+mod postgres;
+mod progress;
+mod proxy;
+mod rate_limit;
+mod routes;
+mod s3;
+mod ses;
+mod sigv4;
+mod slack;
+mod smtp;
+mod sns;
+mod splunk;
+mod sqlite;
+mod sqs;
+mod sso;
+mod state;
+mod stats;
+mod statsd;
+mod teams;
+mod telegram;
+mod toml;
+mod tz;
+mod webhook;
+mod zabbix;
+
+pub use error::Error;
+
+/// `describe_event_details` accepts at most this many event ARNs per call
+const DETAILS_BATCH_SIZE: usize = 10;
+
+/// An event, with its details and affected entities.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HealthEvent {
+    pub timestamp: String,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub last_updated_time: Option<DateTime<Utc>>,
+    pub arn: String,
+    pub service: String,
+    /// The event's region, or `global`
+    pub region: String,
+    /// The Availability Zone, for events confined to one
+    pub availability_zone: Option<String>,
+    /// The event's status: open, closed or upcoming
+    pub status: String,
+    pub event_type_code: String,
+    pub event_type_category: String,
+    /// Who the event is about: PUBLIC, ACCOUNT_SPECIFIC or NONE
+    pub event_scope_code: String,
+    pub detail: String,
+    pub affected_entities: Vec<AffectedEntity>,
+    /// Member accounts affected by the event, only known in org mode
+    pub affected_accounts: Vec<String>,
+    /// Names of the affected accounts, where known
+    pub account_names: BTreeMap<String, String>,
+}
+
+impl HealthEvent {
+    fn entity_values(&self) -> Vec<&str> {
+        self.affected_entities
+            .iter()
+            .map(|entity| entity.value.as_str())
+            .collect()
+    }
+
+    /// One copy of the event per affected entity, each listing only that
+    /// entity. An event without entities is kept as it is.
+    fn per_entity(&self) -> Vec<HealthEvent> {
+        if self.affected_entities.is_empty() {
+            return vec![self.clone()];
+        }
+        self.affected_entities
+            .iter()
+            .map(|entity| HealthEvent {
+                affected_entities: vec![entity.clone()],
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// The event as seen by one member account: only that account and its
+    /// entities are kept.
+    fn for_account(&self, account: &str) -> HealthEvent {
+        HealthEvent {
+            affected_entities: self
+                .affected_entities
+                .iter()
+                .filter(|entity| entity.account_id.as_deref() == Some(account))
+                .cloned()
+                .collect(),
+            affected_accounts: vec![account.to_string()],
+            account_names: self
+                .account_names
+                .iter()
+                .filter(|(id, _)| id.as_str() == account)
+                .map(|(id, name)| (id.clone(), name.clone()))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Tags an event fetched with `account`'s own credentials as affecting
+    /// that account.
+    fn in_account(&self, account: &str) -> HealthEvent {
+        HealthEvent {
+            affected_entities: self
+                .affected_entities
+                .iter()
+                .map(|entity| AffectedEntity {
+                    account_id: Some(account.to_string()),
+                    ..entity.clone()
+                })
+                .collect(),
+            affected_accounts: vec![account.to_string()],
+            ..self.clone()
+        }
+    }
+
+    /// Names every affected account found in `names`.
+    fn with_account_names(&self, names: &HashMap<String, String>) -> HealthEvent {
+        HealthEvent {
+            account_names: self
+                .affected_accounts
+                .iter()
+                .filter_map(|id| Some((id.clone(), names.get(id)?.clone())))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Formats the event's timestamp with `format`, in `zone` if given.
+    fn with_time_format(&self, format: &tz::TimeFormat, zone: Option<&tz::Zone>) -> HealthEvent {
+        HealthEvent {
+            timestamp: match self.start_time {
+                Some(time) => format.format(time, zone),
+                None => self.timestamp.clone(),
+            },
+            ..self.clone()
+        }
+    }
+
+    /// The names of the affected accounts, in order, falling back to the ID
+    /// for accounts without a known name.
+    fn account_name_list(&self) -> Vec<&str> {
+        self.affected_accounts
+            .iter()
+            .map(|id| self.account_names.get(id).unwrap_or(id).as_str())
+            .collect()
+    }
+
+    /// All affected accounts on one line, with their names where known.
+    fn accounts_text(&self) -> String {
+        self.affected_accounts
+            .iter()
+            .map(|id| match self.account_names.get(id) {
+                Some(name) => format!("{} ({})", name, id),
+                None => id.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// All affected entities on one line, for table-like outputs.
+    fn entities_text(&self) -> String {
+        self.affected_entities
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// A resource an event affects.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AffectedEntity {
+    pub value: String,
+    /// The member account that owns the entity, only known in org mode
+    pub account_id: Option<String>,
+    pub arn: Option<String>,
+    /// The entity's status, e.g. IMPAIRED or UNIMPAIRED
+    pub status: Option<String>,
+    pub last_updated_time: Option<DateTime<Utc>>,
+    /// The resource's tags, as AWS Health reports them
+    pub tags: BTreeMap<String, String>,
+}
+
+impl fmt::Display for AffectedEntity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.account_id {
+            Some(account_id) => write!(f, "{} ({})", self.value, account_id),
+            None => f.write_str(&self.value),
+        }
+    }
+}
+
+/// Creates a Health client for `config`. The Health API is served from one
+/// region per partition, which is looked up unless `config` has an endpoint
+/// override.
+pub async fn health_client(config: &SdkConfig) -> Client {
+    Client::from_conf(health_config(config).await.build())
+}
+
+/// The Health client configuration for `config`, talking to the region
+/// serving the Health API whatever the configured region.
+async fn health_config(config: &SdkConfig) -> aws_sdk_health::config::Builder {
+    let partition = Partition::of_region(config.region());
+    // A custom endpoint serves every region, so there's nothing to discover
+    let health_region = match config.endpoint_url() {
+        Some(_) => partition.health_region(),
+        None => discovery::active_health_region(partition).await,
+    };
+    aws_sdk_health::config::Builder::from(config).region(health_region)
+}
+
+/// Counts for the library functions, which don't draw progress.
+static NO_PROGRESS: LazyLock<Progress> = LazyLock::new(|| Progress::new(false));
+
+/// Fetches this account's events matching `filter`, starting in
+/// `start_window` if given, looking up the details of up to `concurrency`
+/// batches of them at a time.
+pub async fn query_events(
+    client: &Client,
+    filter: &FilterArgs,
+    start_window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    concurrency: usize,
+) -> Result<Vec<HealthEvent>, Error> {
+    fetch_events(client, filter, start_window, concurrency)
+        .try_collect()
+        .await
+}
+
+/// Fetches the organization's events matching `filter`, like
+/// [`query_events`], with the affected accounts of up to
+/// `account_concurrency` events looked up at a time. It needs the
+/// organization's management account or a delegated administrator.
+pub async fn query_org_events(
+    client: &Client,
+    filter: &FilterArgs,
+    start_window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    concurrency: usize,
+    account_concurrency: usize,
+) -> Result<Vec<HealthEvent>, Error> {
+    fetch_org_events(
+        client,
+        filter,
+        start_window,
+        concurrency,
+        account_concurrency,
+    )
+    .try_collect()
+    .await
+}
+
+/// Fetches events like [`query_events`], yielding each one as soon as its
+/// batch of details is in. Nothing more is fetched while the caller isn't
+/// polling, beyond the `concurrency` batches already in flight. The stream
+/// ends after the first error, and is `Send`, so it can be driven on
+/// another task.
+pub fn fetch_events<'a>(
+    client: &'a Client,
+    filter: &'a FilterArgs,
+    start_window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    concurrency: usize,
+) -> impl Stream<Item = Result<HealthEvent, Error>> + 'a {
+    event_stream(client, filter, start_window, concurrency, &NO_PROGRESS)
+}
+
+/// Fetches the organization's events like [`query_org_events`], yielding
+/// them as [`fetch_events`] does.
+pub fn fetch_org_events<'a>(
+    client: &'a Client,
+    filter: &'a FilterArgs,
+    start_window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    concurrency: usize,
+    account_concurrency: usize,
+) -> impl Stream<Item = Result<HealthEvent, Error>> + 'a {
+    org::org_event_stream(
+        client,
+        filter,
+        start_window,
+        concurrency,
+        account_concurrency,
+        &NO_PROGRESS,
+    )
+}
+
+async fn get_health_events(
+    client: &Client,
+    filter: &FilterArgs,
+    start_window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    concurrency: usize,
+    progress: &Progress,
+    on_event: impl FnMut(&HealthEvent) -> io::Result<()>,
+) -> Result<(), Error> {
+    let events = event_stream(client, filter, start_window, concurrency, progress);
+    for_each_event(events, on_event).await
+}
+
+/// Calls `on_event` for every event of `events`, stopping at the first
+/// error.
+async fn for_each_event(
+    events: impl Stream<Item = Result<HealthEvent, Error>>,
+    mut on_event: impl FnMut(&HealthEvent) -> io::Result<()>,
+) -> Result<(), Error> {
+    let mut events = pin!(events);
+    while let Some(event) = events.next().await {
+        on_event(&event?)?;
+    }
+    Ok(())
+}
+
+fn event_stream<'a>(
+    client: &'a Client,
+    filter: &'a FilterArgs,
+    start_window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    concurrency: usize,
+    progress: &'a Progress,
+) -> impl Stream<Item = Result<HealthEvent, Error>> + 'a {
+    // Describe events, following nextToken through every page
+    let mut pages = client
+        .describe_events()
+        .filter(filter.event_filter(start_window))
+        .into_paginator()
+        .items()
+        .send();
+
+    // Look up details in batches, with up to `concurrency` batches in flight.
+    // `buffered` (rather than `buffer_unordered`) keeps the report in the
+    // order the API returned the events.
+    let batches = stream::poll_fn(move |cx| pages.poll_next(cx))
+        .filter(move |event| {
+            future::ready(event.as_ref().map_or(true, |event| filter.matches(event)))
+        })
+        .inspect(move |event| {
+            if event.is_ok() {
+                progress.add_events(1);
+            }
+        })
+        .chunks(DETAILS_BATCH_SIZE)
+        .map(move |batch| fetch_batch(client, batch, concurrency, progress))
+        .buffered(concurrency);
+    flatten_batches(batches, filter)
+}
+
+/// The events of `batches` that match `filter`'s details filters, one at a
+/// time, ending after the first error.
+fn flatten_batches<'a>(
+    batches: impl Stream<Item = Result<Vec<HealthEvent>, Error>> + 'a,
+    filter: &'a FilterArgs,
+) -> impl Stream<Item = Result<HealthEvent, Error>> + 'a {
+    batches
+        .scan(false, |failed, batch| {
+            let batch = match *failed {
+                true => None,
+                false => Some(batch),
+            };
+            *failed = matches!(batch, Some(Err(_)));
+            future::ready(batch)
+        })
+        .flat_map(move |batch| {
+            let events: Vec<_> = match batch {
+                Ok(events) => events
+                    .into_iter()
+                    .filter(|event| filter.matches_details(event))
+                    .map(Ok)
+                    .collect(),
+                Err(err) => vec![Err(err)],
+            };
+            stream::iter(events)
+        })
+}
+
+/// Fetches details and affected entities for up to `DETAILS_BATCH_SIZE`
+/// events, looking up the entities of up to `concurrency` events at once.
+async fn fetch_batch(
+    client: &Client,
+    batch: Vec<Result<Event, SdkError<DescribeEventsError, HttpResponse>>>,
+    concurrency: usize,
+    progress: &Progress,
+) -> Result<Vec<HealthEvent>, Error> {
+    let batch = batch.into_iter().collect::<Result<Vec<_>, _>>()?;
+    let arns: Vec<String> = batch
+        .iter()
+        .map(|event| event.arn().unwrap_or("N/A").to_string())
+        .collect();
+
+    // Get event details for the whole batch in one call
+    let event_details_resp = client
+        .describe_event_details()
+        .set_event_arns(Some(arns.clone()))
+        .send()
+        .await?;
+    progress.add_details(event_details_resp.successful_set().len() as u64);
+
+    let mut descriptions = HashMap::new();
+    for details in event_details_resp.successful_set() {
+        let arn = details.event().and_then(|event| event.arn());
+        let latest = details
+            .event_description()
+            .and_then(|description| description.latest_description());
+        if let (Some(arn), Some(latest)) = (arn, latest) {
+            descriptions.insert(arn.to_string(), latest.to_string());
+        }
+    }
+
+    // `buffered` keeps the entity lists in the order of the events
+    let entity_lists: Vec<_> = stream::iter(arns.clone())
+        .map(|arn| fetch_entities(client, arn, progress))
+        .buffered(concurrency)
+        .try_collect()
+        .await?;
+
+    let mut health_events = Vec::with_capacity(batch.len());
+    for ((event, arn), entity_list) in batch.iter().zip(arns).zip(entity_lists) {
+        let detail = descriptions
+            .remove(&arn)
+            .unwrap_or_else(|| "No description available".to_string());
+
+        health_events.push(HealthEvent {
+            timestamp: format_timestamp(event.start_time()),
+            start_time: event.start_time().and_then(to_chrono),
+            end_time: event.end_time().and_then(to_chrono),
+            last_updated_time: event.last_updated_time().and_then(to_chrono),
+            arn,
+            service: event.service().unwrap_or_default().to_string(),
+            region: event.region().unwrap_or_default().to_string(),
+            availability_zone: event.availability_zone().map(str::to_string),
+            status: event
+                .status_code()
+                .map(|status| status.as_str().to_string())
+                .unwrap_or_default(),
+            event_type_code: event.event_type_code().unwrap_or_default().to_string(),
+            event_type_category: event
+                .event_type_category()
+                .map(|category| category.as_str().to_string())
+                .unwrap_or_default(),
+            event_scope_code: event
+                .event_scope_code()
+                .map(|scope| scope.as_str().to_string())
+                .unwrap_or_default(),
+            detail,
+            affected_entities: entity_list,
+            affected_accounts: Vec::new(),
+            account_names: BTreeMap::new(),
+        });
+    }
+
+    Ok(health_events)
+}
+
+async fn fetch_entities(
+    client: &Client,
+    arn: String,
+    progress: &Progress,
+) -> Result<Vec<AffectedEntity>, Error> {
+    let mut entities = client
+        .describe_affected_entities()
+        .set_filter(Some(
+            EntityFilter::builder().event_arns(arn).build().unwrap(),
+        ))
+        .into_paginator()
+        .items()
+        .send();
+
+    let mut entity_list = Vec::new();
+    while let Some(entity) = entities.next().await {
+        progress.add_entities(1);
+        let entity = entity?;
+        if let Some(entity_value) = entity.entity_value() {
+            entity_list.push(AffectedEntity {
+                value: entity_value.to_string(),
+                account_id: None,
+                arn: entity.entity_arn().map(str::to_string),
+                status: entity
+                    .status_code()
+                    .map(|status| status.as_str().to_string()),
+                last_updated_time: entity.last_updated_time().and_then(to_chrono),
+                tags: entity
+                    .tags()
+                    .map(|tags| tags.clone().into_iter().collect())
+                    .unwrap_or_default(),
+            });
+        }
+    }
+    Ok(entity_list)
+}
+
+fn format_timestamp(time: Option<&aws_smithy_types::DateTime>) -> String {
+    match time {
+        Some(time) => time
+            .fmt(aws_sdk_health::primitives::DateTimeFormat::DateTime)
+            .unwrap(),
+        None => "Unknown time".to_string(),
+    }
+}
+
+fn to_chrono(time: &aws_smithy_types::DateTime) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(time.to_millis().ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_config::BehaviorVersion;
+    use aws_sdk_health::config::http::HttpRequest;
+    use aws_sdk_health::config::{Credentials, Region};
+    use aws_smithy_runtime_api::client::http::{
+        HttpConnector, HttpConnectorFuture, SharedHttpConnector, http_client_fn,
+    };
+    use aws_smithy_types::body::SdkBody;
+
+    /// Answers Health API calls with canned JSON, keyed by operation name.
+    #[derive(Debug)]
+    struct FakeHealth;
+
+    impl HttpConnector for FakeHealth {
+        fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+            let target = request.headers().get("x-amz-target").unwrap_or_default();
+            let body = std::str::from_utf8(request.body().bytes().unwrap_or_default()).unwrap();
+            let response = match target.rsplit('.').next() {
+                Some("DescribeEvents") if body.contains("\"nextToken\":\"page-2\"") => {
+                    r#"{"events":[{"arn":"arn:event-3","service":"RDS"}]}"#
+                }
+                Some("DescribeEvents") => {
+                    r#"{"events":[{"arn":"arn:event-1","service":"EC2"},{"arn":"arn:event-2","service":"EC2"}],"nextToken":"page-2"}"#
+                }
+                Some("DescribeEventDetails") => r#"{"successfulSet":[],"failedSet":[]}"#,
+                Some("DescribeAffectedEntities") => r#"{"entities":[]}"#,
+                Some("DescribeEventsForOrganization") => {
+                    r#"{"events":[{"arn":"arn:org-event-1","service":"EC2","eventScopeCode":"ACCOUNT_SPECIFIC"}]}"#
+                }
+                Some("DescribeAffectedAccountsForOrganization") => {
+                    r#"{"affectedAccounts":["111111111111","222222222222"]}"#
+                }
+                Some("DescribeEventDetailsForOrganization") => {
+                    r#"{"successfulSet":[{"awsAccountId":"111111111111","event":{"arn":"arn:org-event-1"},"eventDescription":{"latestDescription":"Instance retirement"}}],"failedSet":[]}"#
+                }
+                Some("DescribeAffectedEntitiesForOrganization") => {
+                    r#"{"entities":[{"entityValue":"i-1","awsAccountId":"111111111111","tags":{"Name":"web"}},{"entityValue":"i-2","awsAccountId":"222222222222"}]}"#
+                }
+                other => panic!("unexpected operation {:?}", other),
+            };
+            HttpConnectorFuture::ready(Ok(HttpResponse::new(
+                200.try_into().unwrap(),
+                SdkBody::from(response),
+            )))
+        }
+    }
+
+    fn fake_client() -> Client {
+        let config = aws_sdk_health::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::for_tests())
+            .http_client(http_client_fn(|_, _| SharedHttpConnector::new(FakeHealth)))
+            .build();
+        Client::from_conf(config)
+    }
+
+    #[tokio::test]
+    async fn fetches_every_page_of_events() {
+        let mut arns = Vec::new();
+
+        let progress = Progress::new(false);
+        get_health_events(
+            &fake_client(),
+            &FilterArgs::default(),
+            None,
+            1,
+            &progress,
+            |event| {
+                arns.push(event.arn.clone());
+                Ok(())
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(arns, ["arn:event-1", "arn:event-2", "arn:event-3"]);
+    }
+
+    #[tokio::test]
+    async fn streams_events_one_at_a_time() {
+        let client = fake_client();
+        let filter = FilterArgs::default();
+        let mut events = pin!(fetch_events(&client, &filter, None, 1));
+        assert_eq!(events.next().await.unwrap().unwrap().arn, "arn:event-1");
+        let rest: Vec<_> = events.map(|event| event.unwrap().arn).collect().await;
+        assert_eq!(rest, ["arn:event-2", "arn:event-3"]);
+    }
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[tokio::test]
+    async fn streams_can_run_on_other_tasks() {
+        let client = fake_client();
+        let filter = FilterArgs::default();
+        assert_send(&fetch_events(&client, &filter, None, 1));
+        assert_send(&fetch_org_events(&client, &filter, None, 1, 1));
+
+        let count = tokio::spawn(async move {
+            let events = fetch_events(&client, &filter, None, 1);
+            events
+                .try_collect::<Vec<_>>()
+                .await
+                .map(|events| events.len())
+        });
+        assert_eq!(count.await.unwrap().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn fetches_org_events_through_affected_accounts() {
+        let progress = Progress::new(false);
+        let mut events = Vec::new();
+
+        org::get_org_health_events(
+            &fake_client(),
+            &FilterArgs::default(),
+            None,
+            1,
+            1,
+            &progress,
+            |event| {
+                events.push(event.clone());
+                Ok(())
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].detail, "Instance retirement");
+        let entities: Vec<String> = events[0]
+            .affected_entities
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(entities, ["i-1 (111111111111)", "i-2 (222222222222)"]);
+        assert_eq!(events[0].event_scope_code, "ACCOUNT_SPECIFIC");
+        assert_eq!(
+            events[0].affected_entities[0].tags,
+            BTreeMap::from([("Name".to_string(), "web".to_string())])
+        );
+        assert_eq!(
+            events[0].affected_accounts,
+            ["111111111111", "222222222222"]
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! Prometheus metrics about the events: how many are open per service,
+//! region and category, and how many scheduled changes are coming up.
+//! `--serve-metrics` serves them over HTTP, fetching events again every
+//! `--metrics-interval`, and `--textfile-dir` writes them once for
+//! node_exporter's textfile collector.
+
+use crate::HealthEvent;
+use chrono::{DateTime, Utc};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::error::Error as StdError;
+use std::fmt::Write as _;
+use std::fs;
+use std::future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::time::sleep;
+
+/// Event counts by service, region and category.
+#[derive(Debug, Default)]
+pub struct Summary {
+    open: BTreeMap<(String, String, String), u64>,
+    upcoming_scheduled_changes: BTreeMap<(String, String), u64>,
+}
+
+impl Summary {
+    pub fn of(events: &[HealthEvent]) -> Self {
+        let mut summary = Summary::default();
+        for event in events {
+            match event.status.as_str() {
+                "open" => {
+                    *summary
+                        .open
+                        .entry((
+                            event.service.clone(),
+                            event.region.clone(),
+                            event.event_type_category.clone(),
+                        ))
+                        .or_default() += 1
+                }
+                "upcoming" if event.event_type_category == "scheduledChange" => {
+                    *summary
+                        .upcoming_scheduled_changes
+                        .entry((event.service.clone(), event.region.clone()))
+                        .or_default() += 1
+                }
+                _ => {}
+            }
+        }
+        summary
+    }
+
+    pub fn open_events(&self) -> u64 {
+        self.open.values().sum()
+    }
+
+    pub fn upcoming_scheduled_changes(&self) -> u64 {
+        self.upcoming_scheduled_changes.values().sum()
+    }
+
+    /// The metrics in the Prometheus text format, as of `refreshed`.
+    pub fn to_prometheus(&self, refreshed: DateTime<Utc>) -> String {
+        let mut text = String::from(
+            "# HELP aws_health_open_events Open AWS Health events.\n\
+             # TYPE aws_health_open_events gauge\n",
+        );
+        for ((service, region, category), count) in &self.open {
+            let _ = writeln!(
+                text,
+                "aws_health_open_events{{service=\"{}\",region=\"{}\",category=\"{}\"}} {}",
+                label(service),
+                label(region),
+                label(category),
+                count
+            );
+        }
+        text.push_str(
+            "# HELP aws_health_upcoming_scheduled_changes Scheduled changes that haven't started yet.\n\
+             # TYPE aws_health_upcoming_scheduled_changes gauge\n",
+        );
+        for ((service, region), count) in &self.upcoming_scheduled_changes {
+            let _ = writeln!(
+                text,
+                "aws_health_upcoming_scheduled_changes{{service=\"{}\",region=\"{}\"}} {}",
+                label(service),
+                label(region),
+                count
+            );
+        }
+        let _ = write!(
+            text,
+            "# HELP aws_health_last_refresh_timestamp_seconds When the events were last fetched.\n\
+             # TYPE aws_health_last_refresh_timestamp_seconds gauge\n\
+             aws_health_last_refresh_timestamp_seconds {}\n",
+            refreshed.timestamp()
+        );
+        text
+    }
+}
+
+/// Escapes a label value.
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Parses a `--serve-metrics` address; `:9898` listens on every interface.
+pub fn parse_listen_address(value: &str) -> Result<SocketAddr, String> {
+    let value = match value.strip_prefix(':') {
+        Some(port) => format!("0.0.0.0:{}", port),
+        None => value.to_string(),
+    };
+    value.parse().map_err(|_| {
+        format!(
+            "expected an address such as :9898 or 127.0.0.1:9898, got {:?}",
+            value
+        )
+    })
+}
+
+/// Writes the metrics to `aws9man.prom` in `dir`, through a temporary file
+/// so the collector never reads half of one.
+pub fn write_textfile(dir: &Path, summary: &Summary) -> Result<PathBuf, Box<dyn StdError>> {
+    let path = dir.join("aws9man.prom");
+    // The collector only reads *.prom files
+    let temp = dir.join(format!(".aws9man.prom.{}", process::id()));
+    fs::write(&temp, summary.to_prometheus(Utc::now()))
+        .map_err(|err| format!("{}: {}", temp.display(), err))?;
+    fs::rename(&temp, &path).map_err(|err| {
+        let _ = fs::remove_file(&temp);
+        format!("{}: {}", path.display(), err)
+    })?;
+    Ok(path)
+}
+
+/// Serves metrics on `address` until the process is stopped, calling
+/// `refresh` for the events every `interval`. A failed refresh keeps the
+/// last metrics, with `aws_health_refresh_success` set to 0.
+pub async fn serve(
+    address: SocketAddr,
+    interval: Duration,
+    mut refresh: impl AsyncFnMut() -> Result<Vec<HealthEvent>, Box<dyn StdError>>,
+) -> Result<(), Box<dyn StdError>> {
+    let listener = TcpListener::bind(address)
+        .await
+        .map_err(|err| format!("could not listen on {}: {}", address, err))?;
+    println!("Serving metrics on http://{}/metrics", address);
+    let page = Arc::new(RwLock::new(None));
+    tokio::spawn(accept(listener, page.clone()));
+
+    let mut last = None;
+    loop {
+        let success = match refresh().await {
+            Ok(events) => {
+                let summary = Summary::of(&events);
+                println!(
+                    "Refreshed metrics: {} open events, {} upcoming scheduled changes",
+                    summary.open_events(),
+                    summary.upcoming_scheduled_changes()
+                );
+                last = Some((summary, Utc::now()));
+                true
+            }
+            Err(err) => {
+                eprintln!("Error refreshing metrics: {}", err);
+                false
+            }
+        };
+        if let Some((summary, refreshed)) = &last {
+            let text = format!(
+                "{}# HELP aws_health_refresh_success Whether the last refresh fetched the events.\n\
+                 # TYPE aws_health_refresh_success gauge\n\
+                 aws_health_refresh_success {}\n",
+                summary.to_prometheus(*refreshed),
+                success as u8
+            );
+            *page.write().expect("the page lock isn't poisoned") = Some(text);
+        }
+        sleep(interval).await;
+    }
+}
+
+async fn accept(listener: TcpListener, page: Arc<RwLock<Option<String>>>) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            // E.g. out of file descriptors; give connections time to close
+            Err(_) => {
+                sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let page = page.clone();
+        tokio::spawn(async move {
+            let service = service_fn(|request: Request<Incoming>| {
+                future::ready(Ok::<_, Infallible>(respond(request.uri().path(), &page)))
+            });
+            // A scraper hanging up early isn't worth reporting
+            let _ = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await;
+        });
+    }
+}
+
+fn respond(path: &str, page: &RwLock<Option<String>>) -> Response<String> {
+    let (status, content_type, body) = match path {
+        "/metrics" => match page.read().expect("the page lock isn't poisoned").clone() {
+            Some(text) => (StatusCode::OK, "text/plain; version=0.0.4", text),
+            None => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "text/plain",
+                "The first refresh hasn't finished yet\n".to_string(),
+            ),
+        },
+        "/" => (
+            StatusCode::OK,
+            "text/html",
+            "<html><body><a href=\"/metrics\">Metrics</a></body></html>\n".to_string(),
+        ),
+        _ => (
+            StatusCode::NOT_FOUND,
+            "text/plain",
+            "Not found\n".to_string(),
+        ),
+    };
+    Response::builder()
+        .status(status)
+        .header("content-type", content_type)
+        .body(body)
+        .expect("the response is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn counts_open_events_and_upcoming_changes() {
+        let event = |service: &str, status: &str, category: &str| HealthEvent {
+            service: service.to_string(),
+            region: "us-east-1".to_string(),
+            status: status.to_string(),
+            event_type_category: category.to_string(),
+            ..Default::default()
+        };
+        let summary = Summary::of(&[
+            event("EC2", "open", "issue"),
+            event("EC2", "open", "issue"),
+            event("EC2", "closed", "issue"),
+            event("RDS", "upcoming", "scheduledChange"),
+        ]);
+        let text = summary.to_prometheus(Utc.timestamp_opt(1_700_000_000, 0).unwrap());
+        assert!(text.contains(
+            "aws_health_open_events{service=\"EC2\",region=\"us-east-1\",category=\"issue\"} 2\n"
+        ));
+        assert!(text.contains(
+            "aws_health_upcoming_scheduled_changes{service=\"RDS\",region=\"us-east-1\"} 1\n"
+        ));
+        assert!(text.contains("aws_health_last_refresh_timestamp_seconds 1700000000\n"));
+        assert_eq!(parse_listen_address(":9898").unwrap().port(), 9898);
+    }
+}
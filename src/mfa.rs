@@ -0,0 +1,109 @@
+//! Assuming roles that require MFA. The SDK's assume-role provider can't send
+//! an MFA token (and ignores `mfa_serial` in profiles), so such roles are
+//! assumed here, once per run, with a code from `--mfa-code` or a prompt.
+
+use aws_config::SdkConfig;
+use aws_credential_types::Credentials;
+use aws_smithy_types::error::display::DisplayErrorContext;
+use aws_types::os_shim_internal::{Env, Fs};
+use aws_types::sdk_config::SharedCredentialsProvider;
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::io::{self, IsTerminal, Write};
+use std::time::SystemTime;
+
+/// A profile that assumes a role with an MFA device.
+#[derive(Debug)]
+pub struct ProfileRole {
+    pub role_arn: String,
+    pub external_id: Option<String>,
+    pub mfa_serial: String,
+    /// Profile holding the credentials to assume the role with
+    pub source_profile: String,
+    pub region: Option<String>,
+}
+
+/// The MFA-protected role `profile` (or the default profile) assumes, if any.
+pub async fn profile_role(profile: Option<&str>) -> Option<ProfileRole> {
+    let selected = profile.map(|profile| Cow::Owned(profile.to_string()));
+    let profiles =
+        aws_config::profile::load(&Fs::real(), &Env::real(), &Default::default(), selected)
+            .await
+            .ok()?;
+    let field = |key: &str| profiles.get(key).map(str::to_string);
+    Some(ProfileRole {
+        role_arn: field("role_arn")?,
+        external_id: field("external_id"),
+        mfa_serial: field("mfa_serial")?,
+        source_profile: field("source_profile")?,
+        region: field("region"),
+    })
+}
+
+/// Returns `code`, or prompts for one on the terminal.
+pub fn read_code(mfa_serial: &str, code: Option<&str>) -> Result<String, Box<dyn StdError>> {
+    let code = match code {
+        Some(code) => code.to_string(),
+        None if io::stdin().is_terminal() => {
+            eprint!("MFA code for {}: ", mfa_serial);
+            io::stderr().flush()?;
+            let mut line = String::new();
+            io::stdin().read_line(&mut line)?;
+            line.trim().to_string()
+        }
+        None => {
+            return Err(format!(
+                "{} requires an MFA code: pass --mfa-code when not running in a terminal",
+                mfa_serial
+            )
+            .into());
+        }
+    };
+    if code.len() != 6 || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return Err("expected a 6-digit MFA code".into());
+    }
+    Ok(code)
+}
+
+/// Returns `base_config` with credentials for `role_arn`, assumed with an MFA
+/// code. The credentials aren't refreshed, so they last for the role's
+/// default session duration.
+pub async fn assume_role(
+    base_config: &SdkConfig,
+    role_arn: &str,
+    external_id: Option<&str>,
+    session_name: &str,
+    mfa_serial: &str,
+    code: &str,
+) -> Result<SdkConfig, Box<dyn StdError>> {
+    let resp = aws_sdk_sts::Client::new(base_config)
+        .assume_role()
+        .role_arn(role_arn)
+        .role_session_name(session_name)
+        .set_external_id(external_id.map(str::to_string))
+        .serial_number(mfa_serial)
+        .token_code(code)
+        .send()
+        .await
+        .map_err(|err| {
+            format!(
+                "could not assume {}: {}",
+                role_arn,
+                DisplayErrorContext(err)
+            )
+        })?;
+    let credentials = resp
+        .credentials()
+        .ok_or_else(|| format!("assuming {} returned no credentials", role_arn))?;
+    let credentials = Credentials::new(
+        credentials.access_key_id(),
+        credentials.secret_access_key(),
+        Some(credentials.session_token().to_string()),
+        SystemTime::try_from(*credentials.expiration()).ok(),
+        "AssumeRoleWithMfa",
+    );
+    Ok(base_config
+        .to_builder()
+        .credentials_provider(SharedCredentialsProvider::new(credentials))
+        .build())
+}
@@ -0,0 +1,139 @@
+//! What the chat and paging sinks have in common: how an event is linked and
+//! summed up, and how its notification is posted.
+
+use crate::HealthEvent;
+use crate::http_client::HttpClient;
+use std::error::Error as StdError;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// Posts rate-limited with 429 are sent again this many times, after the
+/// `Retry-After` the service asks for, up to `MAX_RETRY_AFTER`.
+const MAX_ATTEMPTS: usize = 3;
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// The event's page in the AWS Health Dashboard.
+pub fn console_url(event: &HealthEvent) -> String {
+    format!(
+        "https://health.aws.amazon.com/health/home#/account/event-log?eventID={}&eventTab=details",
+        event.arn
+    )
+}
+
+/// A one-line title, e.g. `EC2 operational issue (us-east-1)`.
+pub fn title(event: &HealthEvent) -> String {
+    let code = event
+        .event_type_code
+        .strip_prefix("AWS_")
+        .unwrap_or(&event.event_type_code);
+    let code = code
+        .strip_prefix(&format!("{}_", event.service))
+        .unwrap_or(code);
+    format!(
+        "{} {} ({})",
+        event.service,
+        code.replace('_', " ").to_lowercase(),
+        event.region
+    )
+}
+
+/// At most `max_chars` characters of `text`, cut at a word boundary with an
+/// ellipsis if it had to be shortened.
+pub fn excerpt(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut chars = text.chars();
+    let cut: String = chars.by_ref().take(max_chars.saturating_sub(1)).collect();
+    let at_word_end = chars.next().is_some_and(char::is_whitespace);
+    let cut = match cut.rfind(char::is_whitespace) {
+        Some(end) if !at_word_end && end > cut.len() / 2 => &cut[..end],
+        _ => &cut,
+    };
+    format!("{}…", cut.trim_end())
+}
+
+/// POSTs `body` to `url`, waiting out rate limits, and returns the response
+/// body. `service` names the destination in errors.
+pub async fn post(
+    http: &HttpClient,
+    service: &str,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: String,
+) -> Result<Vec<u8>, Box<dyn StdError>> {
+    for attempt in 1..=MAX_ATTEMPTS {
+        let response = http
+            .send("POST", url, headers, body.clone().into_bytes())
+            .await?;
+        if response.status().is_success() {
+            return Ok(response.into_body());
+        }
+        if response.status() == 429 && attempt < MAX_ATTEMPTS {
+            let retry_after = response
+                .headers()
+                .get("retry-after")
+                .and_then(|value| value.to_str().ok());
+            sleep(retry_delay(retry_after)).await;
+            continue;
+        }
+        return Err(format!(
+            "{} returned HTTP status {}: {}",
+            service,
+            response.status().as_u16(),
+            String::from_utf8_lossy(response.body()).trim()
+        )
+        .into());
+    }
+    unreachable!("the last attempt returns")
+}
+
+/// How long to wait for a `Retry-After` of `value` seconds, at most
+/// `MAX_RETRY_AFTER`: a second if it's missing or not a number of seconds,
+/// such as `-1` or `NaN`.
+fn retry_delay(value: Option<&str>) -> Duration {
+    value
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        // Also false for NaN
+        .filter(|seconds| *seconds >= 0.0)
+        .and_then(|seconds| {
+            Duration::try_from_secs_f64(seconds.min(MAX_RETRY_AFTER.as_secs_f64())).ok()
+        })
+        .unwrap_or(Duration::from_secs(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortens_descriptions_at_a_word() {
+        assert_eq!(excerpt("  short  ", 10), "short");
+        assert_eq!(
+            excerpt("We are investigating increased error rates", 21),
+            "We are investigating…"
+        );
+        assert_eq!(
+            excerpt("We are investigating increased error rates", 24),
+            "We are investigating…"
+        );
+        assert_eq!(excerpt("Supercalifragilistic", 10), "Supercali…");
+    }
+
+    #[test]
+    fn waits_out_sane_retry_afters_only() {
+        assert_eq!(retry_delay(Some("2.5")), Duration::from_millis(2500));
+        assert_eq!(retry_delay(Some("3600")), MAX_RETRY_AFTER);
+        assert_eq!(retry_delay(Some("inf")), MAX_RETRY_AFTER);
+        for value in ["-1", "NaN", "soon"] {
+            assert_eq!(
+                retry_delay(Some(value)),
+                Duration::from_secs(1),
+                "{}",
+                value
+            );
+        }
+        assert_eq!(retry_delay(None), Duration::from_secs(1));
+    }
+}
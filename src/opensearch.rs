@@ -0,0 +1,283 @@
+//! Bulk-indexes events into OpenSearch or Elasticsearch (`--opensearch-url`),
+//! one document per event with its ARN as the ID, so a re-run updates
+//! documents instead of adding copies.
+//!
+//! Documents have every field of the JSON output, whose RFC 3339 times
+//! (`start_time`, `end_time`, `last_updated_time`) dynamic mapping types as
+//! dates, plus an `@timestamp` of the start time to build index patterns on.
+//!
+//! Requests to Amazon OpenSearch Service domains and Serverless collections
+//! are signed with SigV4; other clusters get the URL's user and password,
+//! if any, as basic auth.
+
+use crate::HealthEvent;
+use crate::batch::batches;
+use crate::http_client::HttpClient;
+use crate::output::event_to_json;
+use crate::sigv4::SignedClient;
+use aws_sigv4::http_request::{PayloadChecksumKind, SigningSettings};
+use aws_smithy_json::deserialize::json_token_iter;
+use aws_smithy_json::deserialize::token::expect_document;
+use aws_smithy_json::serialize::JsonObjectWriter;
+use aws_smithy_types::{Document, base64};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::error::Error as StdError;
+
+/// Bulk requests are kept to 1,000 documents and 5 MiB, well under the
+/// 10 MiB the smallest Amazon OpenSearch instances take.
+const MAX_BATCH_DOCUMENTS: usize = 1000;
+const MAX_BATCH_BYTES: usize = 5 * 1024 * 1024;
+
+/// Where `--opensearch-url` points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenSearchUrl {
+    /// The URL without credentials or a trailing slash
+    url: String,
+    /// The signing name (`es` or `aoss`) and region of an AWS endpoint
+    aws: Option<(String, String)>,
+    /// `user:password` from the URL
+    user_info: Option<String>,
+}
+
+impl OpenSearchUrl {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let error = || "expected http(s)://host[:port][/path]".to_string();
+        let (scheme, rest) = value.split_once("://").ok_or_else(error)?;
+        if scheme != "https" && scheme != "http" {
+            return Err(error());
+        }
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let (user_info, host) = match authority.rsplit_once('@') {
+            Some((user_info, host)) => (Some(user_info.to_string()), host),
+            None => (None, authority),
+        };
+        if host.is_empty() {
+            return Err(error());
+        }
+        // e.g. search-health-abc.eu-west-1.es.amazonaws.com, or
+        // abc.eu-west-1.aoss.amazonaws.com for Serverless
+        let name = host.split(':').next().unwrap_or(host);
+        let labels: Vec<&str> = name
+            .strip_suffix(".cn")
+            .unwrap_or(name)
+            .split('.')
+            .collect();
+        let aws = match labels[..] {
+            [.., region, service @ ("es" | "aoss"), "amazonaws", "com"] => {
+                Some((service.to_string(), region.to_string()))
+            }
+            _ => None,
+        };
+        Ok(OpenSearchUrl {
+            url: format!("{}://{}{}", scheme, host, path.trim_end_matches('/')),
+            aws,
+            user_info,
+        })
+    }
+
+    /// Whether requests are signed, and so need a `SignedClient`.
+    pub fn is_aws(&self) -> bool {
+        self.aws.is_some()
+    }
+}
+
+/// Checks an index name template such as `aws-health-%Y.%m`.
+pub fn parse_index_template(value: &str) -> Result<String, String> {
+    if value.is_empty() || value.starts_with(['_', '-', '+']) {
+        return Err("index names can't be empty or start with _, - or +".to_string());
+    }
+    if StrftimeItems::new(value).any(|item| item == Item::Error) {
+        return Err("invalid strftime placeholder (write a literal % as %%)".to_string());
+    }
+    Ok(value.to_string())
+}
+
+pub struct OpenSearchIndexer {
+    http: HttpClient,
+    /// Set for AWS endpoints
+    signer: Option<SignedClient>,
+    url: OpenSearchUrl,
+    index: String,
+}
+
+impl OpenSearchIndexer {
+    /// Indexes into the index that `index`, a strftime template, names for
+    /// each event's start time (or the time of the run, for events without
+    /// one).
+    pub fn new(
+        http: HttpClient,
+        signer: Option<SignedClient>,
+        url: OpenSearchUrl,
+        index: String,
+    ) -> Self {
+        OpenSearchIndexer {
+            http,
+            signer,
+            url,
+            index,
+        }
+    }
+
+    pub async fn index_events(&self, events: &[HealthEvent]) -> Result<(), Box<dyn StdError>> {
+        let now = Utc::now();
+        let actions = events
+            .iter()
+            .map(|event| {
+                let time = event.start_time.unwrap_or(now);
+                let index = time.format(&self.index);
+                let mut action = String::new();
+                let mut object = JsonObjectWriter::new(&mut action);
+                let mut index_action = object.key("index").start_object();
+                index_action.key("_index").string(&index.to_string());
+                index_action.key("_id").string(&event.arn);
+                index_action.finish();
+                object.finish();
+                format!("{}\n{}\n", action, document(event, time))
+            })
+            .collect();
+        for batch in batches(actions, MAX_BATCH_DOCUMENTS, MAX_BATCH_BYTES, String::len) {
+            self.bulk(batch.concat().into_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    async fn bulk(&self, body: Vec<u8>) -> Result<(), Box<dyn StdError>> {
+        let uri = format!("{}/_bulk", self.url.url);
+        let content_type = ("content-type", "application/x-ndjson");
+        let response = match (&self.signer, &self.url.aws) {
+            (Some(signer), Some((service, region))) => {
+                let mut settings = SigningSettings::default();
+                // Serverless needs the payload hash as a header
+                settings.payload_checksum_kind = PayloadChecksumKind::XAmzSha256;
+                signer
+                    .send(
+                        "POST",
+                        &uri,
+                        service,
+                        region,
+                        settings,
+                        &[content_type],
+                        body,
+                    )
+                    .await?
+            }
+            _ => {
+                let authorization = self
+                    .url
+                    .user_info
+                    .as_ref()
+                    .map(|user_info| format!("Basic {}", base64::encode(user_info)));
+                let mut headers = vec![content_type];
+                if let Some(authorization) = &authorization {
+                    headers.push(("authorization", authorization));
+                }
+                self.http.send("POST", &uri, &headers, body).await?
+            }
+        };
+        if !response.status().is_success() {
+            return Err(format!(
+                "OpenSearch bulk request failed with HTTP status {}: {}",
+                response.status().as_u16(),
+                String::from_utf8_lossy(response.body()).trim()
+            )
+            .into());
+        }
+        match first_error(response.body())? {
+            Some(error) => Err(format!("OpenSearch failed to index an event: {}", error).into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The event's document, stamped with `time`.
+fn document(event: &HealthEvent, time: DateTime<Utc>) -> String {
+    let json = event_to_json(event);
+    let fields = json.strip_prefix('{').unwrap_or(&json);
+    format!(
+        "{{\"@timestamp\":\"{}\",{}",
+        time.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        fields
+    )
+}
+
+/// Describes the first document a bulk response reports as failed, if any.
+fn first_error(response: &[u8]) -> Result<Option<String>, Box<dyn StdError>> {
+    let document = expect_document(&mut json_token_iter(response).peekable())?;
+    let Document::Object(response) = document else {
+        return Err("OpenSearch returned an unexpected bulk response".into());
+    };
+    if !matches!(response.get("errors"), Some(Document::Bool(true))) {
+        return Ok(None);
+    }
+    let Some(Document::Array(items)) = response.get("items") else {
+        return Ok(Some("unknown error".to_string()));
+    };
+    let field = |document: &Document, key: &str| match document {
+        Document::Object(object) => object.get(key).cloned(),
+        _ => None,
+    };
+    Ok(items.iter().find_map(|item| {
+        let error = field(&field(item, "index")?, "error")?;
+        let id = match field(&field(item, "index")?, "_id") {
+            Some(Document::String(id)) => id,
+            _ => String::new(),
+        };
+        let text = |key| match field(&error, key) {
+            Some(Document::String(value)) => value,
+            _ => String::new(),
+        };
+        Some(format!("{}: {}: {}", id, text("type"), text("reason")))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn recognizes_aws_endpoints_and_bulk_errors() {
+        let url =
+            OpenSearchUrl::parse("https://search-health-abc.eu-west-1.es.amazonaws.com/").unwrap();
+        assert_eq!(
+            url.url,
+            "https://search-health-abc.eu-west-1.es.amazonaws.com"
+        );
+        assert_eq!(url.aws, Some(("es".to_string(), "eu-west-1".to_string())));
+        let url = OpenSearchUrl::parse("http://elastic:pw@localhost:9200").unwrap();
+        assert_eq!(url.url, "http://localhost:9200");
+        assert_eq!(url.aws, None);
+        assert_eq!(url.user_info.as_deref(), Some("elastic:pw"));
+
+        assert_eq!(
+            first_error(br#"{"took":3,"errors":true,"items":[{"index":{"_id":"a","status":201}},{"index":{"_id":"b","status":400,"error":{"type":"mapper_parsing_exception","reason":"failed to parse"}}}]}"#)
+                .unwrap(),
+            Some("b: mapper_parsing_exception: failed to parse".to_string())
+        );
+        assert_eq!(
+            first_error(br#"{"took":3,"errors":false,"items":[]}"#).unwrap(),
+            None
+        );
+
+        let start = Utc.with_ymd_and_hms(2024, 3, 6, 9, 0, 0).unwrap();
+        let event = HealthEvent {
+            start_time: Some(start),
+            service: "EC2".to_string(),
+            region: "us-east-1".to_string(),
+            status: "open".to_string(),
+            ..Default::default()
+        };
+        let document = document(&event, start);
+        assert!(document.starts_with(
+            r#"{"@timestamp":"2024-03-06T09:00:00Z","timestamp":"","start_time":"2024-03-06T09:00:00Z","end_time":null,"#
+        ));
+        for field in [
+            r#""service":"EC2""#,
+            r#""region":"us-east-1""#,
+            r#""status":"open""#,
+        ] {
+            assert!(document.contains(field), "{} missing", field);
+        }
+    }
+}
@@ -0,0 +1,378 @@
+//! Organization-wide mode, run from the management account (or a delegated
+//! administrator) through the `*_for_organization` Health APIs.
+
+use crate::filter::FilterArgs;
+use crate::progress::Progress;
+use crate::{
+    AffectedEntity, DETAILS_BATCH_SIZE, Error, HealthEvent, flatten_batches, for_each_event,
+    format_timestamp, to_chrono,
+};
+use aws_sdk_health::Client;
+use aws_sdk_health::config::http::HttpResponse;
+use aws_sdk_health::error::SdkError;
+use aws_sdk_health::operation::describe_events_for_organization::DescribeEventsForOrganizationError;
+use aws_sdk_health::types::{
+    EntityAccountFilter, EventAccountFilter, EventScopeCode, OrganizationEvent,
+};
+use chrono::{DateTime, Utc};
+use clap::Subcommand;
+use futures_util::{Stream, StreamExt, future, stream};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error as StdError;
+use std::io;
+
+#[derive(Subcommand, Debug)]
+pub enum OrgCommand {
+    /// Enable AWS Health organizational view (run from the management account)
+    Enable,
+    /// Show whether AWS Health organizational view is enabled
+    Status,
+}
+
+pub async fn run_command(client: &Client, command: &OrgCommand) -> Result<(), Box<dyn StdError>> {
+    match command {
+        OrgCommand::Enable => {
+            client
+                .enable_health_service_access_for_organization()
+                .send()
+                .await?;
+            println!("AWS Health organizational view enabled");
+        }
+        OrgCommand::Status => {
+            let resp = client
+                .describe_health_service_status_for_organization()
+                .send()
+                .await?;
+            println!(
+                "AWS Health organizational view: {}",
+                resp.health_service_access_status_for_organization()
+                    .unwrap_or("UNKNOWN")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `describe_affected_entities_for_organization` accepts at most this many
+/// event/account filters per call
+const ENTITY_FILTER_BATCH_SIZE: usize = 10;
+
+pub async fn get_org_health_events(
+    client: &Client,
+    filter: &FilterArgs,
+    start_window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    concurrency: usize,
+    account_concurrency: usize,
+    progress: &Progress,
+    on_event: impl FnMut(&HealthEvent) -> io::Result<()>,
+) -> Result<(), Error> {
+    let events = org_event_stream(
+        client,
+        filter,
+        start_window,
+        concurrency,
+        account_concurrency,
+        progress,
+    );
+    for_each_event(events, on_event).await
+}
+
+pub(crate) fn org_event_stream<'a>(
+    client: &'a Client,
+    filter: &'a FilterArgs,
+    start_window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    concurrency: usize,
+    account_concurrency: usize,
+    progress: &'a Progress,
+) -> impl Stream<Item = Result<HealthEvent, Error>> + 'a {
+    let mut pages = client
+        .describe_events_for_organization()
+        .filter(filter.org_event_filter(start_window))
+        .into_paginator()
+        .items()
+        .send();
+
+    // Affected accounts are needed before details can be looked up, so they
+    // are fetched first, then details are looked up in batches like the
+    // single-account path
+    let batches = stream::poll_fn(move |cx| pages.poll_next(cx))
+        .filter(move |event| {
+            future::ready(
+                event
+                    .as_ref()
+                    .map_or(true, |event| filter.matches_org(event)),
+            )
+        })
+        .inspect(move |event| {
+            if event.is_ok() {
+                progress.add_events(1);
+            }
+        })
+        .map(move |event| with_affected_accounts(client, event))
+        .buffered(concurrency)
+        .chunks(DETAILS_BATCH_SIZE)
+        .map(move |batch| fetch_batch(client, batch, account_concurrency, progress))
+        .buffered(concurrency);
+    flatten_batches(batches, filter)
+}
+
+/// An organization event and the member accounts it affects.
+struct AccountEvent {
+    event: OrganizationEvent,
+    affected_accounts: Vec<String>,
+}
+
+impl AccountEvent {
+    fn arn(&self) -> &str {
+        self.event.arn().unwrap_or("N/A")
+    }
+
+    /// Accounts to look the event up through. Account-specific events can
+    /// only be looked up through one of the affected accounts, public events
+    /// are looked up without one.
+    fn lookup_accounts(&self) -> Vec<Option<String>> {
+        if self.event.event_scope_code() == Some(&EventScopeCode::AccountSpecific) {
+            self.affected_accounts.iter().cloned().map(Some).collect()
+        } else {
+            vec![None]
+        }
+    }
+}
+
+async fn with_affected_accounts(
+    client: &Client,
+    event: Result<OrganizationEvent, SdkError<DescribeEventsForOrganizationError, HttpResponse>>,
+) -> Result<AccountEvent, Error> {
+    let event = event?;
+    let affected_accounts = affected_accounts(client, event.arn().unwrap_or("N/A")).await?;
+    Ok(AccountEvent {
+        event,
+        affected_accounts,
+    })
+}
+
+/// Fetches details and affected entities for up to `DETAILS_BATCH_SIZE`
+/// organization events.
+async fn fetch_batch(
+    client: &Client,
+    batch: Vec<Result<AccountEvent, Error>>,
+    account_concurrency: usize,
+    progress: &Progress,
+) -> Result<Vec<HealthEvent>, Error> {
+    let batch = batch.into_iter().collect::<Result<Vec<_>, _>>()?;
+
+    let filters = detail_filters(&batch)?;
+    let mut descriptions = HashMap::new();
+    if !filters.is_empty() {
+        let details = client
+            .describe_event_details_for_organization()
+            .set_organization_event_detail_filters(Some(filters))
+            .send()
+            .await?;
+        progress.add_details(details.successful_set().len() as u64);
+        for details in details.successful_set() {
+            let arn = details.event().and_then(|event| event.arn());
+            let latest = details
+                .event_description()
+                .and_then(|description| description.latest_description());
+            if let (Some(arn), Some(latest)) = (arn, latest) {
+                descriptions.insert(arn.to_string(), latest.to_string());
+            }
+        }
+    }
+
+    let mut health_events = Vec::with_capacity(batch.len());
+    for account_event in batch {
+        let lookup_accounts = account_event.lookup_accounts();
+        let AccountEvent {
+            event,
+            affected_accounts,
+        } = account_event;
+        let arn = event.arn().unwrap_or("N/A").to_string();
+        let entity_list = fetch_entities(
+            client,
+            &arn,
+            &lookup_accounts,
+            account_concurrency,
+            progress,
+        )
+        .await?;
+        let detail = descriptions
+            .remove(&arn)
+            .unwrap_or_else(|| "No description available".to_string());
+
+        health_events.push(HealthEvent {
+            timestamp: format_timestamp(event.start_time()),
+            start_time: event.start_time().and_then(to_chrono),
+            end_time: event.end_time().and_then(to_chrono),
+            last_updated_time: event.last_updated_time().and_then(to_chrono),
+            arn,
+            service: event.service().unwrap_or_default().to_string(),
+            region: event.region().unwrap_or_default().to_string(),
+            // Organization events don't say
+            availability_zone: None,
+            status: event
+                .status_code()
+                .map(|status| status.as_str().to_string())
+                .unwrap_or_default(),
+            event_type_code: event.event_type_code().unwrap_or_default().to_string(),
+            event_type_category: event
+                .event_type_category()
+                .map(|category| category.as_str().to_string())
+                .unwrap_or_default(),
+            event_scope_code: event
+                .event_scope_code()
+                .map(|scope| scope.as_str().to_string())
+                .unwrap_or_default(),
+            detail,
+            affected_entities: entity_list,
+            affected_accounts,
+            account_names: BTreeMap::new(),
+        });
+    }
+
+    Ok(health_events)
+}
+
+/// Looks up an event's entities per (event, account) pair, up to
+/// `ENTITY_FILTER_BATCH_SIZE` pairs at a time, so each one can be attributed
+/// to the account that owns it. Up to `account_concurrency` lookups run in
+/// parallel.
+/// One (event, account) pair per event: the description is the same for
+/// every account, so one is enough. Account-specific events that no longer
+/// affect any account can't be looked up, so they keep only their base
+/// event.
+fn detail_filters(batch: &[AccountEvent]) -> Result<Vec<EventAccountFilter>, Error> {
+    let filters = batch.iter().filter_map(|event| {
+        let account = event.lookup_accounts().into_iter().next()?;
+        Some(
+            EventAccountFilter::builder()
+                .event_arn(event.arn())
+                .set_aws_account_id(account)
+                .build(),
+        )
+    });
+    Ok(filters.collect::<Result<Vec<_>, _>>()?)
+}
+
+async fn fetch_entities(
+    client: &Client,
+    arn: &str,
+    accounts: &[Option<String>],
+    account_concurrency: usize,
+    progress: &Progress,
+) -> Result<Vec<AffectedEntity>, Error> {
+    // Owned chunks, as futures borrowing them couldn't be proven `Send`
+    let chunks = accounts.chunks(ENTITY_FILTER_BATCH_SIZE).map(<[_]>::to_vec);
+    let mut chunks = stream::iter(chunks)
+        .map(|accounts| fetch_entity_chunk(client, arn, accounts, progress))
+        .buffered(account_concurrency);
+
+    let mut entity_list = Vec::new();
+    while let Some(entities) = chunks.next().await {
+        entity_list.extend(entities?);
+    }
+    Ok(entity_list)
+}
+
+async fn fetch_entity_chunk(
+    client: &Client,
+    arn: &str,
+    accounts: Vec<Option<String>>,
+    progress: &Progress,
+) -> Result<Vec<AffectedEntity>, Error> {
+    let filters = accounts
+        .iter()
+        .map(|account| {
+            EntityAccountFilter::builder()
+                .event_arn(arn)
+                .set_aws_account_id(account.clone())
+                .build()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut entities = client
+        .describe_affected_entities_for_organization()
+        .set_organization_entity_account_filters(Some(filters))
+        .into_paginator()
+        .items()
+        .send();
+    let mut entity_list = Vec::new();
+    while let Some(entity) = entities.next().await {
+        progress.add_entities(1);
+        let entity = entity?;
+        if let Some(entity_value) = entity.entity_value() {
+            entity_list.push(AffectedEntity {
+                value: entity_value.to_string(),
+                account_id: entity.aws_account_id().map(str::to_string),
+                arn: entity.entity_arn().map(str::to_string),
+                status: entity
+                    .status_code()
+                    .map(|status| status.as_str().to_string()),
+                last_updated_time: entity.last_updated_time().and_then(to_chrono),
+                tags: entity
+                    .tags()
+                    .map(|tags| tags.clone().into_iter().collect())
+                    .unwrap_or_default(),
+            });
+        }
+    }
+    Ok(entity_list)
+}
+
+/// Lists the member accounts affected by an event.
+async fn affected_accounts(client: &Client, arn: &str) -> Result<Vec<String>, Error> {
+    let mut pages = client
+        .describe_affected_accounts_for_organization()
+        .event_arn(arn)
+        .into_paginator()
+        .items()
+        .send();
+
+    let mut accounts = Vec::new();
+    while let Some(account) = pages.next().await {
+        accounts.push(account?);
+    }
+    Ok(accounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_account_specific_events_without_accounts() {
+        let event = |arn: &str, scope: EventScopeCode, accounts: &[&str]| AccountEvent {
+            event: OrganizationEvent::builder()
+                .arn(arn)
+                .event_scope_code(scope)
+                .build(),
+            affected_accounts: accounts.iter().map(|account| account.to_string()).collect(),
+        };
+        let batch = [
+            event("arn:public", EventScopeCode::Public, &[]),
+            event("arn:no-accounts", EventScopeCode::AccountSpecific, &[]),
+            event(
+                "arn:accounts",
+                EventScopeCode::AccountSpecific,
+                &["111111111111", "222222222222"],
+            ),
+        ];
+        let filters: Vec<_> = detail_filters(&batch)
+            .unwrap()
+            .into_iter()
+            .map(|filter| {
+                (
+                    filter.event_arn().to_string(),
+                    filter.aws_account_id().map(str::to_string),
+                )
+            })
+            .collect();
+        assert_eq!(
+            filters,
+            [
+                ("arn:public".to_string(), None),
+                ("arn:accounts".to_string(), Some("111111111111".to_string())),
+            ]
+        );
+    }
+}
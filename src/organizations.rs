@@ -0,0 +1,122 @@
+//! Account names from AWS Organizations, so reports can show more than raw
+//! 12-digit account IDs.
+//!
+//! Only `ListAccounts` is needed, so rather than depending on the whole
+//! Organizations SDK the call is signed and sent by hand (see `sigv4`).
+
+use crate::partition::Partition;
+use crate::proxy::ProxyHttpClient;
+use crate::sigv4::SignedClient;
+use aws_config::SdkConfig;
+use aws_smithy_json::deserialize::json_token_iter;
+use aws_smithy_json::deserialize::token::expect_document;
+use aws_smithy_json::serialize::JsonObjectWriter;
+use aws_smithy_types::Document;
+use std::collections::HashMap;
+use std::error::Error as StdError;
+
+const SIGNING_NAME: &str = "organizations";
+const TARGET: &str = "AWSOrganizationsV20161128.ListAccounts";
+
+/// Lists every account in the organization, returning their names by ID.
+pub async fn list_account_names(
+    config: &SdkConfig,
+    proxy: Option<&ProxyHttpClient>,
+) -> Result<HashMap<String, String>, Box<dyn StdError>> {
+    let client = SignedClient::new(config, proxy).await?;
+
+    // Organizations is a global service with one endpoint per partition.
+    // `--endpoint-url` applies here too, so mock servers see every call
+    let (default_endpoint, signing_region) = Partition::of_region(config.region())
+        .organizations_endpoint(config.use_fips().unwrap_or_default());
+    let endpoint = client.endpoint_url().unwrap_or(default_endpoint);
+
+    let mut names = HashMap::new();
+    let mut next_token = None;
+    loop {
+        let body = list_accounts(&client, endpoint, signing_region, next_token.as_deref()).await?;
+        let page = parse_page(&body)?;
+        names.extend(page.accounts);
+        next_token = page.next_token;
+        if next_token.is_none() {
+            return Ok(names);
+        }
+    }
+}
+
+/// Sends one signed `ListAccounts` request, returning the response body.
+async fn list_accounts(
+    client: &SignedClient,
+    endpoint: &str,
+    signing_region: &str,
+    next_token: Option<&str>,
+) -> Result<Vec<u8>, Box<dyn StdError>> {
+    let mut body = String::new();
+    let mut object = JsonObjectWriter::new(&mut body);
+    if let Some(next_token) = next_token {
+        object.key("NextToken").string(next_token);
+    }
+    object.finish();
+
+    client
+        .json(endpoint, SIGNING_NAME, signing_region, "1.1", TARGET, body)
+        .await
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Page {
+    accounts: Vec<(String, String)>,
+    next_token: Option<String>,
+}
+
+/// Parses a `ListAccounts` response into (ID, name) pairs and the next token.
+fn parse_page(body: &[u8]) -> Result<Page, Box<dyn StdError>> {
+    let document = expect_document(&mut json_token_iter(body).peekable())?;
+    let mut page = Page::default();
+    let Document::Object(response) = document else {
+        return Err("ListAccounts returned an unexpected response".into());
+    };
+    if let Some(Document::Array(accounts)) = response.get("Accounts") {
+        for account in accounts {
+            if let Document::Object(account) = account
+                && let (Some(Document::String(id)), Some(Document::String(name))) =
+                    (account.get("Id"), account.get("Name"))
+            {
+                page.accounts.push((id.clone(), name.clone()));
+            }
+        }
+    }
+    if let Some(Document::String(next_token)) = response.get("NextToken") {
+        page.next_token = Some(next_token.clone());
+    }
+    Ok(page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sigv4::json_error_message;
+
+    #[test]
+    fn parses_account_names_and_next_token() {
+        let page = parse_page(
+            br#"{"Accounts":[{"Id":"111111111111","Name":"prod","Status":"ACTIVE"},{"Id":"222222222222","Name":"staging"}],"NextToken":"page-2"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            page,
+            Page {
+                accounts: vec![
+                    ("111111111111".to_string(), "prod".to_string()),
+                    ("222222222222".to_string(), "staging".to_string()),
+                ],
+                next_token: Some("page-2".to_string()),
+            }
+        );
+        assert_eq!(
+            json_error_message("ListAccounts", 400, br#"{"__type":"AWSOrganizationsNotInUseException","Message":"not in an organization"}"#),
+            "ListAccounts failed: AWSOrganizationsNotInUseException: not in an organization"
+        );
+    }
+}
@@ -0,0 +1,439 @@
+//! A trace of the run for an OpenTelemetry collector (`--otlp-endpoint`): a
+//! span for the whole run, and a client span under it per Health API call
+//! with its region, attempts and the event ARNs and accounts it asked
+//! about. The trace is sent with OTLP/HTTP in JSON once the run ends.
+
+use crate::http_client::HttpClient;
+use crate::notify;
+use crate::stats::error_code;
+use aws_sdk_health::config::interceptors::FinalizerInterceptorContextRef;
+use aws_sdk_health::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_sdk_health::error::BoxError;
+use aws_sdk_health::operation::describe_affected_accounts_for_organization::DescribeAffectedAccountsForOrganizationInput;
+use aws_sdk_health::operation::describe_affected_entities::DescribeAffectedEntitiesInput;
+use aws_sdk_health::operation::describe_affected_entities_for_organization::DescribeAffectedEntitiesForOrganizationInput;
+use aws_sdk_health::operation::describe_event_details::DescribeEventDetailsInput;
+use aws_sdk_health::operation::describe_event_details_for_organization::DescribeEventDetailsForOrganizationInput;
+use aws_smithy_json::serialize::{JsonObjectWriter, JsonValueWriter};
+use aws_smithy_runtime_api::client::interceptors::context::{
+    BeforeSerializationInterceptorContextRef, BeforeTransmitInterceptorContextRef, Input,
+};
+use aws_smithy_runtime_api::client::orchestrator::Metadata;
+use aws_smithy_types::Number;
+use aws_smithy_types::config_bag::{Storable, StoreReplace};
+use aws_types::region::SigningRegion;
+use std::collections::hash_map::RandomState;
+use std::error::Error as StdError;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Spans per export request, to stay well under collectors' size limits.
+const MAX_BATCH_SPANS: usize = 1000;
+
+/// OTLP span kinds and status codes.
+const KIND_INTERNAL: u8 = 1;
+const KIND_CLIENT: u8 = 3;
+const STATUS_ERROR: u8 = 2;
+
+#[derive(Debug, Clone)]
+enum Value {
+    String(String),
+    Int(u64),
+    Strings(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+struct Span {
+    span_id: u64,
+    parent_span_id: Option<u64>,
+    name: String,
+    kind: u8,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<(&'static str, Value)>,
+    /// The error message, for a span that failed
+    error: Option<String>,
+}
+
+/// A call in flight, kept in the call's config bag from the first hook to
+/// the last.
+#[derive(Debug, Clone)]
+struct Call {
+    span_id: u64,
+    start: SystemTime,
+    attempts: u64,
+    attributes: Vec<(&'static str, Value)>,
+}
+
+impl Storable for Call {
+    type Storer = StoreReplace<Self>;
+}
+
+/// Records a span per API call of the clients it's an interceptor of.
+/// Clones share the same spans.
+#[derive(Debug, Clone)]
+pub struct Tracer {
+    trace_id: u128,
+    root_span_id: u64,
+    started: SystemTime,
+    spans: Arc<Mutex<Vec<Span>>>,
+}
+
+impl Tracer {
+    fn new() -> Self {
+        Tracer {
+            trace_id: (u128::from(random_id()) << 64) | u128::from(random_id()),
+            root_span_id: random_id(),
+            started: SystemTime::now(),
+            spans: Arc::default(),
+        }
+    }
+}
+
+/// Sends a [`Tracer`]'s spans to an OTLP/HTTP endpoint.
+pub struct Exporter {
+    http: HttpClient,
+    url: String,
+    headers: Vec<(String, String)>,
+    tracer: Tracer,
+}
+
+impl Exporter {
+    /// Exports to the collector at `endpoint`, e.g. `http://localhost:4318`.
+    pub fn new(http: HttpClient, endpoint: &str, headers: Vec<(String, String)>) -> Self {
+        Exporter {
+            http,
+            url: format!("{}/v1/traces", endpoint.trim_end_matches('/')),
+            headers,
+            tracer: Tracer::new(),
+        }
+    }
+
+    pub fn tracer(&self) -> &Tracer {
+        &self.tracer
+    }
+
+    /// Ends the run's span, failed with `error` if given, and sends it with
+    /// the spans of the API calls.
+    pub async fn export(
+        &self,
+        error: Option<&(dyn StdError + 'static)>,
+    ) -> Result<(), Box<dyn StdError>> {
+        let tracer = &self.tracer;
+        let mut spans = tracer.spans.lock().unwrap().clone();
+        spans.insert(
+            0,
+            Span {
+                span_id: tracer.root_span_id,
+                parent_span_id: None,
+                name: "aws9man".to_string(),
+                kind: KIND_INTERNAL,
+                start: tracer.started,
+                end: SystemTime::now(),
+                attributes: Vec::new(),
+                error: error.map(|err| err.to_string()),
+            },
+        );
+        let mut headers: Vec<(&str, &str)> = vec![("content-type", "application/json")];
+        headers.extend(
+            self.headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        );
+        for batch in spans.chunks(MAX_BATCH_SPANS) {
+            notify::post(
+                &self.http,
+                "OTLP endpoint",
+                &self.url,
+                &headers,
+                request_body(tracer.trace_id, batch),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+impl Intercept for Tracer {
+    fn name(&self) -> &'static str {
+        "Tracer"
+    }
+
+    // Operation metadata is in the config bag by now, like for `ApiStats`
+    fn read_before_serialization(
+        &self,
+        context: &BeforeSerializationInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let mut attributes = vec![
+            ("rpc.system", Value::String("aws-api".to_string())),
+            ("rpc.service", Value::String("Health".to_string())),
+        ];
+        if let Some(metadata) = cfg.load::<Metadata>() {
+            attributes.push(("rpc.method", Value::String(metadata.name().to_string())));
+        }
+        if let Some(region) = cfg.load::<SigningRegion>() {
+            attributes.push(("cloud.region", Value::String(region.as_ref().to_string())));
+        }
+        let (event_arns, accounts) = asked_about(context.input());
+        if !event_arns.is_empty() {
+            attributes.push(("aws.health.event_arns", Value::Strings(event_arns)));
+        }
+        if !accounts.is_empty() {
+            attributes.push(("aws.health.account_ids", Value::Strings(accounts)));
+        }
+        cfg.interceptor_state().store_put(Call {
+            span_id: random_id(),
+            start: SystemTime::now(),
+            attempts: 0,
+            attributes,
+        });
+        Ok(())
+    }
+
+    fn read_before_attempt(
+        &self,
+        _context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if let Some(mut call) = cfg.load::<Call>().cloned() {
+            call.attempts += 1;
+            cfg.interceptor_state().store_put(call);
+        }
+        Ok(())
+    }
+
+    fn read_after_execution(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let Some(call) = cfg.load::<Call>().cloned() else {
+            return Ok(());
+        };
+        let mut attributes = call.attributes;
+        attributes.push(("aws9man.attempts", Value::Int(call.attempts)));
+        if let Some(response) = context.response() {
+            attributes.push((
+                "http.response.status_code",
+                Value::Int(response.status().as_u16().into()),
+            ));
+            if let Some(request_id) = response.headers().get("x-amzn-requestid") {
+                attributes.push(("aws.request_id", Value::String(request_id.to_string())));
+            }
+        }
+        let error = match context.output_or_error() {
+            Some(Err(error)) => Some(
+                error_code(error)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| error.to_string()),
+            ),
+            _ => None,
+        };
+        let name = match cfg.load::<Metadata>() {
+            Some(metadata) => format!("Health.{}", metadata.name()),
+            None => "Health".to_string(),
+        };
+        self.spans.lock().unwrap().push(Span {
+            span_id: call.span_id,
+            parent_span_id: Some(self.root_span_id),
+            name,
+            kind: KIND_CLIENT,
+            start: call.start,
+            end: SystemTime::now(),
+            attributes,
+            error,
+        });
+        Ok(())
+    }
+}
+
+/// The event ARNs and accounts a call's `input` asks about, if any.
+fn asked_about(input: &Input) -> (Vec<String>, Vec<String>) {
+    if let Some(input) = input.downcast_ref::<DescribeEventDetailsInput>() {
+        return (input.event_arns().to_vec(), Vec::new());
+    }
+    if let Some(input) = input.downcast_ref::<DescribeAffectedEntitiesInput>() {
+        let arns = input.filter().map(|filter| filter.event_arns().to_vec());
+        return (arns.unwrap_or_default(), Vec::new());
+    }
+    if let Some(input) = input.downcast_ref::<DescribeEventDetailsForOrganizationInput>() {
+        let filters = input.organization_event_detail_filters();
+        return (
+            filters.iter().map(|f| f.event_arn().to_string()).collect(),
+            filters
+                .iter()
+                .filter_map(|f| f.aws_account_id().map(str::to_string))
+                .collect(),
+        );
+    }
+    if let Some(input) = input.downcast_ref::<DescribeAffectedEntitiesForOrganizationInput>() {
+        let filters = input.organization_entity_account_filters();
+        return (
+            filters.iter().map(|f| f.event_arn().to_string()).collect(),
+            filters
+                .iter()
+                .filter_map(|f| f.aws_account_id().map(str::to_string))
+                .collect(),
+        );
+    }
+    if let Some(input) = input.downcast_ref::<DescribeAffectedAccountsForOrganizationInput>() {
+        return (
+            input.event_arn().into_iter().map(str::to_string).collect(),
+            Vec::new(),
+        );
+    }
+    (Vec::new(), Vec::new())
+}
+
+/// A random, non-zero span or trace ID half: std's hashers are seeded
+/// randomly per `RandomState`.
+fn random_id() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    hasher.finish().max(1)
+}
+
+fn nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+/// An `ExportTraceServiceRequest` in OTLP's JSON encoding, where IDs are hex
+/// and 64-bit integers are strings.
+fn request_body(trace_id: u128, spans: &[Span]) -> String {
+    let mut body = String::new();
+    let mut request = JsonObjectWriter::new(&mut body);
+    let mut resource_spans = request.key("resourceSpans").start_array();
+    let mut resource_span = resource_spans.value().start_object();
+    let mut resource = resource_span.key("resource").start_object();
+    write_attributes(
+        resource.key("attributes"),
+        &[
+            ("service.name", Value::String("aws9man".to_string())),
+            (
+                "service.version",
+                Value::String(env!("CARGO_PKG_VERSION").to_string()),
+            ),
+        ],
+    );
+    resource.finish();
+    let mut scope_spans = resource_span.key("scopeSpans").start_array();
+    let mut scope_span = scope_spans.value().start_object();
+    let mut scope = scope_span.key("scope").start_object();
+    scope.key("name").string("aws9man");
+    scope.finish();
+    let mut array = scope_span.key("spans").start_array();
+    for span in spans {
+        let mut object = array.value().start_object();
+        object.key("traceId").string(&format!("{:032x}", trace_id));
+        object
+            .key("spanId")
+            .string(&format!("{:016x}", span.span_id));
+        if let Some(parent) = span.parent_span_id {
+            object
+                .key("parentSpanId")
+                .string(&format!("{:016x}", parent));
+        }
+        object.key("name").string(&span.name);
+        object.key("kind").number(Number::PosInt(span.kind.into()));
+        object.key("startTimeUnixNano").string(&nanos(span.start));
+        object.key("endTimeUnixNano").string(&nanos(span.end));
+        write_attributes(object.key("attributes"), &span.attributes);
+        if let Some(error) = &span.error {
+            let mut status = object.key("status").start_object();
+            status
+                .key("code")
+                .number(Number::PosInt(STATUS_ERROR.into()));
+            status.key("message").string(error);
+            status.finish();
+        }
+        object.finish();
+    }
+    array.finish();
+    scope_span.finish();
+    scope_spans.finish();
+    resource_span.finish();
+    resource_spans.finish();
+    request.finish();
+    body
+}
+
+fn write_attributes(writer: JsonValueWriter<'_>, attributes: &[(&str, Value)]) {
+    let mut array = writer.start_array();
+    for (key, value) in attributes {
+        let mut attribute = array.value().start_object();
+        attribute.key("key").string(key);
+        write_value(attribute.key("value"), value);
+        attribute.finish();
+    }
+    array.finish();
+}
+
+fn write_value(writer: JsonValueWriter<'_>, value: &Value) {
+    let mut object = writer.start_object();
+    match value {
+        Value::String(value) => object.key("stringValue").string(value),
+        Value::Int(value) => object.key("intValue").string(&value.to_string()),
+        Value::Strings(values) => {
+            let mut array_value = object.key("arrayValue").start_object();
+            let mut array = array_value.key("values").start_array();
+            for value in values {
+                write_value(array.value(), &Value::String(value.clone()));
+            }
+            array.finish();
+            array_value.finish();
+        }
+    }
+    object.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn encodes_spans_as_otlp_json() {
+        let start = UNIX_EPOCH + Duration::from_secs(1);
+        let span = Span {
+            span_id: 0xab,
+            parent_span_id: Some(1),
+            name: "Health.DescribeEvents".to_string(),
+            kind: KIND_CLIENT,
+            start,
+            end: start + Duration::from_millis(5),
+            attributes: vec![
+                ("aws9man.attempts", Value::Int(2)),
+                (
+                    "aws.health.event_arns",
+                    Value::Strings(vec!["arn".to_string()]),
+                ),
+            ],
+            error: Some("ThrottlingException".to_string()),
+        };
+        let body = request_body(0xff, &[span]);
+        assert!(body.contains(
+            "\"spans\":[{\"traceId\":\"000000000000000000000000000000ff\",\
+             \"spanId\":\"00000000000000ab\",\"parentSpanId\":\"0000000000000001\",\
+             \"name\":\"Health.DescribeEvents\",\"kind\":3,\
+             \"startTimeUnixNano\":\"1000000000\",\"endTimeUnixNano\":\"1005000000\",\
+             \"attributes\":[{\"key\":\"aws9man.attempts\",\"value\":{\"intValue\":\"2\"}},\
+             {\"key\":\"aws.health.event_arns\",\"value\":{\"arrayValue\":{\"values\":\
+             [{\"stringValue\":\"arn\"}]}}}],\
+             \"status\":{\"code\":2,\"message\":\"ThrottlingException\"}}]"
+        ));
+        assert_ne!(random_id(), random_id());
+    }
+}
@@ -0,0 +1,1339 @@
+use crate::tz::{TimeFormat, Zone};
+use crate::{AffectedEntity, HealthEvent};
+use aws_smithy_json::serialize::JsonObjectWriter;
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::{Args, ValueEnum};
+use csv::{QuoteStyle, ReaderBuilder, Terminator, Writer, WriterBuilder};
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error as StdError;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+mod atom;
+mod html;
+mod ics;
+mod influx;
+mod parquet;
+mod xlsx;
+mod zip;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Comma-separated values, one row per event
+    #[default]
+    Csv,
+    /// A single JSON array of events
+    Json,
+    /// Newline-delimited JSON, one object per event
+    Jsonl,
+    /// A YAML sequence of events
+    Yaml,
+    /// A self-contained, sortable HTML report
+    Html,
+    /// Apache Parquet with typed timestamps, e.g. for Athena
+    Parquet,
+    /// Excel workbook with an events sheet and an affected entities sheet
+    Xlsx,
+    /// iCalendar file of scheduled changes
+    Ics,
+    /// Atom feed, one entry per event
+    Atom,
+    /// InfluxDB line protocol, one point per event
+    Influx,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Html => "html",
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Xlsx => "xlsx",
+            OutputFormat::Ics => "ics",
+            OutputFormat::Atom => "atom",
+            OutputFormat::Influx => "lp",
+        }
+    }
+}
+
+// How CSV output is written. (A doc comment here would replace the program
+// description in --help.)
+#[derive(Args, Debug, Clone)]
+pub struct CsvDialect {
+    /// CSV field delimiter: comma, tab, semicolon, pipe or any single
+    /// character
+    #[arg(long = "csv-delimiter", env = "AWS9MAN_CSV_DELIMITER", value_name = "DELIMITER", default_value = "comma", value_parser = parse_delimiter)]
+    pub delimiter: u8,
+
+    /// Which CSV fields to quote
+    #[arg(long = "csv-quote", env = "AWS9MAN_CSV_QUOTE", value_enum, default_value_t = CsvQuote::Necessary)]
+    pub quote: CsvQuote,
+
+    /// Line ending after each CSV record
+    #[arg(long = "csv-terminator", env = "AWS9MAN_CSV_TERMINATOR", value_enum, default_value_t = CsvTerminator::Crlf)]
+    pub terminator: CsvTerminator,
+
+    /// Start CSV files with a UTF-8 byte order mark and turn line breaks
+    /// inside fields into plain \n, so Excel opens them correctly
+    #[arg(long, env = "AWS9MAN_EXCEL_SAFE")]
+    pub excel_safe: bool,
+
+    /// How time columns are shown, from --time-format
+    #[arg(skip)]
+    pub(crate) time_format: TimeFormat,
+
+    /// The zone time columns are shown in, from --tz
+    #[arg(skip)]
+    pub(crate) zone: Option<Zone>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CsvQuote {
+    /// Only fields containing the delimiter, quotes or line breaks
+    Necessary,
+    /// Every field
+    Always,
+    /// Every field that isn't a number
+    NonNumeric,
+    /// No field, even if that makes the file ambiguous
+    Never,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CsvTerminator {
+    /// \r\n, as RFC 4180 and Excel expect
+    Crlf,
+    /// \n
+    Lf,
+}
+
+/// The CLI's defaults: comma-separated, quoted where necessary, with CRLF
+/// line endings.
+impl Default for CsvDialect {
+    fn default() -> Self {
+        CsvDialect {
+            delimiter: b',',
+            quote: CsvQuote::Necessary,
+            terminator: CsvTerminator::Crlf,
+            excel_safe: false,
+            time_format: TimeFormat::Iso8601,
+            zone: None,
+        }
+    }
+}
+
+impl CsvDialect {
+    /// A writer adding rows to existing CSV output.
+    fn writer<W: Write>(&self, out: W) -> CsvWriter<W> {
+        let writer = WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .quote_style(match self.quote {
+                CsvQuote::Necessary => QuoteStyle::Necessary,
+                CsvQuote::Always => QuoteStyle::Always,
+                CsvQuote::NonNumeric => QuoteStyle::NonNumeric,
+                CsvQuote::Never => QuoteStyle::Never,
+            })
+            .terminator(match self.terminator {
+                CsvTerminator::Crlf => Terminator::CRLF,
+                CsvTerminator::Lf => Terminator::Any(b'\n'),
+            })
+            .from_writer(out);
+        CsvWriter {
+            writer,
+            excel_safe: self.excel_safe,
+            time_format: self.time_format.clone(),
+            zone: self.zone.clone(),
+        }
+    }
+
+    /// A writer for new CSV output, starting with `header`.
+    fn start<W: Write>(&self, mut out: W, header: &[&str]) -> io::Result<CsvWriter<W>> {
+        if self.excel_safe {
+            out.write_all("\u{feff}".as_bytes())?;
+        }
+        let mut writer = self.writer(out);
+        writer.write_record(header)?;
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+/// A CSV writer that applies `--excel-safe` to every field.
+pub struct CsvWriter<W: Write> {
+    writer: Writer<W>,
+    excel_safe: bool,
+    time_format: TimeFormat,
+    zone: Option<Zone>,
+}
+
+impl<W: Write> CsvWriter<W> {
+    fn write_record(&mut self, fields: &[&str]) -> io::Result<()> {
+        if !self.excel_safe {
+            return Ok(self.writer.write_record(fields)?);
+        }
+        // Excel shows a stray character for the \r of a line break in a cell
+        let fields = fields
+            .iter()
+            .map(|field| field.replace("\r\n", "\n").replace('\r', "\n"));
+        Ok(self.writer.write_record(fields)?)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn time(&self, time: Option<DateTime<Utc>>) -> String {
+        time_text(time, &self.time_format, self.zone.as_ref())
+    }
+}
+
+fn parse_delimiter(value: &str) -> Result<u8, String> {
+    match value {
+        "comma" => Ok(b','),
+        "tab" | "\\t" => Ok(b'\t'),
+        "semicolon" => Ok(b';'),
+        "pipe" => Ok(b'|'),
+        _ if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+        _ => Err("expected comma, tab, semicolon, pipe or a single ASCII character".to_string()),
+    }
+}
+
+const CSV_HEADER: [&str; 7] = [
+    "Timestamp",
+    "ARN",
+    "Detail",
+    "Affected Entities",
+    "Affected Accounts",
+    "Account Name",
+    "Last Updated",
+];
+
+/// Identifies a CSV row for `--append`: the event ARN, when it was last
+/// updated and the accounts the row is for.
+pub type CsvRowKey = (String, String, String);
+
+pub fn csv_row_key(event: &HealthEvent, dialect: &CsvDialect) -> CsvRowKey {
+    (
+        event.arn.clone(),
+        time_text(
+            event.last_updated_time,
+            &dialect.time_format,
+            dialect.zone.as_ref(),
+        ),
+        event.affected_accounts.join(", "),
+    )
+}
+
+/// A time column, in `zone` if given and UTC otherwise.
+fn time_text(time: Option<DateTime<Utc>>, format: &TimeFormat, zone: Option<&Zone>) -> String {
+    time.map(|time| format.format(time, zone))
+        .unwrap_or_default()
+}
+
+/// Reads the keys of the rows already in the CSV report at `path`, or `None`
+/// if there's no report there yet.
+pub fn read_csv_keys(
+    path: &Path,
+    dialect: &CsvDialect,
+) -> Result<Option<HashSet<CsvRowKey>>, Box<dyn StdError>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    if file.metadata()?.len() == 0 {
+        return Ok(None);
+    }
+    let mut reader = ReaderBuilder::new()
+        .delimiter(dialect.delimiter)
+        .from_reader(file);
+    if reader.headers()?.iter().ne(CSV_HEADER) {
+        return Err(format!(
+            "can't append to {}: its columns aren't those of a CSV report",
+            path.display()
+        )
+        .into());
+    }
+    let mut keys = HashSet::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |index| record.get(index).unwrap_or_default().to_string();
+        keys.insert((field(1), field(6), field(4)));
+    }
+    Ok(Some(keys))
+}
+
+/// Creates the output file at `path`, refusing to replace an existing one
+/// unless `force` is set.
+pub fn create_file(path: &Path, force: bool) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    options.open(path).map_err(|err| match err.kind() {
+        io::ErrorKind::AlreadyExists => io::Error::new(
+            err.kind(),
+            format!(
+                "{} already exists; use --force to replace it or --append to add to it",
+                path.display()
+            ),
+        ),
+        _ => err,
+    })
+}
+
+/// Writes events to `W` as they are fetched, in the chosen format.
+pub enum EventWriter<W: Write> {
+    Csv(Box<CsvWriter<W>>),
+    Json {
+        out: W,
+        first: bool,
+    },
+    Jsonl(W),
+    Yaml {
+        out: W,
+        empty: bool,
+    },
+    Html(W),
+    Ics(W),
+    Atom(W),
+    Influx(W),
+    /// Formats that need every event up front are buffered until `finish`
+    Buffered {
+        format: OutputFormat,
+        out: W,
+        events: Vec<HealthEvent>,
+    },
+}
+
+impl<W: Write> EventWriter<W> {
+    pub fn new(format: OutputFormat, mut out: W, csv: &CsvDialect) -> io::Result<Self> {
+        match format {
+            OutputFormat::Csv => {
+                let writer = csv.start(out, &CSV_HEADER)?;
+                Ok(EventWriter::Csv(Box::new(writer)))
+            }
+            OutputFormat::Json => {
+                out.write_all(b"[")?;
+                Ok(EventWriter::Json { out, first: true })
+            }
+            OutputFormat::Jsonl => Ok(EventWriter::Jsonl(out)),
+            OutputFormat::Yaml => Ok(EventWriter::Yaml { out, empty: true }),
+            OutputFormat::Html => {
+                html::write_header(&mut out)?;
+                Ok(EventWriter::Html(out))
+            }
+            OutputFormat::Ics => {
+                ics::write_header(&mut out)?;
+                Ok(EventWriter::Ics(out))
+            }
+            OutputFormat::Atom => {
+                atom::write_header(&mut out)?;
+                Ok(EventWriter::Atom(out))
+            }
+            OutputFormat::Influx => Ok(EventWriter::Influx(out)),
+            OutputFormat::Parquet | OutputFormat::Xlsx => Ok(EventWriter::Buffered {
+                format,
+                out,
+                events: Vec::new(),
+            }),
+        }
+    }
+
+    /// Adds rows to an existing CSV report on `out`, without a header.
+    pub fn append_csv(out: W, csv: &CsvDialect) -> Self {
+        EventWriter::Csv(Box::new(csv.writer(out)))
+    }
+
+    pub fn write(&mut self, event: &HealthEvent) -> io::Result<()> {
+        match self {
+            EventWriter::Csv(writer) => {
+                writer.write_record(&[
+                    &event.timestamp,
+                    &event.arn,
+                    &event.detail,
+                    &event.entities_text(),
+                    &event.affected_accounts.join(", "),
+                    &event.account_name_list().join(", "),
+                    &writer.time(event.last_updated_time),
+                ])?;
+                // Flush every row so an interrupted run still leaves every
+                // event fetched so far on disk
+                writer.flush()?;
+            }
+            EventWriter::Json { out, first } => {
+                if !*first {
+                    out.write_all(b",")?;
+                }
+                *first = false;
+                out.write_all(event_to_json(event).as_bytes())?;
+            }
+            EventWriter::Jsonl(out) => {
+                // Flush every line so downstream consumers see events immediately
+                writeln!(out, "{}", event_to_json(event))?;
+                out.flush()?;
+            }
+            EventWriter::Yaml { out, empty } => {
+                *empty = false;
+                out.write_all(event_to_yaml(event).as_bytes())?;
+            }
+            EventWriter::Html(out) => html::write_event(out, event)?,
+            EventWriter::Ics(out) => ics::write_event(out, event)?,
+            EventWriter::Atom(out) => atom::write_event(out, event)?,
+            EventWriter::Influx(out) => influx::write_event(out, event)?,
+            EventWriter::Buffered { events, .. } => events.push(event.clone()),
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            EventWriter::Csv(mut writer) => writer.flush(),
+            EventWriter::Json { mut out, .. } => {
+                out.write_all(b"]\n")?;
+                out.flush()
+            }
+            EventWriter::Jsonl(mut out) => out.flush(),
+            EventWriter::Yaml { mut out, empty } => {
+                if empty {
+                    out.write_all(b"[]\n")?;
+                }
+                out.flush()
+            }
+            EventWriter::Html(mut out) => {
+                html::write_footer(&mut out)?;
+                out.flush()
+            }
+            EventWriter::Ics(mut out) => {
+                ics::write_footer(&mut out)?;
+                out.flush()
+            }
+            EventWriter::Atom(mut out) => {
+                atom::write_footer(&mut out)?;
+                out.flush()
+            }
+            EventWriter::Influx(mut out) => out.flush(),
+            EventWriter::Buffered {
+                format,
+                mut out,
+                events,
+            } => {
+                match format {
+                    OutputFormat::Parquet => parquet::write_events(&mut out, &events)?,
+                    OutputFormat::Xlsx => xlsx::write_events(&mut out, &events)?,
+                    _ => unreachable!("{:?} output is not buffered", format),
+                }
+                out.flush()
+            }
+        }
+    }
+}
+
+const ENTITIES_CSV_HEADER: [&str; 6] = [
+    "Event ARN",
+    "Account ID",
+    "Entity ARN",
+    "Entity Value",
+    "Status",
+    "Last Updated",
+];
+
+/// Writes affected entities as a CSV table of their own, one row per entity
+/// keyed by event ARN, to join with the events report.
+pub struct EntityCsvWriter<W: Write>(CsvWriter<W>);
+
+impl<W: Write> EntityCsvWriter<W> {
+    pub fn new(out: W, csv: &CsvDialect) -> io::Result<Self> {
+        Ok(EntityCsvWriter(csv.start(out, &ENTITIES_CSV_HEADER)?))
+    }
+
+    pub fn write(&mut self, event: &HealthEvent) -> io::Result<()> {
+        for entity in &event.affected_entities {
+            self.0.write_record(&[
+                event.arn.as_str(),
+                entity.account_id.as_deref().unwrap_or_default(),
+                entity.arn.as_deref().unwrap_or_default(),
+                &entity.value,
+                entity.status.as_deref().unwrap_or_default(),
+                &self.0.time(entity.last_updated_time),
+            ])?;
+        }
+        self.0.flush()
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SplitBy {
+    /// One file per affected member account
+    Account,
+}
+
+/// Writes every event to one file per affected account under `dir`, named
+/// `<account>_aws_health.<ext>`. Each file only lists that account's entities.
+pub struct AccountSplitWriter {
+    dir: PathBuf,
+    format: OutputFormat,
+    csv: CsvDialect,
+    explode_entities: bool,
+    force: bool,
+    writers: BTreeMap<String, EventWriter<BufWriter<File>>>,
+}
+
+impl AccountSplitWriter {
+    pub fn new(
+        dir: PathBuf,
+        format: OutputFormat,
+        csv: CsvDialect,
+        explode_entities: bool,
+        force: bool,
+    ) -> Self {
+        AccountSplitWriter {
+            dir,
+            format,
+            csv,
+            explode_entities,
+            force,
+            writers: BTreeMap::new(),
+        }
+    }
+
+    pub fn write(&mut self, event: &HealthEvent) -> io::Result<()> {
+        for account in &event.affected_accounts {
+            let writer = match self.writers.get_mut(account) {
+                Some(writer) => writer,
+                None => {
+                    let file = create_file(&self.path(account), self.force)?;
+                    let writer = EventWriter::new(self.format, BufWriter::new(file), &self.csv)?;
+                    self.writers.entry(account.clone()).or_insert(writer)
+                }
+            };
+            let event = event.for_account(account);
+            if self.explode_entities {
+                for row in event.per_entity() {
+                    writer.write(&row)?;
+                }
+            } else {
+                writer.write(&event)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finishes every file, returning their paths.
+    pub fn finish(self) -> io::Result<Vec<PathBuf>> {
+        let paths = self
+            .writers
+            .keys()
+            .map(|account| self.path(account))
+            .collect();
+        for writer in self.writers.into_values() {
+            writer.finish()?;
+        }
+        Ok(paths)
+    }
+
+    fn path(&self, account: &str) -> PathBuf {
+        self.dir.join(format!(
+            "{}_aws_health.{}",
+            account,
+            self.format.extension()
+        ))
+    }
+}
+
+/// Writes events under `dir` in a Hive-style layout,
+/// `region=<region>/year=<yyyy>/month=<mm>/<file>`, partitioned by the event's
+/// region and start time, so Glue can crawl it and Athena query it directly.
+pub struct PartitionedWriter {
+    dir: PathBuf,
+    file_name: String,
+    format: OutputFormat,
+    csv: CsvDialect,
+    explode_entities: bool,
+    force: bool,
+    writers: BTreeMap<PathBuf, EventWriter<BufWriter<File>>>,
+}
+
+/// Hive's name for the partition of rows without a value.
+const DEFAULT_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+impl PartitionedWriter {
+    /// Every partition gets a file named `file_name`.
+    pub fn new(
+        dir: PathBuf,
+        file_name: String,
+        format: OutputFormat,
+        csv: CsvDialect,
+        explode_entities: bool,
+        force: bool,
+    ) -> Self {
+        PartitionedWriter {
+            dir,
+            file_name,
+            format,
+            csv,
+            explode_entities,
+            force,
+            writers: BTreeMap::new(),
+        }
+    }
+
+    pub fn write(&mut self, event: &HealthEvent) -> io::Result<()> {
+        let path = self.partition(event).join(&self.file_name);
+        let writer = match self.writers.get_mut(&path) {
+            Some(writer) => writer,
+            None => {
+                fs::create_dir_all(path.parent().expect("partitions are directories"))?;
+                let file = create_file(&path, self.force)?;
+                let writer = EventWriter::new(self.format, BufWriter::new(file), &self.csv)?;
+                self.writers.entry(path).or_insert(writer)
+            }
+        };
+        if self.explode_entities {
+            for row in event.per_entity() {
+                writer.write(&row)?;
+            }
+            Ok(())
+        } else {
+            writer.write(event)
+        }
+    }
+
+    /// Finishes every file, returning their paths.
+    pub fn finish(self) -> io::Result<Vec<PathBuf>> {
+        let mut paths = Vec::with_capacity(self.writers.len());
+        for (path, writer) in self.writers {
+            writer.finish()?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    fn partition(&self, event: &HealthEvent) -> PathBuf {
+        let region = match event.region.as_str() {
+            "" => DEFAULT_PARTITION,
+            region => region,
+        };
+        let (year, month) = match event.start_time {
+            Some(time) => (time.format("%Y").to_string(), time.format("%m").to_string()),
+            None => (DEFAULT_PARTITION.to_string(), DEFAULT_PARTITION.to_string()),
+        };
+        self.dir
+            .join(format!("region={}", region))
+            .join(format!("year={}", year))
+            .join(format!("month={}", month))
+    }
+}
+
+/// The event as a JSON object, as written in JSON reports. Its keys are
+/// those `HealthEvent` serializes with, plus `entities_by_account`.
+pub fn event_to_json(event: &HealthEvent) -> String {
+    let mut json = String::new();
+    let mut object = JsonObjectWriter::new(&mut json);
+    object.key("timestamp").string(&event.timestamp);
+    for (key, time) in event_times(event) {
+        match time {
+            Some(time) => object.key(key).string(&time),
+            None => object.key(key).null(),
+        }
+    }
+    for (key, value) in event_fields(event) {
+        match value {
+            Some(value) => object.key(key).string(value),
+            None => object.key(key).null(),
+        }
+    }
+    object.key("detail").string(&event.detail);
+    let mut entities = object.key("affected_entities").start_array();
+    for entity in &event.affected_entities {
+        let mut object = entities.value().start_object();
+        for (key, value) in entity_fields(entity) {
+            match value {
+                Some(value) => object.key(key).string(&value),
+                None => object.key(key).null(),
+            }
+        }
+        let mut tags = object.key("tags").start_object();
+        for (key, value) in &entity.tags {
+            tags.key(key).string(value);
+        }
+        tags.finish();
+        object.finish();
+    }
+    entities.finish();
+    let mut accounts = object.key("affected_accounts").start_array();
+    for account in &event.affected_accounts {
+        accounts.value().string(account);
+    }
+    accounts.finish();
+    let mut names = object.key("account_names").start_object();
+    for (account, name) in &event.account_names {
+        names.key(account).string(name);
+    }
+    names.finish();
+    let mut by_account = object.key("entities_by_account").start_object();
+    for (account, values) in entities_by_account(event) {
+        let mut list = by_account.key(account).start_array();
+        for value in values {
+            list.value().string(value);
+        }
+        list.finish();
+    }
+    by_account.finish();
+    object.finish();
+    json
+}
+
+fn event_to_yaml(event: &HealthEvent) -> String {
+    let mut yaml = String::new();
+    yaml.push_str(&format!("- timestamp: {}\n", yaml_string(&event.timestamp)));
+    for (key, time) in event_times(event) {
+        yaml.push_str(&format!("  {}: {}\n", key, yaml_option(time.as_deref())));
+    }
+    for (key, value) in event_fields(event) {
+        yaml.push_str(&format!("  {}: {}\n", key, yaml_option(value)));
+    }
+    if event.detail.contains('\n') && !event.detail.contains('\r') {
+        // Multi-line descriptions read much better as literal blocks; the
+        // explicit indentation indicator keeps leading spaces intact
+        yaml.push_str("  detail: |2-\n");
+        for line in event.detail.lines() {
+            if line.is_empty() {
+                yaml.push('\n');
+            } else {
+                yaml.push_str(&format!("    {}\n", line));
+            }
+        }
+    } else {
+        yaml.push_str(&format!("  detail: {}\n", yaml_string(&event.detail)));
+    }
+    if event.affected_entities.is_empty() {
+        yaml.push_str("  affected_entities: []\n");
+    } else {
+        yaml.push_str("  affected_entities:\n");
+        for entity in &event.affected_entities {
+            for (index, (key, value)) in entity_fields(entity).into_iter().enumerate() {
+                let indent = if index == 0 { "    - " } else { "      " };
+                yaml.push_str(&format!(
+                    "{}{}: {}\n",
+                    indent,
+                    key,
+                    yaml_option(value.as_deref())
+                ));
+            }
+            if entity.tags.is_empty() {
+                yaml.push_str("      tags: {}\n");
+            } else {
+                yaml.push_str("      tags:\n");
+                for (key, value) in &entity.tags {
+                    yaml.push_str(&format!(
+                        "        {}: {}\n",
+                        yaml_string(key),
+                        yaml_string(value)
+                    ));
+                }
+            }
+        }
+    }
+    push_yaml_list(&mut yaml, "affected_accounts", &event.affected_accounts);
+    if event.account_names.is_empty() {
+        yaml.push_str("  account_names: {}\n");
+    } else {
+        yaml.push_str("  account_names:\n");
+        for (account, name) in &event.account_names {
+            yaml.push_str(&format!(
+                "    {}: {}\n",
+                yaml_string(account),
+                yaml_string(name)
+            ));
+        }
+    }
+    let by_account = entities_by_account(event);
+    if by_account.is_empty() {
+        yaml.push_str("  entities_by_account: {}\n");
+    } else {
+        yaml.push_str("  entities_by_account:\n");
+        for (account, values) in by_account {
+            yaml.push_str(&format!("    {}:\n", yaml_string(account)));
+            for value in values {
+                yaml.push_str(&format!("      - {}\n", yaml_string(value)));
+            }
+        }
+    }
+    yaml
+}
+
+/// The event's times as `serde` writes them: RFC 3339 in UTC, with as many
+/// fractional digits as needed.
+fn event_times(event: &HealthEvent) -> [(&'static str, Option<String>); 3] {
+    [
+        ("start_time", event.start_time),
+        ("end_time", event.end_time),
+        ("last_updated_time", event.last_updated_time),
+    ]
+    .map(|(key, time)| (key, time.map(rfc3339)))
+}
+
+/// The event's scalar fields between its times and its description.
+fn event_fields(event: &HealthEvent) -> [(&'static str, Option<&str>); 8] {
+    [
+        ("arn", Some(event.arn.as_str())),
+        ("service", Some(event.service.as_str())),
+        ("region", Some(event.region.as_str())),
+        ("availability_zone", event.availability_zone.as_deref()),
+        ("status", Some(event.status.as_str())),
+        ("event_type_code", Some(event.event_type_code.as_str())),
+        (
+            "event_type_category",
+            Some(event.event_type_category.as_str()),
+        ),
+        ("event_scope_code", Some(event.event_scope_code.as_str())),
+    ]
+}
+
+/// An entity's fields other than its tags.
+fn entity_fields(entity: &AffectedEntity) -> [(&'static str, Option<String>); 5] {
+    [
+        ("value", Some(entity.value.clone())),
+        ("account_id", entity.account_id.clone()),
+        ("arn", entity.arn.clone()),
+        ("status", entity.status.clone()),
+        ("last_updated_time", entity.last_updated_time.map(rfc3339)),
+    ]
+}
+
+fn rfc3339(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// Groups the entity values that have a known owning account by account.
+fn entities_by_account(event: &HealthEvent) -> BTreeMap<&str, Vec<&str>> {
+    let mut by_account: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for entity in &event.affected_entities {
+        if let Some(account) = &entity.account_id {
+            by_account.entry(account).or_default().push(&entity.value);
+        }
+    }
+    by_account
+}
+
+fn push_yaml_list<S: AsRef<str>>(yaml: &mut String, key: &str, values: &[S]) {
+    if values.is_empty() {
+        yaml.push_str(&format!("  {}: []\n", key));
+    } else {
+        yaml.push_str(&format!("  {}:\n", key));
+        for value in values {
+            yaml.push_str(&format!("    - {}\n", yaml_string(value.as_ref())));
+        }
+    }
+}
+
+/// Escapes `value` for use in XML (and HTML) text and attributes.
+pub(crate) fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Quotes `value` as a YAML scalar, or writes `null` for none.
+fn yaml_option(value: Option<&str>) -> String {
+    value.map_or_else(|| "null".to_string(), yaml_string)
+}
+
+/// Quotes `value` as a double-quoted YAML scalar.
+fn yaml_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// An event with every field set, and an entity with none of its optional
+    /// ones.
+    pub(super) fn sample_event() -> HealthEvent {
+        let time = |hour| Some(Utc.with_ymd_and_hms(2024, 3, 6, hour, 0, 0).unwrap());
+        HealthEvent {
+            timestamp: "2024-03-06 09:00:00".to_string(),
+            start_time: time(9),
+            end_time: time(11),
+            last_updated_time: time(10),
+            arn: "arn:aws:health:us-east-1::event/EC2/X/1".to_string(),
+            service: "EC2".to_string(),
+            region: "us-east-1".to_string(),
+            availability_zone: Some("us-east-1a".to_string()),
+            status: "closed".to_string(),
+            event_type_code: "AWS_EC2_OPERATIONAL_ISSUE".to_string(),
+            event_type_category: "issue".to_string(),
+            event_scope_code: "ACCOUNT_SPECIFIC".to_string(),
+            detail: "Elevated \"errors\"".to_string(),
+            affected_entities: vec![
+                AffectedEntity {
+                    value: "i-1".to_string(),
+                    account_id: Some("111111111111".to_string()),
+                    arn: Some("arn:entity-1".to_string()),
+                    status: Some("IMPAIRED".to_string()),
+                    last_updated_time: time(10),
+                    tags: BTreeMap::from([("Name".to_string(), "web".to_string())]),
+                },
+                AffectedEntity {
+                    value: "i-2".to_string(),
+                    account_id: None,
+                    arn: None,
+                    status: None,
+                    last_updated_time: None,
+                    tags: BTreeMap::new(),
+                },
+            ],
+            affected_accounts: vec!["111111111111".to_string()],
+            account_names: BTreeMap::from([("111111111111".to_string(), "prod".to_string())]),
+        }
+    }
+
+    /// What an `EventWriter` for `format` writes for `events`.
+    fn written(format: OutputFormat, csv: &CsvDialect, events: &[HealthEvent]) -> String {
+        let mut out = Vec::new();
+        let mut writer = EventWriter::new(format, &mut out, csv).unwrap();
+        for event in events {
+            writer.write(event).unwrap();
+        }
+        writer.finish().unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn writes_json_arrays() {
+        let first = sample_event();
+        let mut second = sample_event();
+        second.arn = "arn:aws:health:us-east-1::event/EC2/X/2".to_string();
+        let csv = CsvDialect::default();
+        assert_eq!(
+            written(OutputFormat::Json, &csv, &[first.clone(), second.clone()]),
+            format!("[{},{}]\n", event_to_json(&first), event_to_json(&second))
+        );
+        assert_eq!(written(OutputFormat::Json, &csv, &[]), "[]\n");
+    }
+
+    #[test]
+    fn streams_json_lines() {
+        let event = sample_event();
+        let mut out = Vec::new();
+        let mut writer =
+            EventWriter::new(OutputFormat::Jsonl, &mut out, &CsvDialect::default()).unwrap();
+        writer.write(&event).unwrap();
+        // Each line is out before the next event is fetched
+        let EventWriter::Jsonl(line) = &writer else {
+            panic!("not a JSON lines writer");
+        };
+        assert_eq!(**line, format!("{}\n", event_to_json(&event)).into_bytes());
+        writer.write(&event).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{0}\n{0}\n", event_to_json(&event))
+        );
+    }
+
+    #[test]
+    fn writes_yaml_sequences() {
+        let mut event = sample_event();
+        event.detail = "Elevated errors\n\n  Recovering".to_string();
+        assert_eq!(
+            written(OutputFormat::Yaml, &CsvDialect::default(), &[event]),
+            concat!(
+                "- timestamp: \"2024-03-06 09:00:00\"\n",
+                "  start_time: \"2024-03-06T09:00:00Z\"\n",
+                "  end_time: \"2024-03-06T11:00:00Z\"\n",
+                "  last_updated_time: \"2024-03-06T10:00:00Z\"\n",
+                "  arn: \"arn:aws:health:us-east-1::event/EC2/X/1\"\n",
+                "  service: \"EC2\"\n",
+                "  region: \"us-east-1\"\n",
+                "  availability_zone: \"us-east-1a\"\n",
+                "  status: \"closed\"\n",
+                "  event_type_code: \"AWS_EC2_OPERATIONAL_ISSUE\"\n",
+                "  event_type_category: \"issue\"\n",
+                "  event_scope_code: \"ACCOUNT_SPECIFIC\"\n",
+                "  detail: |2-\n",
+                "    Elevated errors\n",
+                "\n",
+                "      Recovering\n",
+                "  affected_entities:\n",
+                "    - value: \"i-1\"\n",
+                "      account_id: \"111111111111\"\n",
+                "      arn: \"arn:entity-1\"\n",
+                "      status: \"IMPAIRED\"\n",
+                "      last_updated_time: \"2024-03-06T10:00:00Z\"\n",
+                "      tags:\n",
+                "        \"Name\": \"web\"\n",
+                "    - value: \"i-2\"\n",
+                "      account_id: null\n",
+                "      arn: null\n",
+                "      status: null\n",
+                "      last_updated_time: null\n",
+                "      tags: {}\n",
+                "  affected_accounts:\n",
+                "    - \"111111111111\"\n",
+                "  account_names:\n",
+                "    \"111111111111\": \"prod\"\n",
+                "  entities_by_account:\n",
+                "    \"111111111111\":\n",
+                "      - \"i-1\"\n",
+            )
+        );
+        assert_eq!(
+            written(OutputFormat::Yaml, &CsvDialect::default(), &[]),
+            "[]\n"
+        );
+    }
+
+    #[test]
+    fn appends_to_csv_reports() {
+        let path = std::env::temp_dir().join(format!("aws9man-append-{}.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let csv = CsvDialect::default();
+        assert!(read_csv_keys(&path, &csv).unwrap().is_none());
+
+        let first = sample_event();
+        let mut writer =
+            EventWriter::new(OutputFormat::Csv, File::create(&path).unwrap(), &csv).unwrap();
+        writer.write(&first).unwrap();
+        writer.finish().unwrap();
+        let keys = read_csv_keys(&path, &csv).unwrap().unwrap();
+        assert_eq!(keys, HashSet::from([csv_row_key(&first, &csv)]));
+
+        // The same event updated later is a row of its own
+        let mut second = sample_event();
+        second.last_updated_time = Some(Utc.with_ymd_and_hms(2024, 3, 6, 12, 0, 0).unwrap());
+        let file = OpenOptions::new().append(true).open(&path).unwrap();
+        let mut writer = EventWriter::append_csv(file, &csv);
+        writer.write(&second).unwrap();
+        writer.finish().unwrap();
+        let keys = read_csv_keys(&path, &csv).unwrap().unwrap();
+        assert_eq!(
+            keys,
+            HashSet::from([csv_row_key(&first, &csv), csv_row_key(&second, &csv)])
+        );
+        assert_eq!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .matches("Timestamp,ARN")
+                .count(),
+            1
+        );
+
+        fs::write(&path, "ARN,Detail\r\n").unwrap();
+        let err = read_csv_keys(&path, &csv).unwrap_err();
+        assert!(err.to_string().contains("aren't those of a CSV report"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn writes_csv_dialects() {
+        let events = [sample_event()];
+        assert_eq!(
+            written(OutputFormat::Csv, &CsvDialect::default(), &events),
+            concat!(
+                "Timestamp,ARN,Detail,Affected Entities,Affected Accounts,Account Name,Last Updated\r\n",
+                "2024-03-06 09:00:00,arn:aws:health:us-east-1::event/EC2/X/1,\"Elevated \"\"errors\"\"\",",
+                "\"i-1 (111111111111), i-2\",111111111111,prod,2024-03-06T10:00:00Z\r\n",
+            )
+        );
+        let european = CsvDialect {
+            delimiter: parse_delimiter("semicolon").unwrap(),
+            quote: CsvQuote::Always,
+            terminator: CsvTerminator::Lf,
+            ..CsvDialect::default()
+        };
+        assert_eq!(
+            written(OutputFormat::Csv, &european, &events),
+            concat!(
+                "\"Timestamp\";\"ARN\";\"Detail\";\"Affected Entities\";\"Affected Accounts\";",
+                "\"Account Name\";\"Last Updated\"\n",
+                "\"2024-03-06 09:00:00\";\"arn:aws:health:us-east-1::event/EC2/X/1\";",
+                "\"Elevated \"\"errors\"\"\";\"i-1 (111111111111), i-2\";\"111111111111\";",
+                "\"prod\";\"2024-03-06T10:00:00Z\"\n",
+            )
+        );
+        assert_eq!(parse_delimiter("tab"), Ok(b'\t'));
+        assert!(parse_delimiter("::").is_err());
+    }
+
+    #[test]
+    fn writes_entities_keyed_by_event() {
+        let mut out = Vec::new();
+        let mut writer = EntityCsvWriter::new(&mut out, &CsvDialect::default()).unwrap();
+        let mut event = sample_event();
+        event.affected_entities[1].value = "db, primary".to_string();
+        writer.write(&event).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "Event ARN,Account ID,Entity ARN,Entity Value,Status,Last Updated\r\n",
+                "arn:aws:health:us-east-1::event/EC2/X/1,111111111111,arn:entity-1,i-1,IMPAIRED,",
+                "2024-03-06T10:00:00Z\r\n",
+                "arn:aws:health:us-east-1::event/EC2/X/1,,,\"db, primary\",,\r\n",
+            )
+        );
+    }
+
+    #[test]
+    fn explodes_entities_into_rows() {
+        let mut without_entities = sample_event();
+        without_entities.arn = "arn:aws:health:us-east-1::event/EC2/X/2".to_string();
+        without_entities.affected_entities.clear();
+        let rows: Vec<_> = [sample_event(), without_entities]
+            .iter()
+            .flat_map(HealthEvent::per_entity)
+            .collect();
+        assert_eq!(
+            written(OutputFormat::Csv, &CsvDialect::default(), &rows),
+            concat!(
+                "Timestamp,ARN,Detail,Affected Entities,Affected Accounts,Account Name,Last Updated\r\n",
+                "2024-03-06 09:00:00,arn:aws:health:us-east-1::event/EC2/X/1,\"Elevated \"\"errors\"\"\",",
+                "i-1 (111111111111),111111111111,prod,2024-03-06T10:00:00Z\r\n",
+                "2024-03-06 09:00:00,arn:aws:health:us-east-1::event/EC2/X/1,\"Elevated \"\"errors\"\"\",",
+                "i-2,111111111111,prod,2024-03-06T10:00:00Z\r\n",
+                "2024-03-06 09:00:00,arn:aws:health:us-east-1::event/EC2/X/2,\"Elevated \"\"errors\"\"\",",
+                ",111111111111,prod,2024-03-06T10:00:00Z\r\n",
+            )
+        );
+    }
+
+    #[test]
+    fn splits_events_by_account() {
+        let dir = std::env::temp_dir().join(format!("aws9man-split-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut event = sample_event();
+        event.affected_accounts.push("222222222222".to_string());
+        event
+            .account_names
+            .insert("222222222222".to_string(), "dev".to_string());
+        event.affected_entities[1].account_id = Some("222222222222".to_string());
+        let mut writer = AccountSplitWriter::new(
+            dir.clone(),
+            OutputFormat::Csv,
+            CsvDialect::default(),
+            false,
+            false,
+        );
+        writer.write(&event).unwrap();
+        let paths = writer.finish().unwrap();
+        assert_eq!(
+            paths,
+            [
+                dir.join("111111111111_aws_health.csv"),
+                dir.join("222222222222_aws_health.csv"),
+            ]
+        );
+        let row = |path: &Path| {
+            fs::read_to_string(path)
+                .unwrap()
+                .lines()
+                .nth(1)
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            row(&paths[0]),
+            "2024-03-06 09:00:00,arn:aws:health:us-east-1::event/EC2/X/1,\"Elevated \"\"errors\"\"\",\
+             i-1 (111111111111),111111111111,prod,2024-03-06T10:00:00Z"
+        );
+        assert_eq!(
+            row(&paths[1]),
+            "2024-03-06 09:00:00,arn:aws:health:us-east-1::event/EC2/X/1,\"Elevated \"\"errors\"\"\",\
+             i-2 (222222222222),222222222222,dev,2024-03-06T10:00:00Z"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn partitions_by_region_and_month() {
+        let dir = std::env::temp_dir().join(format!("aws9man-partitions-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut writer = PartitionedWriter::new(
+            dir.clone(),
+            "events.jsonl".to_string(),
+            OutputFormat::Jsonl,
+            CsvDialect::default(),
+            false,
+            false,
+        );
+        let march = sample_event();
+        let mut april = sample_event();
+        april.start_time = Some(Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap());
+        let mut global = sample_event();
+        global.region = String::new();
+        global.start_time = None;
+        for event in [&march, &april, &march, &global] {
+            writer.write(event).unwrap();
+        }
+        let paths = writer.finish().unwrap();
+
+        let partition = |path: &str| dir.join(path).join("events.jsonl");
+        let march_path = partition("region=us-east-1/year=2024/month=03");
+        let default_path = partition(
+            "region=__HIVE_DEFAULT_PARTITION__/year=__HIVE_DEFAULT_PARTITION__/month=__HIVE_DEFAULT_PARTITION__",
+        );
+        assert_eq!(
+            paths,
+            [
+                default_path.clone(),
+                march_path.clone(),
+                partition("region=us-east-1/year=2024/month=04"),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(&march_path).unwrap(),
+            format!("{0}\n{0}\n", event_to_json(&march))
+        );
+        assert_eq!(
+            fs::read_to_string(&default_path).unwrap(),
+            format!("{}\n", event_to_json(&global))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replaces_reports_only_when_forced() {
+        let path = std::env::temp_dir().join(format!("aws9man-force-{}.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        create_file(&path, false)
+            .unwrap()
+            .write_all(b"earlier report")
+            .unwrap();
+
+        let err = create_file(&path, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(
+            err.to_string()
+                .ends_with("already exists; use --force to replace it or --append to add to it")
+        );
+        assert_eq!(fs::read(&path).unwrap(), b"earlier report");
+
+        create_file(&path, true).unwrap().write_all(b"new").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn writes_excel_safe_csv() {
+        let mut event = sample_event();
+        event.detail = "Café\r\nerrors\rrising".to_string();
+        event.affected_entities.clear();
+        let excel = CsvDialect {
+            excel_safe: true,
+            ..CsvDialect::default()
+        };
+        assert_eq!(
+            written(OutputFormat::Csv, &excel, &[event]),
+            concat!(
+                "\u{feff}Timestamp,ARN,Detail,Affected Entities,Affected Accounts,Account Name,Last Updated\r\n",
+                "2024-03-06 09:00:00,arn:aws:health:us-east-1::event/EC2/X/1,\"Café\nerrors\nrising\",",
+                ",111111111111,prod,2024-03-06T10:00:00Z\r\n",
+            )
+        );
+    }
+
+    #[test]
+    fn shows_every_time_column_in_the_zone() {
+        let zone = Zone::fixed(7 * 3600);
+        let events = [sample_event().with_time_format(&TimeFormat::Iso8601, Some(&zone))];
+        let csv = CsvDialect {
+            zone: Some(zone),
+            ..CsvDialect::default()
+        };
+        // Started at 09:00Z and last updated at 10:00Z, as were its entities
+        let report = written(OutputFormat::Csv, &csv, &events);
+        let row: Vec<_> = report.lines().nth(1).unwrap().split(',').collect();
+        assert_eq!(row[0], "2024-03-06T16:00:00+07:00");
+        assert_eq!(row.last(), Some(&"2024-03-06T17:00:00+07:00"));
+
+        let mut entities = Vec::new();
+        let mut writer = EntityCsvWriter::new(&mut entities, &csv).unwrap();
+        writer.write(&events[0]).unwrap();
+        writer.finish().unwrap();
+        let entities = String::from_utf8(entities).unwrap();
+        assert!(entities.contains(",IMPAIRED,2024-03-06T17:00:00+07:00\r\n"));
+    }
+
+    #[test]
+    fn formats_every_time_column() {
+        let format = TimeFormat::parse("%d/%m/%Y %H:%M").unwrap();
+        let events = [sample_event().with_time_format(&format, None)];
+        let csv = CsvDialect {
+            time_format: format,
+            ..CsvDialect::default()
+        };
+        let report = written(OutputFormat::Csv, &csv, &events);
+        let row: Vec<_> = report.lines().nth(1).unwrap().split(',').collect();
+        assert_eq!(row[0], "06/03/2024 09:00");
+        assert_eq!(row.last(), Some(&"06/03/2024 10:00"));
+        assert_eq!(csv_row_key(&events[0], &csv).1, "06/03/2024 10:00");
+
+        let mut entities = Vec::new();
+        let mut writer = EntityCsvWriter::new(&mut entities, &csv).unwrap();
+        writer.write(&events[0]).unwrap();
+        writer.finish().unwrap();
+        let entities = String::from_utf8(entities).unwrap();
+        assert!(entities.contains(",IMPAIRED,06/03/2024 10:00\r\n"));
+    }
+
+    #[test]
+    fn json_has_every_field_of_the_model() {
+        assert_eq!(
+            event_to_json(&sample_event()),
+            concat!(
+                r#"{"timestamp":"2024-03-06 09:00:00","start_time":"2024-03-06T09:00:00Z","#,
+                r#""end_time":"2024-03-06T11:00:00Z","last_updated_time":"2024-03-06T10:00:00Z","#,
+                r#""arn":"arn:aws:health:us-east-1::event/EC2/X/1","service":"EC2","#,
+                r#""region":"us-east-1","availability_zone":"us-east-1a","status":"closed","#,
+                r#""event_type_code":"AWS_EC2_OPERATIONAL_ISSUE","event_type_category":"issue","#,
+                r#""event_scope_code":"ACCOUNT_SPECIFIC","detail":"Elevated \"errors\"","#,
+                r#""affected_entities":[{"value":"i-1","account_id":"111111111111","#,
+                r#""arn":"arn:entity-1","status":"IMPAIRED","#,
+                r#""last_updated_time":"2024-03-06T10:00:00Z","tags":{"Name":"web"}},"#,
+                r#"{"value":"i-2","account_id":null,"arn":null,"status":null,"#,
+                r#""last_updated_time":null,"tags":{}}],"#,
+                r#""affected_accounts":["111111111111"],"account_names":{"111111111111":"prod"},"#,
+                r#""entities_by_account":{"111111111111":["i-1"]}}"#,
+            )
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Atom (RFC 4287) feed output, for teams that follow events in a feed
+//! reader.
+
+use super::escape_xml as escape;
+use crate::HealthEvent;
+use chrono::{SecondsFormat, Utc};
+use std::io::{self, Write};
+
+const DASHBOARD_URL: &str = "https://health.aws.amazon.com/health/home#/account/event-log";
+
+pub fn write_header<W: Write>(out: &mut W) -> io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(out, r#"<feed xmlns="http://www.w3.org/2005/Atom">"#)?;
+    writeln!(out, "<id>urn:aws9man:health-events</id>")?;
+    writeln!(out, "<title>AWS Health events</title>")?;
+    writeln!(
+        out,
+        "<updated>{}</updated>",
+        Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+    )?;
+    writeln!(out, "<author><name>aws9man</name></author>")?;
+    writeln!(out, r#"<link rel="related" href="{}"/>"#, DASHBOARD_URL)
+}
+
+pub fn write_event<W: Write>(out: &mut W, event: &HealthEvent) -> io::Result<()> {
+    let updated = event
+        .last_updated_time
+        .or(event.start_time)
+        .unwrap_or_else(Utc::now);
+
+    writeln!(out, "<entry>")?;
+    writeln!(out, "<id>{}</id>", escape(&event.arn))?;
+    writeln!(
+        out,
+        "<title>{}</title>",
+        escape(&format!("{} {}", event.service, event.event_type_code))
+    )?;
+    writeln!(
+        out,
+        "<updated>{}</updated>",
+        updated.to_rfc3339_opts(SecondsFormat::Secs, true)
+    )?;
+    if let Some(start) = event.start_time {
+        writeln!(
+            out,
+            "<published>{}</published>",
+            start.to_rfc3339_opts(SecondsFormat::Secs, true)
+        )?;
+    }
+    writeln!(
+        out,
+        r#"<link rel="alternate" href="{}?eventID={}"/>"#,
+        DASHBOARD_URL,
+        escape(&event.arn)
+    )?;
+    if !event.event_type_category.is_empty() {
+        writeln!(
+            out,
+            r#"<category term="{}"/>"#,
+            escape(&event.event_type_category)
+        )?;
+    }
+    writeln!(
+        out,
+        r#"<content type="text">{}</content>"#,
+        escape(&event.detail)
+    )?;
+    writeln!(out, "</entry>")
+}
+
+pub fn write_footer<W: Write>(out: &mut W) -> io::Result<()> {
+    writeln!(out, "</feed>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_escaped_entries() {
+        let mut event = super::super::tests::sample_event();
+        event.detail = "Errors <rising> & \"recovering\"".to_string();
+        let mut out = Vec::new();
+        write_event(&mut out, &event).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "<entry>\n",
+                "<id>arn:aws:health:us-east-1::event/EC2/X/1</id>\n",
+                "<title>EC2 AWS_EC2_OPERATIONAL_ISSUE</title>\n",
+                "<updated>2024-03-06T10:00:00Z</updated>\n",
+                "<published>2024-03-06T09:00:00Z</published>\n",
+                "<link rel=\"alternate\" href=\"https://health.aws.amazon.com/health/home#/account/event-log",
+                "?eventID=arn:aws:health:us-east-1::event/EC2/X/1\"/>\n",
+                "<category term=\"issue\"/>\n",
+                "<content type=\"text\">Errors &lt;rising&gt; &amp; &quot;recovering&quot;</content>\n",
+                "</entry>\n",
+            )
+        );
+    }
+}
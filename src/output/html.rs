@@ -0,0 +1,129 @@
+use super::escape_xml as escape;
+use crate::HealthEvent;
+use std::io::{self, Write};
+
+const HEADER: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>AWS Health events</title>
+<style>
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; margin: 2em; color: #16191f; }
+h1 { font-size: 1.4em; }
+table.events { border-collapse: collapse; width: 100%; }
+table.events > thead th { background: #232f3e; color: #fff; text-align: left; padding: 0.5em; cursor: pointer; user-select: none; }
+table.events > thead th[data-dir="asc"]::after { content: " \25B2"; }
+table.events > thead th[data-dir="desc"]::after { content: " \25BC"; }
+table.events > tbody > tr > td { border-bottom: 1px solid #d5dbdb; padding: 0.5em; vertical-align: top; }
+table.events > tbody > tr:nth-child(even) { background: #f8f8f8; }
+td.arn { font-family: monospace; font-size: 0.85em; word-break: break-all; }
+details > summary { cursor: pointer; }
+details pre { white-space: pre-wrap; font-family: inherit; margin: 0.5em 0 0; }
+table.entities { border-collapse: collapse; font-family: monospace; font-size: 0.85em; }
+table.entities td { padding: 0.1em 0.5em; border: 1px solid #d5dbdb; }
+</style>
+</head>
+<body>
+<h1>AWS Health events</h1>
+<table class="events">
+<thead>
+<tr><th>Timestamp</th><th>ARN</th><th>Detail</th><th>Affected Entities</th><th>Affected Accounts</th></tr>
+</thead>
+<tbody>
+"#;
+
+const FOOTER: &str = r#"</tbody>
+</table>
+<script>
+document.querySelectorAll("table.events > thead th").forEach(function (th, column) {
+  th.addEventListener("click", function () {
+    var table = th.closest("table");
+    var body = table.tBodies[0];
+    var dir = th.dataset.dir === "asc" ? "desc" : "asc";
+    table.querySelectorAll("thead th").forEach(function (other) { delete other.dataset.dir; });
+    th.dataset.dir = dir;
+    var rows = Array.prototype.slice.call(body.rows);
+    rows.sort(function (a, b) {
+      var x = a.cells[column].dataset.sort || a.cells[column].textContent;
+      var y = b.cells[column].dataset.sort || b.cells[column].textContent;
+      var order = x.localeCompare(y, undefined, { numeric: true });
+      return dir === "asc" ? order : -order;
+    });
+    rows.forEach(function (row) { body.appendChild(row); });
+  });
+});
+</script>
+</body>
+</html>
+"#;
+
+pub fn write_header<W: Write>(out: &mut W) -> io::Result<()> {
+    out.write_all(HEADER.as_bytes())
+}
+
+pub fn write_event<W: Write>(out: &mut W, event: &HealthEvent) -> io::Result<()> {
+    let summary = event.detail.lines().next().unwrap_or_default();
+
+    writeln!(out, "<tr>")?;
+    writeln!(out, "<td>{}</td>", escape(&event.timestamp))?;
+    writeln!(out, "<td class=\"arn\">{}</td>", escape(&event.arn))?;
+    writeln!(
+        out,
+        "<td data-sort=\"{}\"><details><summary>{}</summary><pre>{}</pre></details></td>",
+        escape(summary),
+        escape(summary),
+        escape(&event.detail)
+    )?;
+    write!(out, "<td data-sort=\"{}\">", event.affected_entities.len())?;
+    if !event.affected_entities.is_empty() {
+        write!(out, "<table class=\"entities\">")?;
+        for entity in &event.affected_entities {
+            write!(out, "<tr><td>{}</td>", escape(&entity.value))?;
+            if let Some(account_id) = &entity.account_id {
+                write!(out, "<td>{}</td>", escape(account_id))?;
+            }
+            write!(out, "</tr>")?;
+        }
+        write!(out, "</table>")?;
+    }
+    writeln!(out, "</td>")?;
+    writeln!(
+        out,
+        "<td class=\"arn\">{}</td>",
+        escape(&event.accounts_text())
+    )?;
+    writeln!(out, "</tr>")
+}
+
+pub fn write_footer<W: Write>(out: &mut W) -> io::Result<()> {
+    out.write_all(FOOTER.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_escaped_rows() {
+        let mut event = super::super::tests::sample_event();
+        event.detail = "Errors <b>rising</b>\nin \"us-east-1\"".to_string();
+        let mut out = Vec::new();
+        write_event(&mut out, &event).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "<tr>\n",
+                "<td>2024-03-06 09:00:00</td>\n",
+                "<td class=\"arn\">arn:aws:health:us-east-1::event/EC2/X/1</td>\n",
+                "<td data-sort=\"Errors &lt;b&gt;rising&lt;/b&gt;\"><details>",
+                "<summary>Errors &lt;b&gt;rising&lt;/b&gt;</summary>",
+                "<pre>Errors &lt;b&gt;rising&lt;/b&gt;\nin &quot;us-east-1&quot;</pre>",
+                "</details></td>\n",
+                "<td data-sort=\"2\"><table class=\"entities\">",
+                "<tr><td>i-1</td><td>111111111111</td></tr><tr><td>i-2</td></tr></table></td>\n",
+                "<td class=\"arn\">prod (111111111111)</td>\n",
+                "</tr>\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//! iCalendar (RFC 5545) output for scheduled changes, so maintenance
+//! windows can be subscribed to from a calendar client.
+
+use crate::HealthEvent;
+use chrono::{DateTime, Utc};
+use std::io::{self, Write};
+
+const ICS_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+pub fn write_header<W: Write>(out: &mut W) -> io::Result<()> {
+    write_line(out, "BEGIN:VCALENDAR")?;
+    write_line(out, "VERSION:2.0")?;
+    write_line(out, "PRODID:-//aws9man//AWS Health scheduled changes//EN")?;
+    write_line(out, "CALSCALE:GREGORIAN")?;
+    write_line(out, "X-WR-CALNAME:AWS scheduled changes")
+}
+
+/// Writes `event` as a VEVENT. Only scheduled changes with a known start
+/// time end up in the calendar; everything else is skipped.
+pub fn write_event<W: Write>(out: &mut W, event: &HealthEvent) -> io::Result<()> {
+    let start = match event.start_time {
+        Some(start) if event.event_type_category == "scheduledChange" => start,
+        _ => return Ok(()),
+    };
+
+    write_line(out, "BEGIN:VEVENT")?;
+    write_line(out, &format!("UID:{}", escape(&event.arn)))?;
+    write_line(out, &format!("DTSTAMP:{}", format_time(Utc::now())))?;
+    write_line(out, &format!("DTSTART:{}", format_time(start)))?;
+    if let Some(end) = event.end_time {
+        write_line(out, &format!("DTEND:{}", format_time(end)))?;
+    }
+    write_line(
+        out,
+        &format!(
+            "SUMMARY:{}",
+            escape(&format!("{} {}", event.service, event.event_type_code))
+        ),
+    )?;
+    write_line(out, &format!("DESCRIPTION:{}", escape(&event.detail)))?;
+    write_line(out, "END:VEVENT")
+}
+
+pub fn write_footer<W: Write>(out: &mut W) -> io::Result<()> {
+    write_line(out, "END:VCALENDAR")
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format(ICS_TIME_FORMAT).to_string()
+}
+
+/// Escapes a TEXT property value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Writes a content line, folded to 75 octets as the RFC requires.
+fn write_line<W: Write>(out: &mut W, line: &str) -> io::Result<()> {
+    let mut rest = line;
+    let mut limit = 75;
+    while rest.len() > limit {
+        let mut split = limit;
+        while !rest.is_char_boundary(split) {
+            split -= 1;
+        }
+        out.write_all(&rest.as_bytes()[..split])?;
+        out.write_all(b"\r\n ")?;
+        rest = &rest[split..];
+        // Continuation lines start with a space, which counts towards the limit
+        limit = 74;
+    }
+    out.write_all(rest.as_bytes())?;
+    out.write_all(b"\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_folded_escaped_scheduled_changes() {
+        let mut event = super::super::tests::sample_event();
+        let mut out = Vec::new();
+        write_event(&mut out, &event).unwrap();
+        assert!(out.is_empty(), "only scheduled changes are calendar events");
+
+        event.event_type_category = "scheduledChange".to_string();
+        event.event_type_code = "AWS_EC2_MAINTENANCE_SCHEDULED".to_string();
+        event.detail =
+            "Instances i-1, i-2; reboot\nrequired during the window of the scheduled change"
+                .to_string();
+        write_event(&mut out, &event).unwrap();
+        let ics = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = ics.split_terminator("\r\n").collect();
+        assert!(lines[2].starts_with("DTSTAMP:") && lines[2].len() == 24);
+        assert_eq!(
+            [&lines[..2], &lines[3..]].concat(),
+            [
+                "BEGIN:VEVENT",
+                "UID:arn:aws:health:us-east-1::event/EC2/X/1",
+                "DTSTART:20240306T090000Z",
+                "DTEND:20240306T110000Z",
+                "SUMMARY:EC2 AWS_EC2_MAINTENANCE_SCHEDULED",
+                r"DESCRIPTION:Instances i-1\, i-2\; reboot\nrequired during the window of the",
+                "  scheduled change",
+                "END:VEVENT",
+            ]
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! InfluxDB line protocol output, a point per event in the
+//! `aws_health_event` measurement, tagged with its ARN, service, region,
+//! status and category and timestamped at its start. The ARN keeps events
+//! that start at the same time in series of their own, as points of one
+//! series with the same timestamp overwrite each other.
+
+use crate::HealthEvent;
+use std::io::{self, Write};
+
+const MEASUREMENT: &str = "aws_health_event";
+
+pub fn write_event<W: Write>(out: &mut W, event: &HealthEvent) -> io::Result<()> {
+    let mut line = String::from(MEASUREMENT);
+    for (key, value) in [
+        ("arn", &event.arn),
+        ("service", &event.service),
+        ("region", &event.region),
+        ("status", &event.status),
+        ("category", &event.event_type_category),
+        ("event_type_code", &event.event_type_code),
+    ] {
+        // Empty tag values aren't allowed
+        if !value.is_empty() {
+            line.push_str(&format!(",{}={}", key, escape_tag(value)));
+        }
+    }
+    line.push_str(&format!(
+        " detail={},affected_entities={}i,affected_accounts={}",
+        field(&event.detail),
+        event.affected_entities.len(),
+        field(&event.affected_accounts.join(","))
+    ));
+    if let Some(end) = event.end_time {
+        line.push_str(&format!(",end_time={}i", end.timestamp()));
+    }
+    // Without a timestamp the server uses the time it receives the point
+    if let Some(nanos) = event
+        .start_time
+        .and_then(|start| start.timestamp_nanos_opt())
+    {
+        line.push_str(&format!(" {}", nanos));
+    }
+    writeln!(out, "{}", line)
+}
+
+/// Escapes a tag value, in which commas, equals signs and spaces separate
+/// the parts of a line.
+fn escape_tag(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+        .replace('\n', "\\n")
+}
+
+/// A quoted string field value. Newlines would end the line, so they're
+/// escaped too.
+fn field(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn tags_points_with_the_arn_and_escapes_tag_values() {
+        let event = HealthEvent {
+            start_time: Some(Utc.with_ymd_and_hms(2024, 3, 6, 9, 0, 0).unwrap()),
+            arn: "arn:aws:health:global::event/A=1,B".to_string(),
+            service: "Direct Connect".to_string(),
+            region: "global".to_string(),
+            status: "open".to_string(),
+            detail: "Say \"hi\"\nbye".to_string(),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write_event(&mut out, &event).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                r#"aws_health_event,arn=arn:aws:health:global::event/A\=1\,B,"#,
+                r#"service=Direct\ Connect,region=global,status=open "#,
+                r#"detail="Say \"hi\"\nbye",affected_entities=0i,affected_accounts="" "#,
+                "1709715600000000000\n"
+            )
+        );
+    }
+}
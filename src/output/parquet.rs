@@ -0,0 +1,620 @@
+//! A minimal Parquet writer: one row group, uncompressed PLAIN pages.
+//!
+//! The schema is small and fixed, so rather than pulling in the full Arrow
+//! stack this encodes the pages and the Thrift (compact protocol) footer by
+//! hand. See <https://parquet.apache.org/docs/file-format/>.
+
+use crate::HealthEvent;
+use std::io::{self, Write};
+use std::slice;
+
+const MAGIC: &[u8] = b"PAR1";
+
+// parquet.thrift enum values
+const TYPE_INT64: i32 = 2;
+const TYPE_BYTE_ARRAY: i32 = 6;
+const REQUIRED: i32 = 0;
+const OPTIONAL: i32 = 1;
+const REPEATED: i32 = 2;
+const CONVERTED_UTF8: i32 = 0;
+const CONVERTED_LIST: i32 = 3;
+const CONVERTED_TIMESTAMP_MILLIS: i32 = 9;
+const ENCODING_PLAIN: i32 = 0;
+const ENCODING_RLE: i32 = 3;
+const CODEC_UNCOMPRESSED: i32 = 0;
+const PAGE_DATA: i32 = 0;
+
+/// A single leaf column, fully encoded in memory.
+struct Column {
+    path: &'static [&'static str],
+    physical_type: i32,
+    max_rep: u8,
+    max_def: u8,
+    num_values: usize,
+    rep_levels: Vec<u8>,
+    def_levels: Vec<u8>,
+    values: Vec<u8>,
+}
+
+impl Column {
+    fn new(path: &'static [&'static str], physical_type: i32, max_rep: u8, max_def: u8) -> Self {
+        Column {
+            path,
+            physical_type,
+            max_rep,
+            max_def,
+            num_values: 0,
+            rep_levels: Vec::new(),
+            def_levels: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    /// Records one entry. Levels are only kept for columns that need them.
+    fn push_levels(&mut self, rep: u8, def: u8) {
+        self.num_values += 1;
+        if self.max_rep > 0 {
+            self.rep_levels.push(rep);
+        }
+        if self.max_def > 0 {
+            self.def_levels.push(def);
+        }
+    }
+
+    fn push_int64(&mut self, value: i64) {
+        self.values.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn push_bytes(&mut self, value: &[u8]) {
+        self.values
+            .extend_from_slice(&(value.len() as u32).to_le_bytes());
+        self.values.extend_from_slice(value);
+    }
+
+    /// Records a (present) three-level list of strings.
+    fn push_string_list<S: AsRef<str>>(&mut self, values: &[S]) {
+        if values.is_empty() {
+            // Present but empty list
+            self.push_levels(0, 1);
+        }
+        for (i, value) in values.iter().enumerate() {
+            self.push_levels(if i == 0 { 0 } else { 1 }, 2);
+            self.push_bytes(value.as_ref().as_bytes());
+        }
+    }
+
+    /// Encodes the levels and values as the body of a v1 data page.
+    fn page_body(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        if self.max_rep > 0 {
+            write_levels(&mut body, &self.rep_levels, self.max_rep);
+        }
+        if self.max_def > 0 {
+            write_levels(&mut body, &self.def_levels, self.max_def);
+        }
+        body.extend_from_slice(&self.values);
+        body
+    }
+}
+
+/// Optional timestamp columns, in milliseconds since the epoch.
+static TIME_COLUMNS: [&str; 3] = ["start_time", "end_time", "last_updated_time"];
+
+/// Required string columns.
+static STRING_COLUMNS: [&str; 7] = [
+    "arn",
+    "service",
+    "region",
+    "status",
+    "event_type_code",
+    "event_type_category",
+    "detail",
+];
+
+pub fn write_events<W: Write>(out: &mut W, events: &[HealthEvent]) -> io::Result<()> {
+    let mut times = TIME_COLUMNS
+        .each_ref()
+        .map(|name| Column::new(slice::from_ref(name), TYPE_INT64, 0, 1));
+    let mut strings = STRING_COLUMNS
+        .each_ref()
+        .map(|name| Column::new(slice::from_ref(name), TYPE_BYTE_ARRAY, 0, 0));
+    let mut entities = Column::new(
+        &["affected_entities", "list", "element"],
+        TYPE_BYTE_ARRAY,
+        1,
+        2,
+    );
+    let mut accounts = Column::new(
+        &["affected_accounts", "list", "element"],
+        TYPE_BYTE_ARRAY,
+        1,
+        2,
+    );
+    let mut account_names =
+        Column::new(&["account_names", "list", "element"], TYPE_BYTE_ARRAY, 1, 2);
+
+    for event in events {
+        let event_times = [event.start_time, event.end_time, event.last_updated_time];
+        for (column, time) in times.iter_mut().zip(event_times) {
+            match time {
+                Some(time) => {
+                    column.push_levels(0, 1);
+                    column.push_int64(time.timestamp_millis());
+                }
+                None => column.push_levels(0, 0),
+            }
+        }
+        let event_strings = [
+            &event.arn,
+            &event.service,
+            &event.region,
+            &event.status,
+            &event.event_type_code,
+            &event.event_type_category,
+            &event.detail,
+        ];
+        for (column, value) in strings.iter_mut().zip(event_strings) {
+            column.push_levels(0, 0);
+            column.push_bytes(value.as_bytes());
+        }
+        entities.push_string_list(&event.entity_values());
+        accounts.push_string_list(&event.affected_accounts);
+        account_names.push_string_list(&event.account_name_list());
+    }
+    let columns = times
+        .into_iter()
+        .chain(strings)
+        .chain([entities, accounts, account_names]);
+
+    out.write_all(MAGIC)?;
+    let mut offset = MAGIC.len() as i64;
+    let mut chunks = Vec::new();
+    let mut total_byte_size = 0;
+    for column in columns {
+        let body = column.page_body();
+        let mut header = Thrift::default();
+        header.i32_field(1, PAGE_DATA);
+        header.i32_field(2, body.len() as i32);
+        header.i32_field(3, body.len() as i32);
+        header.struct_begin(5);
+        header.i32_field(1, column.num_values as i32);
+        header.i32_field(2, ENCODING_PLAIN);
+        header.i32_field(3, ENCODING_RLE);
+        header.i32_field(4, ENCODING_RLE);
+        header.struct_end();
+        header.stop();
+
+        out.write_all(&header.buf)?;
+        out.write_all(&body)?;
+
+        let size = (header.buf.len() + body.len()) as i64;
+        chunks.push((column, offset, size));
+        offset += size;
+        total_byte_size += size;
+    }
+
+    let mut meta = Thrift::default();
+    meta.i32_field(1, 1);
+    meta.list_header(2, SCHEMA_LEN, CT_STRUCT);
+    write_schema(&mut meta);
+    meta.i64_field(3, events.len() as i64);
+    meta.list_header(4, if events.is_empty() { 0 } else { 1 }, CT_STRUCT);
+    if !events.is_empty() {
+        meta.list_struct_begin();
+        meta.list_header(1, chunks.len(), CT_STRUCT);
+        for (column, offset, size) in &chunks {
+            meta.list_struct_begin();
+            meta.i64_field(2, *offset);
+            meta.struct_begin(3);
+            meta.i32_field(1, column.physical_type);
+            meta.i32_list_field(2, &[ENCODING_PLAIN, ENCODING_RLE]);
+            meta.list_header(3, column.path.len(), CT_BINARY);
+            for part in column.path {
+                meta.binary(part.as_bytes());
+            }
+            meta.i32_field(4, CODEC_UNCOMPRESSED);
+            meta.i64_field(5, column.num_values as i64);
+            meta.i64_field(6, *size);
+            meta.i64_field(7, *size);
+            meta.i64_field(9, *offset);
+            meta.struct_end();
+            meta.list_struct_end();
+        }
+        meta.i64_field(2, total_byte_size);
+        meta.i64_field(3, events.len() as i64);
+        meta.list_struct_end();
+    }
+    meta.binary_field(
+        6,
+        concat!("aws9man version ", env!("CARGO_PKG_VERSION")).as_bytes(),
+    );
+    meta.stop();
+
+    out.write_all(&meta.buf)?;
+    out.write_all(&(meta.buf.len() as u32).to_le_bytes())?;
+    out.write_all(MAGIC)
+}
+
+/// The number of lists of strings, each taking three schema elements.
+const LIST_COLUMNS: usize = 3;
+
+/// The root, the times, the strings and the lists.
+const SCHEMA_LEN: usize = 1 + TIME_COLUMNS.len() + STRING_COLUMNS.len() + 3 * LIST_COLUMNS;
+
+fn write_schema(meta: &mut Thrift) {
+    // Root
+    meta.list_struct_begin();
+    meta.binary_field(4, b"schema");
+    meta.i32_field(
+        5,
+        (TIME_COLUMNS.len() + STRING_COLUMNS.len() + LIST_COLUMNS) as i32,
+    );
+    meta.list_struct_end();
+
+    for name in TIME_COLUMNS {
+        meta.list_struct_begin();
+        meta.i32_field(1, TYPE_INT64);
+        meta.i32_field(3, OPTIONAL);
+        meta.binary_field(4, name.as_bytes());
+        meta.i32_field(6, CONVERTED_TIMESTAMP_MILLIS);
+        // logicalType: TIMESTAMP(isAdjustedToUTC = true, unit = MILLIS)
+        meta.struct_begin(10);
+        meta.struct_begin(8);
+        meta.bool_field(1, true);
+        meta.struct_begin(2);
+        meta.struct_begin(1);
+        meta.struct_end();
+        meta.struct_end();
+        meta.struct_end();
+        meta.struct_end();
+        meta.list_struct_end();
+    }
+
+    for name in STRING_COLUMNS {
+        meta.list_struct_begin();
+        meta.i32_field(1, TYPE_BYTE_ARRAY);
+        meta.i32_field(3, REQUIRED);
+        meta.binary_field(4, name.as_bytes());
+        meta.i32_field(6, CONVERTED_UTF8);
+        meta.list_struct_end();
+    }
+
+    write_string_list_schema(meta, "affected_entities");
+    write_string_list_schema(meta, "affected_accounts");
+    write_string_list_schema(meta, "account_names");
+}
+
+/// Schema elements for a standard three-level LIST of strings.
+fn write_string_list_schema(meta: &mut Thrift, name: &str) {
+    meta.list_struct_begin();
+    meta.i32_field(3, OPTIONAL);
+    meta.binary_field(4, name.as_bytes());
+    meta.i32_field(5, 1);
+    meta.i32_field(6, CONVERTED_LIST);
+    meta.list_struct_end();
+
+    meta.list_struct_begin();
+    meta.i32_field(3, REPEATED);
+    meta.binary_field(4, b"list");
+    meta.i32_field(5, 1);
+    meta.list_struct_end();
+
+    meta.list_struct_begin();
+    meta.i32_field(1, TYPE_BYTE_ARRAY);
+    meta.i32_field(3, REQUIRED);
+    meta.binary_field(4, b"element");
+    meta.i32_field(6, CONVERTED_UTF8);
+    meta.list_struct_end();
+}
+
+/// Writes `levels` using the RLE/bit-packing hybrid encoding, as runs only,
+/// prefixed with their byte length.
+fn write_levels(out: &mut Vec<u8>, levels: &[u8], max_level: u8) {
+    let width_bytes = (8 - max_level.leading_zeros() as usize).div_ceil(8);
+    let mut encoded = Vec::new();
+    let mut i = 0;
+    while i < levels.len() {
+        let value = levels[i];
+        let run = levels[i..]
+            .iter()
+            .take_while(|&&level| level == value)
+            .count();
+        write_varint(&mut encoded, (run as u64) << 1);
+        encoded.extend_from_slice(&[value][..width_bytes]);
+        i += run;
+    }
+    out.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+    out.extend_from_slice(&encoded);
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+// Thrift compact protocol type ids
+const CT_BOOL_TRUE: u8 = 1;
+const CT_BOOL_FALSE: u8 = 2;
+const CT_I32: u8 = 5;
+const CT_I64: u8 = 6;
+const CT_BINARY: u8 = 8;
+const CT_LIST: u8 = 9;
+const CT_STRUCT: u8 = 12;
+
+/// Just enough of the Thrift compact protocol to write Parquet metadata.
+#[derive(Default)]
+struct Thrift {
+    buf: Vec<u8>,
+    last_field: i16,
+    stack: Vec<i16>,
+}
+
+impl Thrift {
+    fn field_header(&mut self, id: i16, field_type: u8) {
+        let delta = id - self.last_field;
+        if (1..=15).contains(&delta) {
+            self.buf.push(((delta as u8) << 4) | field_type);
+        } else {
+            self.buf.push(field_type);
+            write_varint(&mut self.buf, zigzag(id as i64));
+        }
+        self.last_field = id;
+    }
+
+    fn i32_field(&mut self, id: i16, value: i32) {
+        self.field_header(id, CT_I32);
+        write_varint(&mut self.buf, zigzag(value as i64));
+    }
+
+    fn i64_field(&mut self, id: i16, value: i64) {
+        self.field_header(id, CT_I64);
+        write_varint(&mut self.buf, zigzag(value));
+    }
+
+    fn bool_field(&mut self, id: i16, value: bool) {
+        self.field_header(id, if value { CT_BOOL_TRUE } else { CT_BOOL_FALSE });
+    }
+
+    fn binary_field(&mut self, id: i16, value: &[u8]) {
+        self.field_header(id, CT_BINARY);
+        self.binary(value);
+    }
+
+    fn binary(&mut self, value: &[u8]) {
+        write_varint(&mut self.buf, value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    fn i32_list_field(&mut self, id: i16, values: &[i32]) {
+        self.list_header(id, values.len(), CT_I32);
+        for value in values {
+            write_varint(&mut self.buf, zigzag(*value as i64));
+        }
+    }
+
+    fn list_header(&mut self, id: i16, len: usize, element: u8) {
+        self.field_header(id, CT_LIST);
+        if len < 15 {
+            self.buf.push(((len as u8) << 4) | element);
+        } else {
+            self.buf.push(0xf0 | element);
+            write_varint(&mut self.buf, len as u64);
+        }
+    }
+
+    fn struct_begin(&mut self, id: i16) {
+        self.field_header(id, CT_STRUCT);
+        self.list_struct_begin();
+    }
+
+    fn struct_end(&mut self) {
+        self.list_struct_end();
+    }
+
+    /// Starts a struct that is an element of a list (no field header).
+    fn list_struct_begin(&mut self) {
+        self.stack.push(self.last_field);
+        self.last_field = 0;
+    }
+
+    fn list_struct_end(&mut self) {
+        self.stop();
+        self.last_field = self.stack.pop().unwrap_or_default();
+    }
+
+    fn stop(&mut self) {
+        self.buf.push(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// A decoded Thrift compact protocol value.
+    #[derive(Debug)]
+    enum Value {
+        Int(i64),
+        Bool(bool),
+        Binary(Vec<u8>),
+        List(Vec<Value>),
+        Struct(BTreeMap<i16, Value>),
+    }
+
+    impl Value {
+        fn field(&self, id: i16) -> &Value {
+            match self {
+                Value::Struct(fields) => &fields[&id],
+                _ => panic!("{:?} is not a struct", self),
+            }
+        }
+
+        fn int(&self) -> i64 {
+            match self {
+                Value::Int(value) => *value,
+                _ => panic!("{:?} is not an integer", self),
+            }
+        }
+
+        fn list(&self) -> &[Value] {
+            match self {
+                Value::List(values) => values,
+                _ => panic!("{:?} is not a list", self),
+            }
+        }
+
+        fn text(&self) -> &str {
+            match self {
+                Value::Binary(value) => std::str::from_utf8(value).unwrap(),
+                _ => panic!("{:?} is not binary", self),
+            }
+        }
+    }
+
+    struct Reader<'a>(&'a [u8]);
+
+    impl Reader<'_> {
+        fn byte(&mut self) -> u8 {
+            let byte = self.0[0];
+            self.0 = &self.0[1..];
+            byte
+        }
+
+        fn varint(&mut self) -> u64 {
+            let mut value = 0;
+            for shift in (0..64).step_by(7) {
+                let byte = self.byte();
+                value |= ((byte & 0x7f) as u64) << shift;
+                if byte < 0x80 {
+                    break;
+                }
+            }
+            value
+        }
+
+        fn zigzag(&mut self) -> i64 {
+            let value = self.varint();
+            (value >> 1) as i64 ^ -((value & 1) as i64)
+        }
+
+        fn value(&mut self, value_type: u8) -> Value {
+            match value_type {
+                CT_BOOL_TRUE => Value::Bool(true),
+                CT_BOOL_FALSE => Value::Bool(false),
+                CT_I32 | CT_I64 => Value::Int(self.zigzag()),
+                CT_BINARY => {
+                    let len = self.varint() as usize;
+                    let (value, rest) = self.0.split_at(len);
+                    self.0 = rest;
+                    Value::Binary(value.to_vec())
+                }
+                CT_LIST => {
+                    let header = self.byte();
+                    let len = match header >> 4 {
+                        15 => self.varint() as usize,
+                        len => len as usize,
+                    };
+                    Value::List((0..len).map(|_| self.value(header & 0xf)).collect())
+                }
+                CT_STRUCT => {
+                    let mut fields = BTreeMap::new();
+                    let mut id = 0;
+                    loop {
+                        let header = self.byte();
+                        if header == 0 {
+                            break Value::Struct(fields);
+                        }
+                        id = match header >> 4 {
+                            0 => self.zigzag() as i16,
+                            delta => id + delta as i16,
+                        };
+                        fields.insert(id, self.value(header & 0xf));
+                    }
+                }
+                _ => panic!("unexpected Thrift type {}", value_type),
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_through_the_footer() {
+        let event = super::super::tests::sample_event();
+        let mut without_end = event.clone();
+        without_end.end_time = None;
+        let mut file = Vec::new();
+        write_events(&mut file, &[event, without_end]).unwrap();
+
+        assert_eq!(&file[..4], MAGIC);
+        assert_eq!(&file[file.len() - 4..], MAGIC);
+        let footer_len =
+            u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap());
+        let footer_start = file.len() - 8 - footer_len as usize;
+        let mut footer = Reader(&file[footer_start..file.len() - 8]);
+        let meta = footer.value(CT_STRUCT);
+        assert!(footer.0.is_empty());
+        assert_eq!(meta.field(3).int(), 2);
+
+        let schema = meta.field(2).list();
+        let names: Vec<&str> = schema
+            .iter()
+            .map(|element| element.field(4).text())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "schema",
+                "start_time",
+                "end_time",
+                "last_updated_time",
+                "arn",
+                "service",
+                "region",
+                "status",
+                "event_type_code",
+                "event_type_category",
+                "detail",
+                "affected_entities",
+                "list",
+                "element",
+                "affected_accounts",
+                "list",
+                "element",
+                "account_names",
+                "list",
+                "element",
+            ]
+        );
+        assert_eq!(schema[0].field(5).int(), 13);
+        for time in &schema[1..4] {
+            assert_eq!(time.field(1).int(), TYPE_INT64 as i64);
+            assert_eq!(time.field(6).int(), CONVERTED_TIMESTAMP_MILLIS as i64);
+            // Adjusted to UTC
+            assert!(matches!(
+                time.field(10).field(8).field(1),
+                Value::Bool(true)
+            ));
+        }
+
+        // Read end_time back: one definition level per row, then the values
+        let columns = meta.field(4).list()[0].field(1).list();
+        assert_eq!(columns.len(), 13);
+        let chunk = columns[1].field(3);
+        assert_eq!(chunk.field(3).list()[0].text(), "end_time");
+        let mut page = Reader(&file[chunk.field(9).int() as usize..footer_start]);
+        let header = page.value(CT_STRUCT);
+        assert_eq!(header.field(5).field(1).int(), 2);
+        let body = &page.0[..header.field(2).int() as usize];
+        // A run of one present value, then a run of one missing value
+        assert_eq!(&body[..8], [4, 0, 0, 0, 2, 1, 2, 0]);
+        let millis = i64::from_le_bytes(body[8..].try_into().unwrap());
+        assert_eq!(millis, 1_709_722_800_000);
+    }
+}
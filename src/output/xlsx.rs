@@ -0,0 +1,179 @@
+//! Excel workbook output: an "Events" sheet and an "Affected Entities" sheet,
+//! written as minimal SpreadsheetML inside a zip archive.
+
+use super::escape_xml;
+use super::zip::ZipWriter;
+use crate::HealthEvent;
+use std::io::{self, Write};
+
+// Excel refuses to open cells longer than this
+const MAX_CELL_CHARS: usize = 32767;
+
+// Indices into `cellXfs` in STYLES
+const STYLE_DATE: u8 = 1;
+const STYLE_WRAP: u8 = 2;
+const STYLE_HEADER: u8 = 3;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/worksheets/sheet2.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>"#;
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Events" sheetId="1" r:id="rId1"/><sheet name="Affected Entities" sheetId="2" r:id="rId2"/></sheets></workbook>"#;
+
+const WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet2.xml"/><Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#;
+
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><numFmts count="1"><numFmt numFmtId="164" formatCode="yyyy-mm-dd hh:mm:ss"/></numFmts><fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="4"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="164" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0" applyAlignment="1"><alignment vertical="top" wrapText="1"/></xf><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/></cellXfs><cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles></styleSheet>"#;
+
+enum Cell<'a> {
+    Text(&'a str, Option<u8>),
+    Date(f64),
+    Empty,
+}
+
+/// Accumulates one worksheet's XML.
+struct Sheet {
+    xml: String,
+    rows: usize,
+}
+
+impl Sheet {
+    fn new(widths: &[u32], header: &[&str]) -> Self {
+        let mut xml = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetViews><sheetView workbookViewId="0"><pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/></sheetView></sheetViews><cols>"#,
+        );
+        for (i, width) in widths.iter().enumerate() {
+            xml.push_str(&format!(
+                r#"<col min="{0}" max="{0}" width="{1}" customWidth="1"/>"#,
+                i + 1,
+                width
+            ));
+        }
+        xml.push_str("</cols><sheetData>");
+
+        let mut sheet = Sheet { xml, rows: 0 };
+        let header: Vec<Cell> = header
+            .iter()
+            .map(|title| Cell::Text(title, Some(STYLE_HEADER)))
+            .collect();
+        sheet.row(&header);
+        sheet
+    }
+
+    fn row(&mut self, cells: &[Cell]) {
+        self.rows += 1;
+        self.xml.push_str(&format!(r#"<row r="{}">"#, self.rows));
+        for (i, cell) in cells.iter().enumerate() {
+            let reference = format!("{}{}", (b'A' + i as u8) as char, self.rows);
+            match cell {
+                Cell::Text(text, style) => {
+                    let style = style.map(|s| format!(r#" s="{}""#, s)).unwrap_or_default();
+                    self.xml.push_str(&format!(
+                        r#"<c r="{}" t="inlineStr"{}><is><t xml:space="preserve">{}</t></is></c>"#,
+                        reference,
+                        style,
+                        escape(text)
+                    ));
+                }
+                Cell::Date(serial) => {
+                    self.xml.push_str(&format!(
+                        r#"<c r="{}" s="{}"><v>{}</v></c>"#,
+                        reference, STYLE_DATE, serial
+                    ));
+                }
+                Cell::Empty => {}
+            }
+        }
+        self.xml.push_str("</row>");
+    }
+
+    fn finish(mut self) -> String {
+        self.xml.push_str("</sheetData></worksheet>");
+        self.xml
+    }
+}
+
+pub fn write_events<W: Write>(out: &mut W, events: &[HealthEvent]) -> io::Result<()> {
+    let mut event_sheet = Sheet::new(
+        &[20, 60, 100, 60, 30],
+        &[
+            "Timestamp",
+            "ARN",
+            "Detail",
+            "Affected Entities",
+            "Affected Accounts",
+        ],
+    );
+    let mut entity_sheet = Sheet::new(&[60, 60, 20], &["Event ARN", "Entity", "Account"]);
+
+    for event in events {
+        let timestamp = match event.start_time {
+            // Days since 1899-12-30, Excel's (1900 date system) epoch
+            Some(time) => Cell::Date(time.timestamp_millis() as f64 / 86_400_000.0 + 25569.0),
+            None => Cell::Empty,
+        };
+        let entities = event.entities_text();
+        let accounts = event.accounts_text();
+        event_sheet.row(&[
+            timestamp,
+            Cell::Text(&event.arn, None),
+            Cell::Text(&event.detail, Some(STYLE_WRAP)),
+            Cell::Text(&entities, Some(STYLE_WRAP)),
+            Cell::Text(&accounts, Some(STYLE_WRAP)),
+        ]);
+        for entity in &event.affected_entities {
+            let account = match &entity.account_id {
+                Some(account_id) => Cell::Text(account_id, None),
+                None => Cell::Empty,
+            };
+            entity_sheet.row(&[
+                Cell::Text(&event.arn, None),
+                Cell::Text(&entity.value, None),
+                account,
+            ]);
+        }
+    }
+
+    let mut zip = ZipWriter::default();
+    zip.add("[Content_Types].xml", CONTENT_TYPES.as_bytes());
+    zip.add("_rels/.rels", ROOT_RELS.as_bytes());
+    zip.add("xl/workbook.xml", WORKBOOK.as_bytes());
+    zip.add("xl/_rels/workbook.xml.rels", WORKBOOK_RELS.as_bytes());
+    zip.add("xl/styles.xml", STYLES.as_bytes());
+    zip.add("xl/worksheets/sheet1.xml", event_sheet.finish().as_bytes());
+    zip.add("xl/worksheets/sheet2.xml", entity_sheet.finish().as_bytes());
+    out.write_all(&zip.finish())
+}
+
+/// Escapes `text` for XML, dropping characters XML can't represent and
+/// truncating to Excel's cell limit.
+fn escape(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .take(MAX_CELL_CHARS)
+        .filter(|&c| matches!(c, '\t' | '\n' | '\r') || !c.is_control())
+        .collect();
+    escape_xml(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_cells_excel_can_open() {
+        assert_eq!(
+            escape("<a> & \"b\"\tc\u{1}\n"),
+            "&lt;a&gt; &amp; &quot;b&quot;\tc\n"
+        );
+        assert_eq!(
+            escape(&"x".repeat(MAX_CELL_CHARS + 1)).len(),
+            MAX_CELL_CHARS
+        );
+    }
+}
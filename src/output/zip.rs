@@ -0,0 +1,152 @@
+//! A tiny zip archive writer. Entries are stored uncompressed, which every
+//! zip reader (including Excel) accepts.
+
+/// Builds a zip archive in memory.
+#[derive(Default)]
+pub struct ZipWriter {
+    buf: Vec<u8>,
+    central: Vec<u8>,
+    entries: u16,
+}
+
+// 1980-01-01 00:00, the earliest MS-DOS timestamp
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+impl ZipWriter {
+    pub fn add(&mut self, name: &str, data: &[u8]) {
+        let offset = self.buf.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+
+        // Local file header
+        self.buf.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        self.buf.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        self.buf.extend_from_slice(&0u16.to_le_bytes()); // flags
+        self.buf.extend_from_slice(&0u16.to_le_bytes()); // stored
+        self.buf.extend_from_slice(&DOS_TIME.to_le_bytes());
+        self.buf.extend_from_slice(&DOS_DATE.to_le_bytes());
+        self.buf.extend_from_slice(&crc.to_le_bytes());
+        self.buf.extend_from_slice(&size.to_le_bytes());
+        self.buf.extend_from_slice(&size.to_le_bytes());
+        self.buf
+            .extend_from_slice(&(name.len() as u16).to_le_bytes());
+        self.buf.extend_from_slice(&0u16.to_le_bytes()); // extra length
+        self.buf.extend_from_slice(name.as_bytes());
+        self.buf.extend_from_slice(data);
+
+        // Central directory header
+        self.central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        self.central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        self.central.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        self.central.extend_from_slice(&0u16.to_le_bytes()); // flags
+        self.central.extend_from_slice(&0u16.to_le_bytes()); // stored
+        self.central.extend_from_slice(&DOS_TIME.to_le_bytes());
+        self.central.extend_from_slice(&DOS_DATE.to_le_bytes());
+        self.central.extend_from_slice(&crc.to_le_bytes());
+        self.central.extend_from_slice(&size.to_le_bytes());
+        self.central.extend_from_slice(&size.to_le_bytes());
+        self.central
+            .extend_from_slice(&(name.len() as u16).to_le_bytes());
+        self.central.extend_from_slice(&0u16.to_le_bytes()); // extra length
+        self.central.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.central.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        self.central.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        self.central.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        self.central.extend_from_slice(&offset.to_le_bytes());
+        self.central.extend_from_slice(name.as_bytes());
+
+        self.entries += 1;
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let central_offset = self.buf.len() as u32;
+        let central_size = self.central.len() as u32;
+        self.buf.append(&mut self.central);
+
+        // End of central directory record
+        self.buf.extend_from_slice(&0x06054b50u32.to_le_bytes());
+        self.buf.extend_from_slice(&0u16.to_le_bytes()); // this disk
+        self.buf.extend_from_slice(&0u16.to_le_bytes()); // disk with central directory
+        self.buf.extend_from_slice(&self.entries.to_le_bytes());
+        self.buf.extend_from_slice(&self.entries.to_le_bytes());
+        self.buf.extend_from_slice(&central_size.to_le_bytes());
+        self.buf.extend_from_slice(&central_offset.to_le_bytes());
+        self.buf.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.buf
+    }
+}
+
+/// CRC-32 (IEEE 802.3), as used by zip and gzip.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb88320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(buf: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(buf[at..at + 2].try_into().unwrap())
+    }
+
+    fn u32_at(buf: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(buf[at..at + 4].try_into().unwrap())
+    }
+
+    /// Reads the archive back through its central directory, as zip readers
+    /// do, checking each entry's local header and CRC on the way.
+    fn entries(zip: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let end = zip.len() - 22;
+        assert_eq!(u32_at(zip, end), 0x06054b50);
+        let count = u16_at(zip, end + 10);
+        let mut central = u32_at(zip, end + 16) as usize;
+        assert_eq!(central + u32_at(zip, end + 12) as usize, end);
+
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            assert_eq!(u32_at(zip, central), 0x02014b50);
+            let crc = u32_at(zip, central + 16);
+            let size = u32_at(zip, central + 20) as usize;
+            let name_len = u16_at(zip, central + 28) as usize;
+            let local = u32_at(zip, central + 42) as usize;
+            let name = &zip[central + 46..central + 46 + name_len];
+
+            assert_eq!(u32_at(zip, local), 0x04034b50);
+            assert_eq!(u32_at(zip, local + 14), crc);
+            assert_eq!(&zip[local + 30..local + 30 + name_len], name);
+            let data = &zip[local + 30 + name_len..local + 30 + name_len + size];
+            assert_eq!(crc32(data), crc);
+
+            entries.push((String::from_utf8(name.to_vec()).unwrap(), data.to_vec()));
+            central += 46 + name_len;
+        }
+        assert_eq!(central, end);
+        entries
+    }
+
+    #[test]
+    fn writes_readable_archives() {
+        // The standard CRC-32 check value
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+
+        let mut zip = ZipWriter::default();
+        zip.add("[Content_Types].xml", b"<Types/>");
+        zip.add("xl/empty.xml", b"");
+        assert_eq!(
+            entries(&zip.finish()),
+            [
+                ("[Content_Types].xml".to_string(), b"<Types/>".to_vec()),
+                ("xl/empty.xml".to_string(), Vec::new()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Drives PagerDuty alerts from Health issues through the Events API v2
+//! (`--pagerduty-routing-key`).
+//!
+//! An open `issue` event triggers an alert, deduplicated by event ARN so
+//! later updates add to the same alert; the event closing resolves it. Other
+//! categories, such as scheduled changes, don't page.
+
+use crate::HealthEvent;
+use crate::http_client::HttpClient;
+use crate::notify::{self, console_url, excerpt, title};
+use aws_smithy_json::serialize::JsonObjectWriter;
+use std::error::Error as StdError;
+
+/// PagerDuty cuts summaries at 1,024 characters.
+const MAX_SUMMARY_CHARS: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+    Trigger,
+    Resolve,
+}
+
+impl Action {
+    /// What an update of `event` does to its alert, if anything.
+    fn for_event(event: &HealthEvent) -> Option<Action> {
+        match (event.event_type_category.as_str(), event.status.as_str()) {
+            ("issue", "open") => Some(Action::Trigger),
+            ("issue", "closed") => Some(Action::Resolve),
+            _ => None,
+        }
+    }
+}
+
+pub struct PagerDutyNotifier {
+    http: HttpClient,
+    events_url: String,
+    routing_key: String,
+}
+
+impl PagerDutyNotifier {
+    pub fn new(http: HttpClient, events_url: String, routing_key: String) -> Self {
+        PagerDutyNotifier {
+            http,
+            events_url,
+            routing_key,
+        }
+    }
+
+    /// Triggers and resolves alerts for `events`, returning how many of each.
+    pub async fn notify(
+        &self,
+        events: &[HealthEvent],
+    ) -> Result<(usize, usize), Box<dyn StdError>> {
+        let (mut triggered, mut resolved) = (0, 0);
+        for event in events {
+            let Some(action) = Action::for_event(event) else {
+                continue;
+            };
+            notify::post(
+                &self.http,
+                "PagerDuty",
+                &self.events_url,
+                &[("content-type", "application/json")],
+                self.body(event, action),
+            )
+            .await?;
+            match action {
+                Action::Trigger => triggered += 1,
+                Action::Resolve => resolved += 1,
+            }
+        }
+        Ok((triggered, resolved))
+    }
+
+    fn body(&self, event: &HealthEvent, action: Action) -> String {
+        let mut body = String::new();
+        let mut object = JsonObjectWriter::new(&mut body);
+        object.key("routing_key").string(&self.routing_key);
+        object.key("dedup_key").string(&event.arn);
+        if action == Action::Resolve {
+            object.key("event_action").string("resolve");
+            object.finish();
+            return body;
+        }
+        object.key("event_action").string("trigger");
+        object.key("client").string("aws9man");
+        object.key("client_url").string(&console_url(event));
+
+        let mut payload = object.key("payload").start_object();
+        let summary = format!("{}: {}", title(event), event.detail);
+        payload
+            .key("summary")
+            .string(&excerpt(&summary, MAX_SUMMARY_CHARS));
+        payload.key("source").string(&event.region);
+        payload.key("severity").string("error");
+        payload.key("component").string(&event.service);
+        payload.key("group").string(&event.region);
+        payload.key("class").string(&event.event_type_code);
+        let mut details = payload.key("custom_details").start_object();
+        details.key("event_arn").string(&event.arn);
+        details.key("start").string(&event.timestamp);
+        let mut entities = details.key("affected_entities").start_array();
+        for value in event.entity_values() {
+            entities.value().string(value);
+        }
+        entities.finish();
+        if !event.affected_accounts.is_empty() {
+            details
+                .key("affected_accounts")
+                .string(&event.accounts_text());
+        }
+        details.finish();
+        payload.finish();
+
+        let mut links = object.key("links").start_array();
+        let mut link = links.value().start_object();
+        link.key("href").string(&console_url(event));
+        link.key("text").string("AWS Health Dashboard");
+        link.finish();
+        links.finish();
+        object.finish();
+        body
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_only_for_issues() {
+        let event = |category: &str, status: &str| HealthEvent {
+            event_type_category: category.to_string(),
+            status: status.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            Action::for_event(&event("issue", "open")),
+            Some(Action::Trigger)
+        );
+        assert_eq!(
+            Action::for_event(&event("issue", "closed")),
+            Some(Action::Resolve)
+        );
+        assert_eq!(Action::for_event(&event("scheduledChange", "open")), None);
+    }
+}
@@ -0,0 +1,76 @@
+//! AWS partitions (commercial, GovCloud, China), which decide where the
+//! global Health and Organizations APIs live and how ARNs are spelled.
+
+use aws_types::region::Region;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Partition {
+    Aws,
+    AwsUsGov,
+    AwsCn,
+}
+
+impl Partition {
+    /// The partition `region` belongs to, or the commercial partition when
+    /// no region is configured.
+    pub fn of_region(region: Option<&Region>) -> Self {
+        let region = region.map(Region::as_ref).unwrap_or_default();
+        if region.starts_with("us-gov-") {
+            Partition::AwsUsGov
+        } else if region.starts_with("cn-") {
+            Partition::AwsCn
+        } else {
+            Partition::Aws
+        }
+    }
+
+    /// The partition as it appears in ARNs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Partition::Aws => "aws",
+            Partition::AwsUsGov => "aws-us-gov",
+            Partition::AwsCn => "aws-cn",
+        }
+    }
+
+    /// The domain the partition's endpoints are under.
+    pub fn dns_suffix(&self) -> &'static str {
+        match self {
+            Partition::Aws | Partition::AwsUsGov => "amazonaws.com",
+            Partition::AwsCn => "amazonaws.com.cn",
+        }
+    }
+
+    /// The region the partition's Health API is served from. Events from
+    /// every region are reported there.
+    pub fn health_region(&self) -> Region {
+        Region::from_static(match self {
+            Partition::Aws => "us-east-1",
+            Partition::AwsUsGov => "us-gov-west-1",
+            Partition::AwsCn => "cn-northwest-1",
+        })
+    }
+
+    /// The Organizations endpoint and the region to sign requests to it for.
+    pub fn organizations_endpoint(&self, fips: bool) -> (&'static str, &'static str) {
+        match (self, fips) {
+            (Partition::Aws, false) => (
+                "https://organizations.us-east-1.amazonaws.com/",
+                "us-east-1",
+            ),
+            (Partition::Aws, true) => (
+                "https://organizations-fips.us-east-1.amazonaws.com/",
+                "us-east-1",
+            ),
+            // The GovCloud endpoint is FIPS validated already
+            (Partition::AwsUsGov, _) => (
+                "https://organizations.us-gov-west-1.amazonaws.com/",
+                "us-gov-west-1",
+            ),
+            (Partition::AwsCn, _) => (
+                "https://organizations.cn-northwest-1.amazonaws.com.cn/",
+                "cn-northwest-1",
+            ),
+        }
+    }
+}
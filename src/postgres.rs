@@ -0,0 +1,234 @@
+//! Upserts events into a PostgreSQL database through the `psql` CLI, as
+//! `sqlite` does for SQLite. sqlx, or any other PostgreSQL client crate,
+//! isn't among the dependencies this builds with.
+//!
+//! Values never go into SQL: rows are streamed into temporary tables with
+//! `COPY ... FROM STDIN` in its text format, whose escaping doesn't depend
+//! on server settings, and upserted from there in one transaction when the
+//! run finishes.
+
+use crate::HealthEvent;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::io::{self, BufWriter, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+const SCHEMA: &str = "\
+CREATE TABLE IF NOT EXISTS health_events (
+    arn TEXT PRIMARY KEY,
+    service TEXT NOT NULL,
+    region TEXT NOT NULL,
+    status TEXT NOT NULL,
+    event_type_code TEXT NOT NULL,
+    event_type_category TEXT NOT NULL,
+    start_time TIMESTAMPTZ,
+    end_time TIMESTAMPTZ,
+    last_updated_time TIMESTAMPTZ,
+    detail TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE TABLE IF NOT EXISTS affected_entities (
+    event_arn TEXT NOT NULL REFERENCES health_events (arn) ON DELETE CASCADE,
+    account_id TEXT NOT NULL DEFAULT '',
+    entity_value TEXT NOT NULL,
+    entity_arn TEXT,
+    status TEXT,
+    last_updated_time TIMESTAMPTZ,
+    PRIMARY KEY (event_arn, account_id, entity_value)
+);
+";
+
+/// Where the rows are copied before the upsert, with the position of the
+/// write they came from so the last write of an event wins.
+const INCOMING: &str = "\
+CREATE TEMPORARY TABLE incoming_events (
+    position BIGINT NOT NULL,
+    arn TEXT NOT NULL,
+    service TEXT NOT NULL,
+    region TEXT NOT NULL,
+    status TEXT NOT NULL,
+    event_type_code TEXT NOT NULL,
+    event_type_category TEXT NOT NULL,
+    start_time TIMESTAMPTZ,
+    end_time TIMESTAMPTZ,
+    last_updated_time TIMESTAMPTZ,
+    detail TEXT NOT NULL
+) ON COMMIT DROP;
+CREATE TEMPORARY TABLE incoming_entities (
+    position BIGINT NOT NULL,
+    event_arn TEXT NOT NULL,
+    account_id TEXT NOT NULL,
+    entity_value TEXT NOT NULL,
+    entity_arn TEXT,
+    status TEXT,
+    last_updated_time TIMESTAMPTZ
+) ON COMMIT DROP;
+COPY incoming_events FROM STDIN;
+";
+
+const UPSERT: &str = "\
+INSERT INTO health_events (arn, service, region, status, event_type_code,
+    event_type_category, start_time, end_time, last_updated_time, detail)
+SELECT DISTINCT ON (arn) arn, service, region, status, event_type_code,
+    event_type_category, start_time, end_time, last_updated_time, detail
+FROM incoming_events ORDER BY arn, position DESC
+ON CONFLICT (arn) DO UPDATE SET service = excluded.service,
+    region = excluded.region, status = excluded.status,
+    event_type_code = excluded.event_type_code,
+    event_type_category = excluded.event_type_category,
+    start_time = excluded.start_time, end_time = excluded.end_time,
+    last_updated_time = excluded.last_updated_time, detail = excluded.detail,
+    updated_at = now();
+-- Replace the entity lists so entities that recovered don't linger
+DELETE FROM affected_entities WHERE event_arn IN (SELECT arn FROM incoming_events);
+INSERT INTO affected_entities (event_arn, account_id, entity_value, entity_arn,
+    status, last_updated_time)
+SELECT event_arn, account_id, entity_value, entity_arn, status, last_updated_time
+FROM incoming_entities
+WHERE (event_arn, position) IN (SELECT arn, max(position) FROM incoming_events GROUP BY arn)
+ON CONFLICT DO NOTHING;
+COMMIT;
+";
+
+pub struct PostgresWriter {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    /// The entity rows, copied once every event row has been
+    entities: Vec<u8>,
+    position: u64,
+}
+
+impl PostgresWriter {
+    /// Connects to `url`, a libpq connection string or URI. psql reads a
+    /// password left out of it from `PGPASSWORD` or `~/.pgpass`.
+    pub fn open(url: &str) -> io::Result<Self> {
+        let mut child = Command::new("psql")
+            .args(["--no-psqlrc", "--quiet", "--set", "ON_ERROR_STOP=1"])
+            .arg("--dbname")
+            .arg(url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("could not run psql (is it installed?): {}", err),
+                )
+            })?;
+        let mut stdin = BufWriter::new(child.stdin.take().expect("stdin is piped"));
+        stdin.write_all(b"BEGIN;\n")?;
+        stdin.write_all(SCHEMA.as_bytes())?;
+        stdin.write_all(INCOMING.as_bytes())?;
+        Ok(PostgresWriter {
+            child,
+            stdin,
+            entities: Vec::new(),
+            position: 0,
+        })
+    }
+
+    pub fn write(&mut self, event: &HealthEvent) -> io::Result<()> {
+        self.position += 1;
+        let position = self.position.to_string();
+        let start_time = time(event.start_time);
+        let end_time = time(event.end_time);
+        let last_updated_time = time(event.last_updated_time);
+        self.stdin.write_all(
+            copy_row(&[
+                Some(position.as_str()),
+                Some(&event.arn),
+                Some(&event.service),
+                Some(&event.region),
+                Some(&event.status),
+                Some(&event.event_type_code),
+                Some(&event.event_type_category),
+                start_time.as_deref(),
+                end_time.as_deref(),
+                last_updated_time.as_deref(),
+                Some(&event.detail),
+            ])
+            .as_bytes(),
+        )?;
+        for entity in &event.affected_entities {
+            let last_updated_time = time(entity.last_updated_time);
+            self.entities.extend_from_slice(
+                copy_row(&[
+                    Some(position.as_str()),
+                    Some(&event.arn),
+                    Some(entity.account_id.as_deref().unwrap_or_default()),
+                    Some(&entity.value),
+                    entity.arn.as_deref(),
+                    entity.status.as_deref(),
+                    last_updated_time.as_deref(),
+                ])
+                .as_bytes(),
+            );
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.stdin
+            .write_all(b"\\.\nCOPY incoming_entities FROM STDIN;\n")?;
+        self.stdin.write_all(&self.entities)?;
+        self.stdin.write_all(b"\\.\n")?;
+        self.stdin.write_all(UPSERT.as_bytes())?;
+        self.stdin.flush()?;
+        drop(self.stdin);
+
+        let status = self.child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("psql exited with {}", status)));
+        }
+        Ok(())
+    }
+}
+
+/// A line of `COPY` text format: tab-separated values, `\N` for NULL.
+fn copy_row(values: &[Option<&str>]) -> String {
+    let values: Vec<String> = values.iter().map(|value| copy_value(*value)).collect();
+    values.join("\t") + "\n"
+}
+
+/// Escapes `value` for `COPY` text format, where backslashes and the
+/// characters separating values and rows are backslash escaped. PostgreSQL
+/// text can't hold NUL characters, so they're dropped.
+fn copy_value(value: Option<&str>) -> String {
+    let Some(value) = value else {
+        return "\\N".to_string();
+    };
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\0' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn time(value: Option<DateTime<Utc>>) -> Option<String> {
+    value.map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_copy_values() {
+        assert_eq!(copy_value(Some("it's a \"quote\"")), "it's a \"quote\"");
+        assert_eq!(copy_value(Some(r"C:\temp\N")), r"C:\\temp\\N");
+        assert_eq!(copy_value(Some("a\0b")), "ab");
+        assert_eq!(
+            copy_value(Some("line\r\nnext\tcell")),
+            r"line\r\nnext\tcell"
+        );
+        assert_eq!(copy_value(Some(r"\.")), r"\\.");
+        assert_eq!(copy_value(None), r"\N");
+        assert_eq!(copy_row(&[Some("a"), None, Some("")]), "a\t\\N\t\n");
+    }
+}
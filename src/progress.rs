@@ -0,0 +1,117 @@
+//! A one-line progress display on stderr, so long fetches don't look hung.
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const SPINNER: &[char] = &['|', '/', '-', '\\'];
+
+/// Counts events, details and entities as they are fetched.
+#[derive(Debug)]
+pub struct Progress {
+    enabled: bool,
+    events: AtomicU64,
+    details: AtomicU64,
+    entities: AtomicU64,
+    started: Instant,
+    last_draw: Mutex<Option<Instant>>,
+}
+
+impl Progress {
+    /// Draws only when `enabled`; see [`Progress::for_stderr`].
+    pub fn new(enabled: bool) -> Self {
+        Progress {
+            enabled,
+            events: AtomicU64::new(0),
+            details: AtomicU64::new(0),
+            entities: AtomicU64::new(0),
+            started: Instant::now(),
+            last_draw: Mutex::new(None),
+        }
+    }
+
+    /// Enabled when stderr is a terminal.
+    pub fn for_stderr() -> Self {
+        Self::new(io::stderr().is_terminal())
+    }
+
+    pub fn add_events(&self, count: u64) {
+        self.events.fetch_add(count, Ordering::Relaxed);
+        self.tick();
+    }
+
+    pub fn add_details(&self, count: u64) {
+        self.details.fetch_add(count, Ordering::Relaxed);
+        self.tick();
+    }
+
+    pub fn add_entities(&self, count: u64) {
+        self.entities.fetch_add(count, Ordering::Relaxed);
+        self.tick();
+    }
+
+    /// Clears the progress line while `f` writes to the terminal, then
+    /// draws it again.
+    pub fn suspend<T>(&self, f: impl FnOnce() -> T) -> T {
+        if !self.enabled {
+            return f();
+        }
+        clear_line();
+        let result = f();
+        self.draw();
+        result
+    }
+
+    /// Removes the progress line once fetching is done.
+    pub fn finish(&self) {
+        if self.enabled {
+            clear_line();
+        }
+    }
+
+    /// Redraws the line, at most once every `REDRAW_INTERVAL`.
+    fn tick(&self) {
+        if !self.enabled {
+            return;
+        }
+        let mut last_draw = self.last_draw.lock().unwrap();
+        if last_draw.is_some_and(|last| last.elapsed() < REDRAW_INTERVAL) {
+            return;
+        }
+        *last_draw = Some(Instant::now());
+        drop(last_draw);
+        self.draw();
+    }
+
+    fn draw(&self) {
+        let elapsed = self.started.elapsed();
+        let spinner = SPINNER[(elapsed.as_millis() / REDRAW_INTERVAL.as_millis()) as usize % 4];
+        let mut stderr = io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r\x1b[2K{} {}s  events: {}  details: {}  entities: {}",
+            spinner,
+            elapsed.as_secs(),
+            self.events.load(Ordering::Relaxed),
+            self.details.load(Ordering::Relaxed),
+            self.entities.load(Ordering::Relaxed),
+        );
+        let _ = stderr.flush();
+    }
+}
+
+impl Drop for Progress {
+    // Also clears the line when fetching fails part way, before the error is
+    // reported
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+fn clear_line() {
+    let mut stderr = io::stderr().lock();
+    let _ = write!(stderr, "\r\x1b[2K");
+    let _ = stderr.flush();
+}
@@ -0,0 +1,225 @@
+//! Notification routing rules: `[[routes]]` tables in the config file that
+//! send the new events matching them to destinations of their own, on top of
+//! the ones the flags give, which still get every event:
+//!
+//! ```toml
+//! [[routes]]
+//! name = "db-team"
+//! service = ["RDS"]
+//! category = "issue"
+//! slack-webhook = "https://hooks.slack.com/services/..."
+//!
+//! [[routes]]
+//! category = "scheduledChange"
+//! email-to = ["platform@example.com"]
+//! ```
+//!
+//! A route matches an event when each of its `service`, `region`,
+//! `category`, `account` and `severity` lists (a string or an array) is
+//! missing or has one of the event's values. Events match every route they
+//! fit, not just the first.
+
+use crate::HealthEvent;
+use crate::filter::Category;
+use crate::toml::Value;
+use clap::ValueEnum;
+
+/// Severities, from the event's category: issues are `critical`,
+/// investigations and scheduled changes `warning`, and account notifications
+/// `info`.
+const SEVERITIES: &[&str] = &["critical", "warning", "info"];
+
+/// Where a route's events go. Email goes through the --smtp-host or --ses
+/// given on the command line, and the Telegram chat through
+/// --telegram-bot-token.
+#[derive(Debug, Clone, Default)]
+pub struct Destinations {
+    pub slack_webhook: Option<String>,
+    pub teams_webhook: Option<String>,
+    pub discord_webhook: Option<String>,
+    pub telegram_chat_id: Option<String>,
+    pub pagerduty_routing_key: Option<String>,
+    pub webhook_url: Option<String>,
+    pub email_to: Vec<String>,
+}
+
+impl Destinations {
+    fn is_empty(&self) -> bool {
+        self.slack_webhook.is_none()
+            && self.teams_webhook.is_none()
+            && self.discord_webhook.is_none()
+            && self.telegram_chat_id.is_none()
+            && self.pagerduty_routing_key.is_none()
+            && self.webhook_url.is_none()
+            && self.email_to.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Route {
+    /// The route's `name`, or its position such as `#2`
+    pub name: String,
+    services: Vec<String>,
+    regions: Vec<String>,
+    categories: Vec<String>,
+    accounts: Vec<String>,
+    severities: Vec<String>,
+    pub destinations: Destinations,
+}
+
+impl Route {
+    pub fn matches(&self, event: &HealthEvent) -> bool {
+        any(&self.services, |service| {
+            service.eq_ignore_ascii_case(&event.service)
+        }) && any(&self.regions, |region| region == event.region)
+            && any(&self.categories, |category| {
+                category == event.event_type_category
+            })
+            && any(&self.accounts, |account| {
+                event.affected_accounts.iter().any(|id| id == account)
+                    || event
+                        .affected_entities
+                        .iter()
+                        .any(|entity| entity.account_id.as_deref() == Some(account))
+            })
+            && any(&self.severities, |severity| {
+                severity == self::severity(event)
+            })
+    }
+}
+
+/// Whether a criterion is unset or one of its values matches.
+fn any(wanted: &[String], matches: impl Fn(&str) -> bool) -> bool {
+    wanted.is_empty() || wanted.iter().any(|value| matches(value))
+}
+
+fn severity(event: &HealthEvent) -> &'static str {
+    match event.event_type_category.as_str() {
+        "issue" => "critical",
+        "investigation" | "scheduledChange" => "warning",
+        _ => "info",
+    }
+}
+
+/// Reads the `routes` setting, an array of tables.
+pub fn parse(value: &Value) -> Result<Vec<Route>, String> {
+    let tables = value
+        .as_array()
+        .ok_or("`routes` must be an array of tables, e.g. [[routes]]")?;
+    let mut routes = Vec::new();
+    for (index, table) in tables.iter().enumerate() {
+        let table = table
+            .as_table()
+            .ok_or("`routes` must be an array of tables, e.g. [[routes]]")?;
+        let name = match table.get("name") {
+            Some(Value::String(name)) => name.clone(),
+            Some(_) => return Err(format!("route #{}: `name` must be a string", index + 1)),
+            None => format!("#{}", index + 1),
+        };
+        let mut route = Route {
+            name,
+            services: Vec::new(),
+            regions: Vec::new(),
+            categories: Vec::new(),
+            accounts: Vec::new(),
+            severities: Vec::new(),
+            destinations: Destinations::default(),
+        };
+        for (key, value) in table {
+            let error = |message: &str| format!("route {}: `{}` {}", route.name, key, message);
+            let string = || {
+                value
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| error("must be a string"))
+            };
+            let strings = || match value {
+                Value::String(value) => Ok(vec![value.clone()]),
+                Value::Array(values) => values
+                    .iter()
+                    .map(|value| value.as_str().map(str::to_string))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| error("must be a string or an array of strings")),
+                _ => Err(error("must be a string or an array of strings")),
+            };
+            let destinations = &mut route.destinations;
+            match key.replace('_', "-").as_str() {
+                "name" => {}
+                "service" => route.services = strings()?,
+                "region" => route.regions = strings()?,
+                "category" => {
+                    route.categories = strings()?
+                        .iter()
+                        .map(|category| {
+                            Category::from_str(category, false)
+                                .map(|_| category.clone())
+                                .map_err(|_| error(&format!("has unknown category {:?}", category)))
+                        })
+                        .collect::<Result<_, _>>()?
+                }
+                "account" => route.accounts = strings()?,
+                "severity" => {
+                    route.severities = strings()?;
+                    if let Some(unknown) = route
+                        .severities
+                        .iter()
+                        .find(|severity| !SEVERITIES.contains(&severity.as_str()))
+                    {
+                        return Err(error(&format!(
+                            "has unknown severity {:?}; expected one of {}",
+                            unknown,
+                            SEVERITIES.join(", ")
+                        )));
+                    }
+                }
+                "slack-webhook" => destinations.slack_webhook = Some(string()?),
+                "teams-webhook" => destinations.teams_webhook = Some(string()?),
+                "discord-webhook" => destinations.discord_webhook = Some(string()?),
+                "telegram-chat-id" => destinations.telegram_chat_id = Some(string()?),
+                "pagerduty-routing-key" => destinations.pagerduty_routing_key = Some(string()?),
+                "webhook-url" => destinations.webhook_url = Some(string()?),
+                "email-to" => destinations.email_to = strings()?,
+                _ => return Err(format!("route {}: unknown setting `{}`", route.name, key)),
+            }
+        }
+        if route.destinations.is_empty() {
+            return Err(format!(
+                "route {} has no destination, such as slack-webhook or email-to",
+                route.name
+            ));
+        }
+        routes.push(route);
+    }
+    Ok(routes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toml;
+
+    #[test]
+    fn matches_events_on_every_criterion() {
+        let config = toml::parse(
+            "[[routes]]\nname = \"db-team\"\nservice = [\"RDS\"]\nseverity = \"critical\"\n\
+             slack-webhook = \"https://hooks.slack.com/services/x\"",
+        )
+        .unwrap();
+        let routes = parse(&config["routes"]).unwrap();
+        let event = |service: &str, category: &str| HealthEvent {
+            service: service.to_string(),
+            event_type_category: category.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(routes[0].name, "db-team");
+        assert!(routes[0].matches(&event("RDS", "issue")));
+        assert!(!routes[0].matches(&event("RDS", "scheduledChange")));
+        assert!(!routes[0].matches(&event("EC2", "issue")));
+
+        let unrouted = toml::parse("[[routes]]\nservice = \"RDS\"").unwrap();
+        assert_eq!(
+            parse(&unrouted["routes"]).unwrap_err(),
+            "route #1 has no destination, such as slack-webhook or email-to"
+        );
+    }
+}
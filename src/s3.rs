@@ -0,0 +1,183 @@
+//! Uploads the files a run wrote to S3 (`--s3-uri`).
+
+use crate::sigv4::{SignedClient, xml_error_message};
+use aws_sigv4::http_request::{
+    PayloadChecksumKind, PercentEncodingMode, SigningSettings, UriPathNormalizationMode,
+};
+use chrono::Utc;
+use chrono::format::{Item, StrftimeItems};
+use std::error::Error as StdError;
+use std::fs;
+use std::path::Path;
+
+/// Where `--s3-uri` points: a bucket, and a key prefix that may contain
+/// strftime placeholders.
+#[derive(Debug, Clone, PartialEq)]
+pub struct S3Uri {
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Uri {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let rest = value
+            .strip_prefix("s3://")
+            .ok_or("expected s3://bucket/prefix")?;
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err("missing bucket name".to_string());
+        }
+        if StrftimeItems::new(prefix).any(|item| item == Item::Error) {
+            return Err("invalid strftime placeholder in the key prefix".to_string());
+        }
+        Ok(S3Uri {
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+        })
+    }
+}
+
+pub struct Uploader {
+    client: SignedClient,
+    uri: S3Uri,
+    /// The key prefix, with placeholders expanded once for the whole run
+    prefix: String,
+    kms_key_id: Option<String>,
+}
+
+impl Uploader {
+    pub fn new(client: SignedClient, uri: S3Uri, kms_key_id: Option<String>) -> Self {
+        let prefix = Utc::now().format(&uri.prefix).to_string();
+        Uploader {
+            client,
+            uri,
+            prefix,
+            kms_key_id,
+        }
+    }
+
+    /// Uploads the file at `path` as the prefix followed by `name`,
+    /// returning its S3 URI.
+    pub async fn upload(&self, path: &Path, name: &str) -> Result<String, Box<dyn StdError>> {
+        let body = fs::read(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let key = format!("{}{}", self.prefix, name);
+
+        let mut region = self.client.region().to_string();
+        let mut response = self.put_object(&region, &key, body.clone()).await?;
+        // A bucket in another region answers with a redirect naming it
+        if response.status() == 301
+            && let Some(bucket_region) = response
+                .headers()
+                .get("x-amz-bucket-region")
+                .and_then(|value| value.to_str().ok())
+        {
+            region = bucket_region.to_string();
+            response = self.put_object(&region, &key, body).await?;
+        }
+        if !response.status().is_success() {
+            return Err(xml_error_message(
+                "S3 upload",
+                response.status().as_u16(),
+                response.body(),
+            )
+            .into());
+        }
+        Ok(format!("s3://{}/{}", self.uri.bucket, key))
+    }
+
+    async fn put_object(
+        &self,
+        region: &str,
+        key: &str,
+        body: Vec<u8>,
+    ) -> Result<http::Response<Vec<u8>>, Box<dyn StdError>> {
+        let bucket = &self.uri.bucket;
+        let key = encode_key(key);
+        // Buckets with dots in their names don't match the wildcard
+        // certificate of virtual-hosted URLs
+        let endpoint = self.client.endpoint("s3", region);
+        let uri = if self.client.endpoint_url().is_some() || bucket.contains('.') {
+            format!("{}{}/{}", endpoint, bucket, key)
+        } else {
+            format!(
+                "https://{}.{}{}",
+                bucket,
+                endpoint.trim_start_matches("https://"),
+                key
+            )
+        };
+
+        let mut headers = vec![("content-type", content_type(&key))];
+        if let Some(kms_key_id) = &self.kms_key_id {
+            headers.push(("x-amz-server-side-encryption", "aws:kms"));
+            headers.push((
+                "x-amz-server-side-encryption-aws-kms-key-id",
+                kms_key_id.as_str(),
+            ));
+        }
+        // S3 signs the path as sent, and wants the payload hash as a header
+        let mut settings = SigningSettings::default();
+        settings.percent_encoding_mode = PercentEncodingMode::Single;
+        settings.uri_path_normalization_mode = UriPathNormalizationMode::Disabled;
+        settings.payload_checksum_kind = PayloadChecksumKind::XAmzSha256;
+        self.client
+            .send("PUT", &uri, "s3", region, settings, &headers, body)
+            .await
+    }
+}
+
+/// Percent-encodes an object key for a URL path, keeping its slashes.
+fn encode_key(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// The content type for a report, by its extension, so S3 serves HTML
+/// reports as web pages.
+pub fn content_type(key: &str) -> &'static str {
+    match key.rsplit('.').next().unwrap_or_default() {
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "jsonl" => "application/x-ndjson",
+        "yaml" => "application/yaml",
+        "html" => "text/html",
+        "parquet" => "application/vnd.apache.parquet",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "ics" => "text/calendar",
+        "atom" => "application/atom+xml",
+        "lp" => "text/plain",
+        "gz" => "application/gzip",
+        "zst" => "application/zstd",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bucket_and_key_prefix() {
+        assert_eq!(
+            S3Uri::parse("s3://reports/health/%Y/%m/"),
+            Ok(S3Uri {
+                bucket: "reports".to_string(),
+                prefix: "health/%Y/%m/".to_string(),
+            })
+        );
+        assert_eq!(S3Uri::parse("s3://reports").unwrap().prefix, "");
+        assert!(S3Uri::parse("reports/health").is_err());
+        assert_eq!(
+            encode_key("health/region=us-east-1/a b.csv"),
+            "health/region%3Dus-east-1/a%20b.csv"
+        );
+    }
+}
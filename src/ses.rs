@@ -0,0 +1,110 @@
+//! Sends notification mail through Amazon SES (`--ses`), as raw MIME so
+//! digests keep their attachments.
+
+use crate::email::Message;
+use crate::sigv4::SignedClient;
+use aws_sigv4::http_request::SigningSettings;
+use aws_smithy_json::deserialize::json_token_iter;
+use aws_smithy_json::deserialize::token::expect_document;
+use aws_smithy_json::serialize::JsonObjectWriter;
+use aws_smithy_types::{Document, base64};
+use std::error::Error as StdError;
+
+pub struct SesSender {
+    client: SignedClient,
+}
+
+impl SesSender {
+    pub fn new(client: SignedClient) -> Self {
+        SesSender { client }
+    }
+
+    /// Sends each message with `SendEmail`, in the configured region.
+    pub async fn send(&self, messages: &[Message]) -> Result<(), Box<dyn StdError>> {
+        let region = self.client.region();
+        let uri = format!(
+            "{}v2/email/outbound-emails",
+            self.client.endpoint("email", region)
+        );
+        for message in messages {
+            let mut body = String::new();
+            let mut request = JsonObjectWriter::new(&mut body);
+            request.key("FromEmailAddress").string(&message.from);
+            let mut destination = request.key("Destination").start_object();
+            let mut to = destination.key("ToAddresses").start_array();
+            for address in &message.to {
+                to.value().string(address);
+            }
+            to.finish();
+            destination.finish();
+            let mut content = request.key("Content").start_object();
+            let mut raw = content.key("Raw").start_object();
+            raw.key("Data").string(&base64::encode(message.to_mime()));
+            raw.finish();
+            content.finish();
+            request.finish();
+
+            let response = self
+                .client
+                .send(
+                    "POST",
+                    &uri,
+                    "ses",
+                    region,
+                    SigningSettings::default(),
+                    &[("content-type", "application/json")],
+                    body.into_bytes(),
+                )
+                .await?;
+            if !response.status().is_success() {
+                let code = response
+                    .headers()
+                    .get("x-amzn-errortype")
+                    .and_then(|value| value.to_str().ok());
+                return Err(
+                    error_message(code, response.status().as_u16(), response.body()).into(),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describes a REST JSON error, whose code comes in the `x-amzn-ErrorType`
+/// header, e.g. `MessageRejected:http://internal.amazon.com/...`.
+fn error_message(code: Option<&str>, status: u16, body: &[u8]) -> String {
+    let code = code.and_then(|code| code.split(':').next());
+    let message = match expect_document(&mut json_token_iter(body).peekable()) {
+        Ok(Document::Object(error)) => match error.get("message").or(error.get("Message")) {
+            Some(Document::String(message)) => Some(message.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    match (code, message) {
+        (Some(code), Some(message)) => format!("SendEmail failed: {}: {}", code, message),
+        (Some(code), None) => format!("SendEmail failed: {}", code),
+        _ => format!("SendEmail failed with HTTP status {}", status),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_errors() {
+        assert_eq!(
+            error_message(
+                Some("MessageRejected:http://internal.amazon.com/coral/com.amazonaws.sesv2/"),
+                400,
+                br#"{"message":"Email address is not verified."}"#
+            ),
+            "SendEmail failed: MessageRejected: Email address is not verified."
+        );
+        assert_eq!(
+            error_message(None, 503, b""),
+            "SendEmail failed with HTTP status 503"
+        );
+    }
+}
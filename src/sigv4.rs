@@ -0,0 +1,268 @@
+//! Signed requests to AWS APIs the tool only makes a call or two to, so
+//! they don't each need a whole SDK as a dependency.
+
+use crate::http_client::HttpClient;
+use crate::partition::Partition;
+use crate::proxy::ProxyHttpClient;
+use aws_config::SdkConfig;
+use aws_credential_types::Credentials;
+use aws_credential_types::provider::ProvideCredentials;
+use aws_sigv4::http_request::{SignableBody, SignableRequest, SigningSettings, sign};
+use aws_sigv4::sign::v4;
+use aws_smithy_json::deserialize::json_token_iter;
+use aws_smithy_json::deserialize::token::expect_document;
+use aws_smithy_types::Document;
+use std::error::Error as StdError;
+use std::fmt;
+use std::time::SystemTime;
+
+/// Sends requests signed with the configured credentials, through the
+/// configured proxy.
+pub struct SignedClient {
+    http: HttpClient,
+    credentials: Credentials,
+    region: String,
+    partition: Partition,
+    endpoint_url: Option<String>,
+}
+
+impl SignedClient {
+    pub async fn new(
+        config: &SdkConfig,
+        proxy: Option<&ProxyHttpClient>,
+    ) -> Result<Self, Box<dyn StdError>> {
+        let credentials = config
+            .credentials_provider()
+            .ok_or("no credentials configured")?
+            .provide_credentials()
+            .await?;
+        Ok(SignedClient {
+            http: HttpClient::new(proxy),
+            credentials,
+            region: config
+                .region()
+                .map(|region| region.to_string())
+                .unwrap_or_else(|| "us-east-1".to_string()),
+            partition: Partition::of_region(config.region()),
+            endpoint_url: config.endpoint_url().map(str::to_string),
+        })
+    }
+
+    /// The configured region.
+    pub fn region(&self) -> &str {
+        &self.region
+    }
+
+    /// The `--endpoint-url` override, if any. It applies to every service, so
+    /// mock servers see every call.
+    pub fn endpoint_url(&self) -> Option<&str> {
+        self.endpoint_url.as_deref()
+    }
+
+    /// The endpoint of the service with `prefix` (e.g. `sqs`) in `region`.
+    pub fn endpoint(&self, prefix: &str, region: &str) -> String {
+        match &self.endpoint_url {
+            Some(endpoint_url) => format!("{}/", endpoint_url.trim_end_matches('/')),
+            None => format!(
+                "https://{}.{}.{}/",
+                prefix,
+                region,
+                self.partition.dns_suffix()
+            ),
+        }
+    }
+
+    /// Signs a request for the service `signing_name` in `region` and sends
+    /// it, returning the response whatever its status.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send(
+        &self,
+        method: &str,
+        uri: &str,
+        signing_name: &str,
+        region: &str,
+        settings: SigningSettings,
+        headers: &[(&str, &str)],
+        body: Vec<u8>,
+    ) -> Result<http::Response<Vec<u8>>, Box<dyn StdError>> {
+        let identity = self.credentials.clone().into();
+        let params = v4::SigningParams::builder()
+            .identity(&identity)
+            .region(region)
+            .name(signing_name)
+            .time(SystemTime::now())
+            .settings(settings)
+            .build()?
+            .into();
+        let signable = SignableRequest::new(
+            method,
+            uri,
+            headers.iter().copied(),
+            SignableBody::Bytes(&body),
+        )?;
+        let (instructions, _signature) = sign(signable, &params)?.into_parts();
+
+        let headers: Vec<(&str, &str)> = headers
+            .iter()
+            .copied()
+            .chain(instructions.headers())
+            .collect();
+        self.http.send(method, uri, &headers, body).await
+    }
+
+    /// Calls `target` (e.g. `AmazonSQS.SendMessageBatch`) on an AWS JSON
+    /// protocol API at `endpoint`, returning the response body.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn json(
+        &self,
+        endpoint: &str,
+        signing_name: &str,
+        region: &str,
+        json_version: &str,
+        target: &str,
+        body: String,
+    ) -> Result<Vec<u8>, Box<dyn StdError>> {
+        let content_type = format!("application/x-amz-json-{}", json_version);
+        let response = self
+            .send(
+                "POST",
+                endpoint,
+                signing_name,
+                region,
+                SigningSettings::default(),
+                &[("content-type", &content_type), ("x-amz-target", target)],
+                body.into_bytes(),
+            )
+            .await?;
+        if !response.status().is_success() {
+            let action = target.rsplit('.').next().unwrap_or(target);
+            return Err(Box::new(ApiError {
+                code: json_error_code(response.body()),
+                message: json_error_message(action, response.status().as_u16(), response.body()),
+            }));
+        }
+        Ok(response.into_body())
+    }
+
+    /// Calls `action` on an AWS Query protocol API such as SNS, returning
+    /// the response body.
+    pub async fn query(
+        &self,
+        endpoint_prefix: &str,
+        region: &str,
+        version: &str,
+        action: &str,
+        params: &[(String, String)],
+    ) -> Result<Vec<u8>, Box<dyn StdError>> {
+        let mut body = format!("Action={}&Version={}", action, version);
+        for (name, value) in params {
+            body.push('&');
+            body.push_str(&form_encode(name));
+            body.push('=');
+            body.push_str(&form_encode(value));
+        }
+        let response = self
+            .send(
+                "POST",
+                &self.endpoint(endpoint_prefix, region),
+                endpoint_prefix,
+                region,
+                SigningSettings::default(),
+                &[("content-type", "application/x-www-form-urlencoded")],
+                body.into_bytes(),
+            )
+            .await?;
+        if !response.status().is_success() {
+            return Err(
+                xml_error_message(action, response.status().as_u16(), response.body()).into(),
+            );
+        }
+        Ok(response.into_body())
+    }
+}
+
+/// Percent-encodes `value` for a form body.
+fn form_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// An error response from an AWS JSON protocol API.
+#[derive(Debug)]
+pub struct ApiError {
+    /// The error code, e.g. `ResourceNotFoundException`
+    pub code: Option<String>,
+    message: String,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl StdError for ApiError {}
+
+/// Whether `err` is an `ApiError` with `code`.
+pub fn is_api_error(err: &(dyn StdError + 'static), code: &str) -> bool {
+    err.downcast_ref::<ApiError>()
+        .is_some_and(|err| err.code.as_deref() == Some(code))
+}
+
+/// The code of a JSON error response. `__type` may be prefixed with the
+/// model namespace, e.g. `aws#Code`.
+fn json_error_code(body: &[u8]) -> Option<String> {
+    match expect_document(&mut json_token_iter(body).peekable()).ok()? {
+        Document::Object(error) => match error.get("__type") {
+            Some(Document::String(code)) => {
+                Some(code.rsplit('#').next().unwrap_or_default().to_string())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Describes a JSON error response, e.g. `ListAccounts failed:
+/// AccessDeniedException: ...`.
+pub fn json_error_message(action: &str, status: u16, body: &[u8]) -> String {
+    let document = expect_document(&mut json_token_iter(body).peekable()).ok();
+    let field = |key: &str| match &document {
+        Some(Document::Object(error)) => match error.get(key) {
+            Some(Document::String(value)) => Some(value.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    let code = json_error_code(body);
+    let message = field("Message").or_else(|| field("message"));
+    match (code, message) {
+        (Some(code), Some(message)) => format!("{} failed: {}: {}", action, code, message),
+        (Some(code), None) => format!("{} failed: {}", action, code),
+        _ => format!("{} failed with HTTP status {}", action, status),
+    }
+}
+
+/// Describes an XML error response, e.g. `Publish failed: NotFound: Topic
+/// does not exist`.
+pub fn xml_error_message(action: &str, status: u16, body: &[u8]) -> String {
+    let body = String::from_utf8_lossy(body);
+    let element = |name: &str| {
+        let start = body.find(&format!("<{}>", name))? + name.len() + 2;
+        let end = start + body[start..].find(&format!("</{}>", name))?;
+        Some(body[start..end].to_string())
+    };
+    match (element("Code"), element("Message")) {
+        (Some(code), Some(message)) => format!("{} failed: {}: {}", action, code, message),
+        (Some(code), None) => format!("{} failed: {}", action, code),
+        _ => format!("{} failed with HTTP status {}", action, status),
+    }
+}
@@ -0,0 +1,191 @@
+//! Posts a Block Kit message per event to a Slack incoming webhook
+//! (`--slack-webhook`).
+
+use crate::HealthEvent;
+use crate::http_client::HttpClient;
+use crate::notify::{self, console_url, excerpt, title};
+use aws_smithy_json::serialize::{JsonArrayWriter, JsonObjectWriter};
+use std::error::Error as StdError;
+
+/// Descriptions are cut to this many characters; the link has the rest.
+const MAX_DESCRIPTION_CHARS: usize = 600;
+
+/// Slack rejects messages with section fields longer than 2,000 characters.
+const MAX_FIELD_CHARS: usize = 2000;
+
+pub struct SlackNotifier {
+    http: HttpClient,
+    webhook: String,
+}
+
+impl SlackNotifier {
+    pub fn new(http: HttpClient, webhook: String) -> Self {
+        SlackNotifier { http, webhook }
+    }
+
+    /// Posts one message per event, in order.
+    pub async fn notify(&self, events: &[HealthEvent]) -> Result<(), Box<dyn StdError>> {
+        for event in events {
+            notify::post(
+                &self.http,
+                "Slack",
+                &self.webhook,
+                &[("content-type", "application/json")],
+                message(event),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// The event as a Block Kit message, with a plain-text fallback for
+/// notifications.
+fn message(event: &HealthEvent) -> String {
+    let title = title(event);
+    let mut message = String::new();
+    let mut object = JsonObjectWriter::new(&mut message);
+    object.key("text").string(&title);
+    let mut blocks = object.key("blocks").start_array();
+
+    let mut header = blocks.value().start_object();
+    header.key("type").string("header");
+    text(&mut header, "plain_text", &excerpt(&title, 150));
+    header.finish();
+
+    let mut section = blocks.value().start_object();
+    section.key("type").string("section");
+    let mut fields = section.key("fields").start_array();
+    field(&mut fields, "Service", &event.service);
+    field(&mut fields, "Region", &event.region);
+    field(&mut fields, "Status", &event.status);
+    field(&mut fields, "Category", &event.event_type_category);
+    field(&mut fields, "Start", &event.timestamp);
+    if !event.affected_accounts.is_empty() {
+        field(&mut fields, "Accounts", &event.accounts_text());
+    }
+    fields.finish();
+    section.finish();
+
+    let mut section = blocks.value().start_object();
+    section.key("type").string("section");
+    text(
+        &mut section,
+        "mrkdwn",
+        &escape(&excerpt(&event.detail, MAX_DESCRIPTION_CHARS)),
+    );
+    section.finish();
+
+    let mut context = blocks.value().start_object();
+    context.key("type").string("context");
+    let mut elements = context.key("elements").start_array();
+    let mut link = elements.value().start_object();
+    link.key("type").string("mrkdwn");
+    link.key("text").string(&format!(
+        "<{}|View in the AWS Health Dashboard>",
+        console_url(event)
+    ));
+    link.finish();
+    elements.finish();
+    context.finish();
+
+    blocks.finish();
+    object.finish();
+    message
+}
+
+fn text(block: &mut JsonObjectWriter<'_>, kind: &str, text: &str) {
+    let mut object = block.key("text").start_object();
+    object.key("type").string(kind);
+    object.key("text").string(text);
+    object.finish();
+}
+
+fn field(fields: &mut JsonArrayWriter<'_>, label: &str, value: &str) {
+    let value = if value.is_empty() { "-" } else { value };
+    let label = format!("*{}*\n", label);
+    let value = escaped_excerpt(value, MAX_FIELD_CHARS - label.chars().count());
+    let mut object = fields.value().start_object();
+    object.key("type").string("mrkdwn");
+    object.key("text").string(&(label + &value));
+    object.finish();
+}
+
+/// `value`, escaped and cut so it's at most `max_chars` characters once
+/// escaped. Cutting before escaping keeps entities like `&amp;` whole.
+fn escaped_excerpt(value: &str, max_chars: usize) -> String {
+    let mut limit = max_chars;
+    loop {
+        let escaped = escape(&excerpt(value, limit));
+        match escaped.chars().count().checked_sub(max_chars) {
+            Some(over) if over > 0 => limit -= over,
+            _ => return escaped,
+        }
+    }
+}
+
+/// Escapes the characters Slack's mrkdwn gives meaning to.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_smithy_json::deserialize::json_token_iter;
+    use aws_smithy_json::deserialize::token::expect_document;
+
+    #[test]
+    fn builds_block_kit_messages() {
+        let event = HealthEvent {
+            timestamp: "2024-03-06 09:00:00".to_string(),
+            arn: "arn:aws:health:us-east-1::event/EC2/AWS_EC2_OPERATIONAL_ISSUE/1".to_string(),
+            service: "EC2".to_string(),
+            region: "us-east-1".to_string(),
+            status: "open".to_string(),
+            event_type_code: "AWS_EC2_OPERATIONAL_ISSUE".to_string(),
+            event_type_category: "issue".to_string(),
+            detail: "Errors <rising> & climbing".to_string(),
+            affected_accounts: (0..200).map(|i| format!("{:012}", i)).collect(),
+            ..Default::default()
+        };
+        let message = message(&event);
+        let document =
+            expect_document(&mut json_token_iter(message.as_bytes()).peekable()).unwrap();
+        let message = document.as_object().unwrap();
+        assert_eq!(
+            message["text"].as_string(),
+            Some("EC2 operational issue (us-east-1)")
+        );
+        let blocks = message["blocks"].as_array().unwrap();
+        let fields: Vec<&str> = blocks[1].as_object().unwrap()["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| field.as_object().unwrap()["text"].as_string().unwrap())
+            .collect();
+        assert_eq!(
+            fields[..5],
+            [
+                "*Service*\nEC2",
+                "*Region*\nus-east-1",
+                "*Status*\nopen",
+                "*Category*\nissue",
+                "*Start*\n2024-03-06 09:00:00",
+            ]
+        );
+        // 200 accounts are 2,798 characters, more than Slack takes
+        assert!(fields[5].starts_with("*Accounts*\n000000000000, 000000000001"));
+        assert!(fields[5].ends_with('…'));
+        assert!(fields[5].chars().count() <= MAX_FIELD_CHARS);
+        let detail = &blocks[2].as_object().unwrap()["text"].as_object().unwrap()["text"];
+        assert_eq!(
+            detail.as_string(),
+            Some("Errors &lt;rising&gt; &amp; climbing")
+        );
+
+        assert_eq!(escaped_excerpt("a & b & c", 12), "a &amp; b…");
+    }
+}
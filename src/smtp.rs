@@ -0,0 +1,250 @@
+//! Sends mail through an SMTP server (`--smtp-host`), over implicit TLS or
+//! STARTTLS, authenticating with AUTH PLAIN when given credentials.
+
+use crate::email::Message;
+use aws_smithy_types::base64;
+use clap::{Args, ValueEnum};
+use hyper_rustls::ConfigBuilderExt;
+use std::error::Error as StdError;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::rustls::ClientConfig;
+use tokio_rustls::rustls::pki_types::ServerName;
+
+/// How long to wait for the server to connect or answer a command.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// Where notification mail is sent. (A doc comment here would replace the
+// program description in --help.)
+#[derive(Args, Debug)]
+pub struct SmtpArgs {
+    /// Email new events through this SMTP server, to --email-to
+    #[arg(
+        long,
+        env = "AWS9MAN_SMTP_HOST",
+        value_name = "HOST",
+        requires = "email_from"
+    )]
+    pub smtp_host: Option<String>,
+
+    /// SMTP port [default: 587, or 465 with --smtp-tls implicit and 25 with
+    /// --smtp-tls none]
+    #[arg(
+        long,
+        env = "AWS9MAN_SMTP_PORT",
+        value_name = "PORT",
+        requires = "smtp_host"
+    )]
+    smtp_port: Option<u16>,
+
+    /// How to encrypt the SMTP connection
+    #[arg(long, env = "AWS9MAN_SMTP_TLS", value_enum, default_value_t = SmtpTls::Starttls)]
+    smtp_tls: SmtpTls,
+
+    /// SMTP user name, for AUTH PLAIN
+    #[arg(long, env = "AWS9MAN_SMTP_USER", value_name = "USER", requires_all = ["smtp_host", "smtp_password"])]
+    smtp_user: Option<String>,
+
+    /// SMTP password for --smtp-user
+    #[arg(
+        long,
+        env = "AWS9MAN_SMTP_PASSWORD",
+        value_name = "PASSWORD",
+        hide_env_values = true,
+        requires = "smtp_user"
+    )]
+    smtp_password: Option<String>,
+}
+
+impl SmtpArgs {
+    /// The server to mail through, if one was given.
+    pub fn server(&self) -> Option<SmtpServer> {
+        Some(SmtpServer {
+            host: self.smtp_host.clone()?,
+            port: self.smtp_port.unwrap_or(self.smtp_tls.default_port()),
+            tls: self.smtp_tls,
+            credentials: self.smtp_user.clone().zip(self.smtp_password.clone()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SmtpTls {
+    /// Upgrade a plain connection with STARTTLS (usually port 587)
+    Starttls,
+    /// Connect over TLS from the start (usually port 465)
+    Implicit,
+    /// Don't encrypt, e.g. for a relay on localhost
+    None,
+}
+
+impl SmtpTls {
+    fn default_port(self) -> u16 {
+        match self {
+            SmtpTls::Starttls => 587,
+            SmtpTls::Implicit => 465,
+            SmtpTls::None => 25,
+        }
+    }
+}
+
+pub struct SmtpServer {
+    host: String,
+    port: u16,
+    tls: SmtpTls,
+    /// User name and password for AUTH PLAIN
+    credentials: Option<(String, String)>,
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+struct Connection {
+    stream: BufStream<Pin<Box<dyn Stream>>>,
+}
+
+impl SmtpServer {
+    /// Sends `messages` in one session.
+    pub async fn send(&self, messages: &[Message]) -> Result<(), Box<dyn StdError>> {
+        let tcp = timeout(TIMEOUT, TcpStream::connect((self.host.as_str(), self.port)))
+            .await
+            .map_err(|_| format!("timed out connecting to {}:{}", self.host, self.port))?
+            .map_err(|err| format!("could not connect to {}:{}: {}", self.host, self.port, err))?;
+        let stream: Pin<Box<dyn Stream>> = match self.tls {
+            SmtpTls::Implicit => Box::pin(self.wrap_tls(tcp).await?),
+            _ => Box::pin(tcp),
+        };
+        let mut connection = Connection {
+            stream: BufStream::new(stream),
+        };
+        connection.reply(220).await?;
+        connection.command("EHLO aws9man", 250).await?;
+        if self.tls == SmtpTls::Starttls {
+            connection.command("STARTTLS", 220).await?;
+            let stream = connection.stream.into_inner();
+            connection = Connection {
+                stream: BufStream::new(Box::pin(self.wrap_tls(stream).await?)),
+            };
+            connection.command("EHLO aws9man", 250).await?;
+        }
+        if let Some((user, password)) = &self.credentials {
+            let token = base64::encode(format!("\0{}\0{}", user, password));
+            connection
+                .command(&format!("AUTH PLAIN {}", token), 235)
+                .await?;
+        }
+
+        for message in messages {
+            connection
+                .command(&format!("MAIL FROM:<{}>", address(&message.from)), 250)
+                .await?;
+            for to in &message.to {
+                connection
+                    .command(&format!("RCPT TO:<{}>", address(to)), 250)
+                    .await?;
+            }
+            connection.command("DATA", 354).await?;
+            connection.write(&dot_stuff(&message.to_mime())).await?;
+            connection.command(".", 250).await?;
+        }
+        connection.command("QUIT", 221).await?;
+        Ok(())
+    }
+
+    async fn wrap_tls<S>(&self, stream: S) -> io::Result<tokio_rustls::client::TlsStream<S>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let config = ClientConfig::builder()
+            .with_native_roots()?
+            .with_no_client_auth();
+        let name = ServerName::try_from(self.host.clone())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        TlsConnector::from(Arc::new(config))
+            .connect(name, stream)
+            .await
+    }
+}
+
+impl Connection {
+    async fn write(&mut self, data: &str) -> io::Result<()> {
+        self.stream.write_all(data.as_bytes()).await?;
+        self.stream.flush().await
+    }
+
+    async fn command(&mut self, command: &str, expected: u16) -> Result<(), Box<dyn StdError>> {
+        self.write(&format!("{}\r\n", command)).await?;
+        self.reply(expected).await.map_err(|err| {
+            // Don't echo credentials into errors
+            let verb = command.split(' ').next().unwrap_or(command);
+            format!("SMTP {} failed: {}", verb, err).into()
+        })
+    }
+
+    /// Reads a reply, which may span several lines, and checks its code.
+    async fn reply(&mut self, expected: u16) -> Result<(), Box<dyn StdError>> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            let read = timeout(TIMEOUT, self.stream.read_line(&mut line))
+                .await
+                .map_err(|_| "timed out waiting for the server")??;
+            if read == 0 {
+                return Err("the server closed the connection".into());
+            }
+            text.push_str(line.trim_end());
+            // `250-...` continues the reply; `250 ...` ends it
+            if line.as_bytes().get(3) != Some(&b'-') {
+                break;
+            }
+            text.push(' ');
+        }
+        match text.get(..3).and_then(|code| code.parse::<u16>().ok()) {
+            // 251 (user not local; will forward) accepts a recipient too
+            Some(code) if code == expected || (expected == 250 && code == 251) => Ok(()),
+            _ => Err(text.into()),
+        }
+    }
+}
+
+/// The address in `Name <address>`, or the whole value if it's bare.
+fn address(mailbox: &str) -> &str {
+    match (mailbox.rfind('<'), mailbox.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &mailbox[start + 1..end],
+        _ => mailbox.trim(),
+    }
+}
+
+/// Doubles leading dots so no line ends the DATA early, and ends with CRLF.
+fn dot_stuff(mime: &str) -> String {
+    let mut data = String::with_capacity(mime.len() + 2);
+    for line in mime.split_inclusive("\r\n") {
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+    }
+    if !data.ends_with("\r\n") {
+        data.push_str("\r\n");
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_addresses_and_data() {
+        assert_eq!(address("Ops <ops@example.com>"), "ops@example.com");
+        assert_eq!(address(" ops@example.com "), "ops@example.com");
+        assert_eq!(dot_stuff("a\r\n.b\r\nc"), "a\r\n..b\r\nc\r\n");
+    }
+}
@@ -0,0 +1,234 @@
+//! Publishes events to an SNS topic (`--sns-topic-arn`), as the JSON objects
+//! of JSON reports.
+
+use crate::HealthEvent;
+use crate::notify::excerpt;
+use crate::output::event_to_json;
+use crate::sigv4::SignedClient;
+use crate::sqs::{deduplication_id, fit_id};
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error as StdError;
+use std::hash::{Hash, Hasher};
+
+const API_VERSION: &str = "2010-03-31";
+/// SNS rejects messages larger than 256 KiB.
+const MAX_MESSAGE_BYTES: usize = 256 * 1024;
+/// FIFO topics keep digests in one group, in the order published.
+const DIGEST_GROUP_ID: &str = "aws-health-digest";
+
+/// Checks that `value` is an SNS topic ARN.
+pub fn parse_topic_arn(value: &str) -> Result<String, String> {
+    match value.split(':').collect::<Vec<_>>()[..] {
+        ["arn", _, "sns", region, _, name] if !region.is_empty() && !name.is_empty() => {
+            Ok(value.to_string())
+        }
+        _ => Err("expected arn:<partition>:sns:<region>:<account>:<topic>".to_string()),
+    }
+}
+
+pub struct SnsPublisher {
+    client: SignedClient,
+    topic_arn: String,
+}
+
+impl SnsPublisher {
+    pub fn new(client: SignedClient, topic_arn: String) -> Self {
+        SnsPublisher { client, topic_arn }
+    }
+
+    /// Publishes one message per event. The event's service, region and
+    /// category are message attributes too, for subscription filter
+    /// policies. Events too large for a message lose their entities and
+    /// then words of their description; those still too large are skipped.
+    pub async fn publish_events(&self, events: &[HealthEvent]) -> Result<(), Box<dyn StdError>> {
+        for event in events {
+            let Some(message) = fitted_json(event) else {
+                continue;
+            };
+            let mut params = vec![
+                (
+                    "Subject".to_string(),
+                    format!("AWS Health: {}", event.event_type_code),
+                ),
+                ("Message".to_string(), message),
+            ];
+            if self.is_fifo() {
+                // Keeps each event's updates in order, and drops a resend of
+                // the same update, as for SQS
+                params.push(("MessageGroupId".to_string(), fit_id(&event.arn).to_string()));
+                params.push((
+                    "MessageDeduplicationId".to_string(),
+                    deduplication_id(event),
+                ));
+            }
+            let attributes = [
+                ("service", &event.service),
+                ("region", &event.region),
+                ("category", &event.event_type_category),
+            ];
+            let attributes = attributes.iter().filter(|(_, value)| !value.is_empty());
+            for (index, (name, value)) in attributes.enumerate() {
+                let prefix = format!("MessageAttributes.entry.{}", index + 1);
+                params.push((format!("{}.Name", prefix), name.to_string()));
+                params.push((format!("{}.Value.DataType", prefix), "String".to_string()));
+                params.push((format!("{}.Value.StringValue", prefix), value.to_string()));
+            }
+            self.publish(params).await?;
+        }
+        Ok(())
+    }
+
+    /// Publishes every event in as few messages as fit, each a JSON array,
+    /// returning the number of messages.
+    pub async fn publish_digest(&self, events: &[HealthEvent]) -> Result<usize, Box<dyn StdError>> {
+        let messages = digest_messages(events);
+        let count = messages.len();
+        for (index, message) in messages.into_iter().enumerate() {
+            let subject = match count {
+                1 => format!("AWS Health: {} events", events.len()),
+                _ => format!(
+                    "AWS Health: {} events ({}/{})",
+                    events.len(),
+                    index + 1,
+                    count
+                ),
+            };
+            let mut params = vec![("Subject".to_string(), subject)];
+            if self.is_fifo() {
+                params.push(("MessageGroupId".to_string(), DIGEST_GROUP_ID.to_string()));
+                params.push(("MessageDeduplicationId".to_string(), digest_id(&message)));
+            }
+            params.push(("Message".to_string(), message));
+            self.publish(params).await?;
+        }
+        Ok(count)
+    }
+
+    fn is_fifo(&self) -> bool {
+        self.topic_arn.ends_with(".fifo")
+    }
+
+    async fn publish(&self, mut params: Vec<(String, String)>) -> Result<(), Box<dyn StdError>> {
+        params.insert(0, ("TopicArn".to_string(), self.topic_arn.clone()));
+        let region = self.topic_arn.split(':').nth(3).unwrap_or_default();
+        self.client
+            .query("sns", region, API_VERSION, "Publish", &params)
+            .await?;
+        Ok(())
+    }
+}
+
+/// The event's JSON object, cut to fit in a message: without its entities
+/// and then with a shorter description, the console link having the rest.
+/// `None`, with a warning, if even that is too large.
+fn fitted_json(event: &HealthEvent) -> Option<String> {
+    let json = event_to_json(event);
+    if json.len() <= MAX_MESSAGE_BYTES {
+        return Some(json);
+    }
+    let mut event = HealthEvent {
+        affected_entities: Vec::new(),
+        ..event.clone()
+    };
+    loop {
+        let json = event_to_json(&event);
+        if json.len() <= MAX_MESSAGE_BYTES {
+            return Some(json);
+        }
+        if event.detail.is_empty() {
+            eprintln!(
+                "Warning: {} is too large for an SNS message, skipping it",
+                event.arn
+            );
+            return None;
+        }
+        let chars = event.detail.chars().count();
+        event.detail = if chars > 1 {
+            excerpt(&event.detail, chars / 2)
+        } else {
+            String::new()
+        };
+    }
+}
+
+/// The events as JSON arrays of at most `MAX_MESSAGE_BYTES`, in order.
+fn digest_messages(events: &[HealthEvent]) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut message = String::new();
+    for event in events {
+        let Some(json) = fitted_json(event) else {
+            continue;
+        };
+        if !message.is_empty() && message.len() + json.len() + 2 > MAX_MESSAGE_BYTES {
+            messages.push(message + "]");
+            message = String::new();
+        }
+        message.push(if message.is_empty() { '[' } else { ',' });
+        message.push_str(&json);
+    }
+    if !message.is_empty() {
+        messages.push(message + "]");
+    }
+    messages
+}
+
+/// A deduplication ID for a digest, from its content: FIFO topics drop a
+/// resend of the same digest within five minutes.
+fn digest_id(message: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    message.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_topic_arns() {
+        let arn = "arn:aws:sns:eu-west-1:111111111111:health";
+        assert_eq!(parse_topic_arn(arn), Ok(arn.to_string()));
+        assert!(parse_topic_arn("arn:aws:sqs:eu-west-1:111111111111:health").is_err());
+        assert!(parse_topic_arn("health").is_err());
+    }
+
+    #[test]
+    fn splits_digests_at_256_kib() {
+        let event = |index: usize| HealthEvent {
+            arn: format!("arn:aws:health:us-east-1::event/EC2/{}", index),
+            detail: "x".repeat(100 * 1024),
+            ..Default::default()
+        };
+        let events: Vec<_> = (0..5).map(event).collect();
+        let messages = digest_messages(&events);
+        // Two 100 KiB events fit in a message, a third doesn't
+        assert_eq!(messages.len(), 3);
+        for message in &messages {
+            assert!(message.len() <= MAX_MESSAGE_BYTES);
+            assert!(message.starts_with('[') && message.ends_with(']'));
+        }
+        assert!(messages[0].contains("event/EC2/0\""));
+        assert!(messages[0].contains("event/EC2/1\""));
+        assert!(messages[2].contains("event/EC2/4\""));
+        assert_eq!(digest_messages(&[]), Vec::<String>::new());
+    }
+
+    #[test]
+    fn cuts_events_too_large_for_a_message() {
+        let event = HealthEvent {
+            arn: "arn:aws:health:us-east-1::event/EC2/1".to_string(),
+            detail: "word ".repeat(100 * 1024),
+            ..Default::default()
+        };
+        let json = fitted_json(&event).unwrap();
+        assert!(json.len() <= MAX_MESSAGE_BYTES);
+        assert!(json.contains("word…"));
+        assert!(json.contains("event/EC2/1"));
+
+        let small = HealthEvent {
+            detail: "Elevated errors".to_string(),
+            ..event
+        };
+        assert_eq!(fitted_json(&small), Some(event_to_json(&small)));
+    }
+}
@@ -0,0 +1,169 @@
+//! Forwards events to a Splunk HTTP Event Collector (`--splunk-hec-url`),
+//! one HEC event per Health event, in batches.
+
+use crate::HealthEvent;
+use crate::batch::batches;
+use crate::http_client::HttpClient;
+use crate::output::event_to_json;
+use aws_smithy_json::serialize::JsonObjectWriter;
+use aws_smithy_types::Number;
+use chrono::Utc;
+use std::error::Error as StdError;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// HEC takes 1 MB per request by default (`max_content_length`).
+const MAX_BATCH_EVENTS: usize = 1000;
+const MAX_BATCH_BYTES: usize = 1000 * 1000;
+/// Batches HEC turns away as busy (429 or 5xx), or that fail to reach it,
+/// are sent again this many times.
+const MAX_ATTEMPTS: usize = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The event endpoint for `--splunk-hec-url`: the URL itself if it names a
+/// collector endpoint, else its `/services/collector/event`.
+pub fn parse_hec_url(value: &str) -> Result<String, String> {
+    if !value.starts_with("https://") && !value.starts_with("http://") {
+        return Err("expected an http(s):// URL, e.g. https://splunk:8088".to_string());
+    }
+    match value.contains("/services/collector") {
+        true => Ok(value.to_string()),
+        false => Ok(format!(
+            "{}/services/collector/event",
+            value.trim_end_matches('/')
+        )),
+    }
+}
+
+pub struct SplunkHec {
+    http: HttpClient,
+    url: String,
+    authorization: String,
+    index: Option<String>,
+    sourcetype: String,
+}
+
+impl SplunkHec {
+    pub fn new(
+        http: HttpClient,
+        url: String,
+        token: &str,
+        index: Option<String>,
+        sourcetype: String,
+    ) -> Self {
+        SplunkHec {
+            http,
+            url,
+            authorization: format!("Splunk {}", token),
+            index,
+            sourcetype,
+        }
+    }
+
+    pub async fn send_events(&self, events: &[HealthEvent]) -> Result<(), Box<dyn StdError>> {
+        let now = Utc::now();
+        let records = events
+            .iter()
+            .map(|event| {
+                let time = event.last_updated_time.or(event.start_time).unwrap_or(now);
+                self.hec_event(event, time.timestamp_millis() as f64 / 1000.0)
+            })
+            .collect();
+        for batch in batches(records, MAX_BATCH_EVENTS, MAX_BATCH_BYTES, String::len) {
+            self.send_batch(batch.concat().into_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    /// The event wrapped in HEC's envelope, timed in seconds since the epoch.
+    fn hec_event(&self, event: &HealthEvent, time: f64) -> String {
+        let mut envelope = String::new();
+        let mut object = JsonObjectWriter::new(&mut envelope);
+        object.key("time").number(Number::Float(time));
+        object.key("source").string("aws9man");
+        object.key("sourcetype").string(&self.sourcetype);
+        if let Some(index) = &self.index {
+            object.key("index").string(index);
+        }
+        object.finish();
+        // Splice the event object in as the last field
+        envelope.pop();
+        format!("{},\"event\":{}}}", envelope, event_to_json(event))
+    }
+
+    async fn send_batch(&self, body: Vec<u8>) -> Result<(), Box<dyn StdError>> {
+        let headers = [
+            ("authorization", self.authorization.as_str()),
+            ("content-type", "application/json"),
+        ];
+        let mut last_failure = String::new();
+        for attempt in 0..MAX_ATTEMPTS {
+            if attempt > 0 {
+                sleep(RETRY_DELAY * attempt as u32).await;
+            }
+            match self
+                .http
+                .send("POST", &self.url, &headers, body.clone())
+                .await
+            {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    last_failure = format!(
+                        "HTTP status {}: {}",
+                        response.status().as_u16(),
+                        String::from_utf8_lossy(response.body()).trim()
+                    );
+                    if !response.status().is_server_error() && response.status() != 429 {
+                        break;
+                    }
+                }
+                Err(err) => last_failure = err.to_string(),
+            }
+        }
+        Err(format!("Splunk HEC request failed: {}", last_failure).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_event_endpoint() {
+        assert_eq!(
+            parse_hec_url("https://splunk.example.com:8088/").unwrap(),
+            "https://splunk.example.com:8088/services/collector/event"
+        );
+        assert_eq!(
+            parse_hec_url("https://http-inputs-acme.splunkcloud.com/services/collector").unwrap(),
+            "https://http-inputs-acme.splunkcloud.com/services/collector"
+        );
+        assert!(parse_hec_url("splunk:8088").is_err());
+
+        let hec = SplunkHec::new(
+            HttpClient::new(None),
+            "https://splunk:8088/services/collector/event".to_string(),
+            "token",
+            Some("aws".to_string()),
+            "aws:health".to_string(),
+        );
+        let event = HealthEvent {
+            service: "EC2".to_string(),
+            status: "open".to_string(),
+            event_type_category: "issue".to_string(),
+            ..Default::default()
+        };
+        let record = hec.hec_event(&event, 1709715600.5);
+        assert!(record.starts_with(
+            r#"{"time":1709715600.5,"source":"aws9man","sourcetype":"aws:health","index":"aws","event":{"#
+        ));
+        for field in [
+            r#""service":"EC2""#,
+            r#""status":"open""#,
+            r#""event_type_category":"issue""#,
+            r#""last_updated_time":null"#,
+        ] {
+            assert!(record.contains(field), "{} missing from {}", field, record);
+        }
+    }
+}
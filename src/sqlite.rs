@@ -0,0 +1,297 @@
+//! Upserts events into a local SQLite database through the `sqlite3` CLI.
+
+use crate::HealthEvent;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+const SCHEMA: &str = "\
+CREATE TABLE IF NOT EXISTS events (
+    arn TEXT PRIMARY KEY,
+    service TEXT NOT NULL DEFAULT '',
+    region TEXT NOT NULL DEFAULT '',
+    status TEXT NOT NULL DEFAULT '',
+    event_type_code TEXT NOT NULL DEFAULT '',
+    event_type_category TEXT NOT NULL DEFAULT '',
+    start_time TEXT,
+    end_time TEXT,
+    last_updated_time TEXT,
+    detail TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT ''
+);
+CREATE TABLE IF NOT EXISTS affected_entities (
+    event_arn TEXT NOT NULL REFERENCES events (arn) ON DELETE CASCADE,
+    entity_value TEXT NOT NULL,
+    account_id TEXT NOT NULL DEFAULT '',
+    entity_arn TEXT,
+    status TEXT,
+    last_updated_time TEXT,
+    PRIMARY KEY (event_arn, entity_value)
+);
+CREATE TABLE IF NOT EXISTS affected_accounts (
+    event_arn TEXT NOT NULL REFERENCES events (arn) ON DELETE CASCADE,
+    account_id TEXT NOT NULL,
+    PRIMARY KEY (event_arn, account_id)
+);
+CREATE TABLE IF NOT EXISTS accounts (
+    account_id TEXT PRIMARY KEY,
+    account_name TEXT NOT NULL
+);
+";
+
+/// Columns added since the first schema, which databases it created lack.
+const ADDED_COLUMNS: &[(&str, &str)] = &[
+    ("events", "service TEXT NOT NULL DEFAULT ''"),
+    ("events", "region TEXT NOT NULL DEFAULT ''"),
+    ("events", "status TEXT NOT NULL DEFAULT ''"),
+    ("events", "event_type_code TEXT NOT NULL DEFAULT ''"),
+    ("events", "event_type_category TEXT NOT NULL DEFAULT ''"),
+    ("events", "end_time TEXT"),
+    ("events", "last_updated_time TEXT"),
+    ("events", "updated_at TEXT NOT NULL DEFAULT ''"),
+    ("affected_entities", "account_id TEXT NOT NULL DEFAULT ''"),
+    ("affected_entities", "entity_arn TEXT"),
+    ("affected_entities", "status TEXT"),
+    ("affected_entities", "last_updated_time TEXT"),
+];
+
+pub struct SqliteWriter {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+}
+
+impl SqliteWriter {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let missing = missing_columns(path)?;
+        let mut child = Command::new("sqlite3")
+            .arg("-bail")
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("could not run sqlite3 (is it installed?): {}", err),
+                )
+            })?;
+        let mut stdin = BufWriter::new(child.stdin.take().expect("stdin is piped"));
+        stdin.write_all(SCHEMA.as_bytes())?;
+        for (table, column) in missing {
+            writeln!(stdin, "ALTER TABLE {} ADD COLUMN {};", table, column)?;
+        }
+        stdin.write_all(b"BEGIN;\n")?;
+        Ok(SqliteWriter { child, stdin })
+    }
+
+    pub fn write(&mut self, event: &HealthEvent) -> io::Result<()> {
+        let arn = quote(&event.arn);
+        writeln!(
+            self.stdin,
+            "INSERT INTO events (arn, service, region, status, event_type_code, \
+             event_type_category, start_time, end_time, last_updated_time, detail, updated_at) \
+             VALUES ({}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}) \
+             ON CONFLICT (arn) DO UPDATE SET service = excluded.service, \
+             region = excluded.region, status = excluded.status, \
+             event_type_code = excluded.event_type_code, \
+             event_type_category = excluded.event_type_category, \
+             start_time = excluded.start_time, end_time = excluded.end_time, \
+             last_updated_time = excluded.last_updated_time, detail = excluded.detail, \
+             updated_at = excluded.updated_at;",
+            arn,
+            quote(&event.service),
+            quote(&event.region),
+            quote(&event.status),
+            quote(&event.event_type_code),
+            quote(&event.event_type_category),
+            time(event.start_time),
+            time(event.end_time),
+            time(event.last_updated_time),
+            quote(&event.detail),
+            NOW
+        )?;
+        // Replace the entity list so entities that recovered don't linger
+        writeln!(
+            self.stdin,
+            "DELETE FROM affected_entities WHERE event_arn = {};",
+            arn
+        )?;
+        for entity in &event.affected_entities {
+            writeln!(
+                self.stdin,
+                "INSERT OR IGNORE INTO affected_entities (event_arn, entity_value, account_id, \
+                 entity_arn, status, last_updated_time) VALUES ({}, {}, {}, {}, {}, {});",
+                arn,
+                quote(&entity.value),
+                quote(entity.account_id.as_deref().unwrap_or_default()),
+                optional(entity.arn.as_deref()),
+                optional(entity.status.as_deref()),
+                time(entity.last_updated_time)
+            )?;
+        }
+        writeln!(
+            self.stdin,
+            "DELETE FROM affected_accounts WHERE event_arn = {};",
+            arn
+        )?;
+        for account in &event.affected_accounts {
+            writeln!(
+                self.stdin,
+                "INSERT OR IGNORE INTO affected_accounts (event_arn, account_id) VALUES ({}, {});",
+                arn,
+                quote(account)
+            )?;
+        }
+        for (account, name) in &event.account_names {
+            writeln!(
+                self.stdin,
+                "INSERT INTO accounts (account_id, account_name) VALUES ({}, {}) \
+                 ON CONFLICT (account_id) DO UPDATE SET account_name = excluded.account_name;",
+                quote(account),
+                quote(name)
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.stdin.write_all(b"COMMIT;\n")?;
+        self.stdin.flush()?;
+        drop(self.stdin);
+
+        let status = self.child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("sqlite3 exited with {}", status)));
+        }
+        Ok(())
+    }
+}
+
+/// The columns of `ADDED_COLUMNS` that the database at `path` has tables
+/// without. A new database has no tables, so `SCHEMA` creates them whole.
+fn missing_columns(path: &Path) -> io::Result<Vec<(&'static str, &'static str)>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let output = Command::new("sqlite3")
+        .arg("-readonly")
+        .arg(path)
+        .arg(
+            "SELECT m.name || '.' || p.name FROM sqlite_master m, pragma_table_info(m.name) p \
+             WHERE m.type = 'table';",
+        )
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!("could not run sqlite3 (is it installed?): {}", err),
+            )
+        })?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "sqlite3 exited with {}",
+            output.status
+        )));
+    }
+    let existing = String::from_utf8_lossy(&output.stdout);
+    let existing: Vec<&str> = existing.lines().collect();
+    let tables: Vec<&str> = existing
+        .iter()
+        .filter_map(|column| column.split_once('.').map(|(table, _)| table))
+        .collect();
+    Ok(ADDED_COLUMNS
+        .iter()
+        .filter(|(table, column)| {
+            let name = column.split(' ').next().unwrap_or_default();
+            tables.contains(table) && !existing.contains(&format!("{}.{}", table, name).as_str())
+        })
+        .copied()
+        .collect())
+}
+
+/// The time of the write, in the format of the other times.
+const NOW: &str = "strftime('%Y-%m-%dT%H:%M:%SZ', 'now')";
+
+/// Quotes `value` as an SQL string literal.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Quotes `value`, or gives NULL for None.
+fn optional(value: Option<&str>) -> String {
+    value.map(quote).unwrap_or_else(|| "NULL".to_string())
+}
+
+fn time(value: Option<DateTime<Utc>>) -> String {
+    optional(
+        value
+            .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+            .as_deref(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AffectedEntity;
+    use std::fs;
+
+    fn query(path: &Path, sql: &str) -> String {
+        let output = Command::new("sqlite3").arg(path).arg(sql).output().unwrap();
+        assert!(output.status.success(), "{}", sql);
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn upserts_events_into_old_and_new_databases() {
+        if Command::new("sqlite3").arg("-version").output().is_err() {
+            eprintln!("skipping: sqlite3 isn't installed");
+            return;
+        }
+        let path = std::env::temp_dir().join(format!("aws9man-sqlite-{}.db", std::process::id()));
+        let _ = fs::remove_file(&path);
+        // A database from before the event's other fields were stored
+        query(
+            &path,
+            "CREATE TABLE events (arn TEXT PRIMARY KEY, start_time TEXT, detail TEXT NOT NULL);",
+        );
+
+        let mut event = HealthEvent {
+            arn: "arn:event-1".to_string(),
+            service: "EC2".to_string(),
+            region: "us-east-1".to_string(),
+            status: "open".to_string(),
+            event_type_category: "issue".to_string(),
+            detail: "It's broken".to_string(),
+            affected_entities: vec![AffectedEntity {
+                value: "i-1".to_string(),
+                account_id: None,
+                arn: None,
+                status: Some("IMPAIRED".to_string()),
+                last_updated_time: None,
+                tags: Default::default(),
+            }],
+            ..Default::default()
+        };
+        for status in ["open", "closed"] {
+            event.status = status.to_string();
+            let mut db = SqliteWriter::open(&path).unwrap();
+            db.write(&event).unwrap();
+            db.finish().unwrap();
+        }
+        let events = query(
+            &path,
+            "SELECT arn, service, region, status, event_type_category, detail, updated_at != '' \
+             FROM events;",
+        );
+        let entities = query(&path, "SELECT entity_value, status FROM affected_entities;");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            events,
+            "arn:event-1|EC2|us-east-1|closed|issue|It's broken|1\n"
+        );
+        assert_eq!(entities, "i-1|IMPAIRED\n");
+    }
+}
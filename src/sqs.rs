@@ -0,0 +1,215 @@
+//! Sends one SQS message per event (`--sqs-queue-url`), as the JSON objects
+//! of JSON reports.
+
+use crate::HealthEvent;
+use crate::batch::batches;
+use crate::output::event_to_json;
+use crate::sigv4::SignedClient;
+use aws_smithy_json::deserialize::json_token_iter;
+use aws_smithy_json::deserialize::token::expect_document;
+use aws_smithy_json::serialize::JsonObjectWriter;
+use aws_smithy_types::Document;
+use std::error::Error as StdError;
+
+/// `SendMessageBatch` takes at most 10 messages, of at most 256 KiB in all.
+const MAX_BATCH_MESSAGES: usize = 10;
+const MAX_BATCH_BYTES: usize = 256 * 1024;
+
+/// A queue URL such as `https://sqs.eu-west-1.amazonaws.com/111111111111/health`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueUrl {
+    url: String,
+    region: String,
+}
+
+impl QueueUrl {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let error = || "expected https://sqs.<region>.amazonaws.com/<account>/<queue>".to_string();
+        let host = value
+            .strip_prefix("https://")
+            .or_else(|| value.strip_prefix("http://"))
+            .and_then(|rest| rest.split('/').next())
+            .ok_or_else(error)?;
+        // Legacy queue URLs put the region first, e.g. eu-west-1.queue.amazonaws.com
+        let region = match host.split('.').collect::<Vec<_>>()[..] {
+            ["sqs", region, ..] => region,
+            [region, "queue", ..] => region,
+            _ => return Err(error()),
+        };
+        Ok(QueueUrl {
+            url: value.to_string(),
+            region: region.to_string(),
+        })
+    }
+
+    /// FIFO queues need a group and deduplication ID for every message.
+    fn is_fifo(&self) -> bool {
+        self.url.ends_with(".fifo")
+    }
+}
+
+pub struct SqsSender {
+    client: SignedClient,
+    queue: QueueUrl,
+}
+
+impl SqsSender {
+    pub fn new(client: SignedClient, queue: QueueUrl) -> Self {
+        SqsSender { client, queue }
+    }
+
+    /// Sends one message per event, in as few batches as fit.
+    pub async fn send_events(&self, events: &[HealthEvent]) -> Result<(), Box<dyn StdError>> {
+        let messages = events
+            .iter()
+            .map(|event| (event, event_to_json(event)))
+            .collect();
+        for batch in batches(
+            messages,
+            MAX_BATCH_MESSAGES,
+            MAX_BATCH_BYTES,
+            |(_, json)| json.len(),
+        ) {
+            self.send_batch(&batch).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_batch(&self, batch: &[(&HealthEvent, String)]) -> Result<(), Box<dyn StdError>> {
+        let body = request_body(&self.queue, batch);
+        let endpoint = self.client.endpoint("sqs", &self.queue.region);
+        let response = self
+            .client
+            .json(
+                &endpoint,
+                "sqs",
+                &self.queue.region,
+                "1.0",
+                "AmazonSQS.SendMessageBatch",
+                body,
+            )
+            .await?;
+        match first_failure(&response)? {
+            Some(failure) => Err(format!("SendMessageBatch failed: {}", failure).into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A `SendMessageBatch` request sending `batch`'s messages to `queue`.
+fn request_body(queue: &QueueUrl, batch: &[(&HealthEvent, String)]) -> String {
+    let mut body = String::new();
+    let mut request = JsonObjectWriter::new(&mut body);
+    request.key("QueueUrl").string(&queue.url);
+    let mut entries = request.key("Entries").start_array();
+    for (index, (event, json)) in batch.iter().enumerate() {
+        let mut entry = entries.value().start_object();
+        entry.key("Id").string(&index.to_string());
+        entry.key("MessageBody").string(json);
+        if queue.is_fifo() {
+            // Keeps each event's updates in order, and drops a resend of
+            // the same update
+            entry.key("MessageGroupId").string(fit_id(&event.arn));
+            entry
+                .key("MessageDeduplicationId")
+                .string(&deduplication_id(event));
+        }
+        entry.finish();
+    }
+    entries.finish();
+    request.finish();
+    body
+}
+
+/// Identifies an update of an event: its ARN and last update time.
+pub(crate) fn deduplication_id(event: &HealthEvent) -> String {
+    let updated = event
+        .last_updated_time
+        .map(|time| time.timestamp_millis())
+        .unwrap_or_default();
+    fit_id(&format!("{}@{}", event.arn, updated)).to_string()
+}
+
+/// The end of `id` that fits the 128 characters SQS and SNS allow in IDs. The end
+/// of an event ARN is its distinctive part.
+pub(crate) fn fit_id(id: &str) -> &str {
+    &id[id.len().saturating_sub(128)..]
+}
+
+/// Describes the first entry of a batch that SQS didn't accept, if any.
+fn first_failure(response: &[u8]) -> Result<Option<String>, Box<dyn StdError>> {
+    let document = expect_document(&mut json_token_iter(response).peekable())?;
+    let Document::Object(response) = document else {
+        return Err("SendMessageBatch returned an unexpected response".into());
+    };
+    let Some(Document::Array(failed)) = response.get("Failed") else {
+        return Ok(None);
+    };
+    Ok(failed.first().map(|failure| {
+        let field = |key: &str| match failure {
+            Document::Object(failure) => match failure.get(key) {
+                Some(Document::String(value)) => value.clone(),
+                _ => String::new(),
+            },
+            _ => String::new(),
+        };
+        format!("{}: {}", field("Code"), field("Message"))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn reads_the_region_from_queue_urls() {
+        let queue =
+            QueueUrl::parse("https://sqs.eu-west-1.amazonaws.com/111111111111/health").unwrap();
+        assert_eq!(queue.region, "eu-west-1");
+        assert!(!queue.is_fifo());
+        let legacy =
+            QueueUrl::parse("https://ap-south-1.queue.amazonaws.com/111111111111/h.fifo").unwrap();
+        assert_eq!(legacy.region, "ap-south-1");
+        assert!(legacy.is_fifo());
+        assert!(QueueUrl::parse("health").is_err());
+    }
+
+    #[test]
+    fn reports_the_first_failed_entry() {
+        assert_eq!(
+            first_failure(br#"{"Successful":[],"Failed":[{"Id":"0","SenderFault":true,"Code":"InvalidParameterValue","Message":"too long"}]}"#)
+                .unwrap(),
+            Some("InvalidParameterValue: too long".to_string())
+        );
+        assert_eq!(
+            first_failure(br#"{"Successful":[{"Id":"0","MessageId":"m-1"}],"Failed":[]}"#).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn sends_fifo_messages_with_an_id_per_update() {
+        let queue =
+            QueueUrl::parse("https://ap-south-1.queue.amazonaws.com/111111111111/h.fifo").unwrap();
+        let event = HealthEvent {
+            arn: "arn:event-1".to_string(),
+            service: "EC2".to_string(),
+            status: "open".to_string(),
+            event_type_category: "issue".to_string(),
+            last_updated_time: Utc.timestamp_millis_opt(1709715600000).single(),
+            ..Default::default()
+        };
+        let body = request_body(&queue, &[(&event, event_to_json(&event))]);
+        for field in [
+            r#"\"service\":\"EC2\""#,
+            r#"\"status\":\"open\""#,
+            r#"\"event_type_category\":\"issue\""#,
+            r#"\"last_updated_time\":\"2024-03-06T09:00:00Z\""#,
+            r#""MessageGroupId":"arn:event-1""#,
+            r#""MessageDeduplicationId":"arn:event-1@1709715600000""#,
+        ] {
+            assert!(body.contains(field), "{} missing from {}", field, body);
+        }
+    }
+}
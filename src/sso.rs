@@ -0,0 +1,41 @@
+//! Friendlier failures for IAM Identity Center (SSO) profiles.
+
+use aws_config::SdkConfig;
+use aws_credential_types::provider::ProvideCredentials;
+use aws_smithy_types::error::display::DisplayErrorContext;
+use aws_types::os_shim_internal::{Env, Fs};
+use std::borrow::Cow;
+use std::error::Error as StdError;
+
+/// If `profile` (or the default profile) signs in through SSO, resolves
+/// `config`'s credentials up front so a missing or expired SSO session fails
+/// with the `aws sso login` command that fixes it, rather than with a generic
+/// credentials error on the first API call.
+pub async fn check_session(
+    config: &SdkConfig,
+    profile: Option<&str>,
+) -> Result<(), Box<dyn StdError>> {
+    let selected = profile.map(|profile| Cow::Owned(profile.to_string()));
+    let Ok(profiles) =
+        aws_config::profile::load(&Fs::real(), &Env::real(), &Default::default(), selected).await
+    else {
+        return Ok(());
+    };
+    if profiles.get("sso_session").is_none() && profiles.get("sso_start_url").is_none() {
+        return Ok(());
+    }
+    let Some(provider) = config.credentials_provider() else {
+        return Ok(());
+    };
+
+    match provider.provide_credentials().await {
+        Ok(_) => Ok(()),
+        Err(err) => Err(format!(
+            "the SSO session for profile {} is missing or has expired; run `aws sso login --profile {}` and try again ({})",
+            profiles.selected_profile(),
+            profiles.selected_profile(),
+            DisplayErrorContext(err)
+        )
+        .into()),
+    }
+}
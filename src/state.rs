@@ -0,0 +1,55 @@
+//! The state file `--incremental` keeps between runs: the latest
+//! `lastUpdatedTime` of any event reported so far.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const DEFAULT_PATH: &str = "aws9man.state";
+
+/// Reads the high-water mark, or `None` before the first run.
+pub fn read_high_water_mark(path: &Path) -> io::Result<Option<DateTime<Utc>>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    DateTime::parse_from_rfc3339(contents.trim())
+        .map(|time| Some(time.with_timezone(&Utc)))
+        .map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: not an RFC 3339 timestamp: {}", path.display(), err),
+            )
+        })
+}
+
+/// Replaces the high-water mark. The new state is written next to the old
+/// one and renamed over it, so an interrupted write can't corrupt it.
+pub fn write_high_water_mark(path: &Path, time: DateTime<Utc>) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    fs::write(
+        &temp,
+        format!("{}\n", time.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+    )?;
+    fs::rename(&temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn round_trips_the_high_water_mark() {
+        let path = std::env::temp_dir().join(format!("aws9man-state-{}", std::process::id()));
+        assert_eq!(read_high_water_mark(&path).unwrap(), None);
+
+        let time = Utc.timestamp_millis_opt(1_714_573_800_123).unwrap();
+        write_high_water_mark(&path, time).unwrap();
+        assert_eq!(read_high_water_mark(&path).unwrap(), Some(time));
+        fs::remove_file(&path).unwrap();
+    }
+}
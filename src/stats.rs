@@ -0,0 +1,156 @@
+//! Per-operation API call statistics, collected by an SDK interceptor and
+//! printed at the end of a run.
+
+use aws_sdk_health::config::interceptors::FinalizerInterceptorContextRef;
+use aws_sdk_health::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_sdk_health::error::{BoxError, ProvideErrorMetadata};
+use aws_sdk_health::operation::describe_affected_accounts_for_organization::DescribeAffectedAccountsForOrganizationError;
+use aws_sdk_health::operation::describe_affected_entities::DescribeAffectedEntitiesError;
+use aws_sdk_health::operation::describe_affected_entities_for_organization::DescribeAffectedEntitiesForOrganizationError;
+use aws_sdk_health::operation::describe_event_details::DescribeEventDetailsError;
+use aws_sdk_health::operation::describe_event_details_for_organization::DescribeEventDetailsForOrganizationError;
+use aws_sdk_health::operation::describe_events::DescribeEventsError;
+use aws_sdk_health::operation::describe_events_for_organization::DescribeEventsForOrganizationError;
+use aws_smithy_runtime_api::client::interceptors::context::{
+    BeforeSerializationInterceptorContextRef, BeforeTransmitInterceptorContextRef, Error,
+};
+use aws_smithy_runtime_api::client::orchestrator::{Metadata, OrchestratorError};
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Error codes AWS services use for throttling
+const THROTTLING_CODES: &[&str] = &[
+    "Throttling",
+    "ThrottlingException",
+    "ThrottledException",
+    "RequestThrottledException",
+    "TooManyRequestsException",
+    "RequestLimitExceeded",
+];
+
+#[derive(Debug, Default, Clone, Copy)]
+struct OperationStats {
+    calls: u64,
+    attempts: u64,
+    throttles: u64,
+}
+
+/// Counts calls, attempts and throttled responses for every operation a
+/// client sends. Clones share the same counters.
+#[derive(Debug, Clone)]
+pub struct ApiStats {
+    started: Instant,
+    operations: Arc<Mutex<BTreeMap<String, OperationStats>>>,
+}
+
+impl ApiStats {
+    pub fn new() -> Self {
+        ApiStats {
+            started: Instant::now(),
+            operations: Arc::default(),
+        }
+    }
+
+    fn update(&self, cfg: &ConfigBag, f: impl FnOnce(&mut OperationStats)) {
+        let name = cfg
+            .load::<Metadata>()
+            .map_or("Unknown", |metadata| metadata.name());
+        let mut operations = self.operations.lock().unwrap();
+        f(operations.entry(name.to_string()).or_default());
+    }
+
+    /// Prints a per-operation summary and the total wall-clock time.
+    pub fn print_summary(&self) {
+        let operations = self.operations.lock().unwrap();
+        let mut total = OperationStats::default();
+        eprintln!("API calls:");
+        for (name, stats) in operations.iter() {
+            eprintln!("  {:<26} {}", name, describe(stats));
+            total.calls += stats.calls;
+            total.attempts += stats.attempts;
+            total.throttles += stats.throttles;
+        }
+        eprintln!("  {:<26} {}", "Total", describe(&total));
+        eprintln!("Finished in {:.1}s", self.started.elapsed().as_secs_f64());
+    }
+}
+
+fn describe(stats: &OperationStats) -> String {
+    format!(
+        "{} calls, {} retries, {} throttled",
+        stats.calls,
+        stats.attempts.saturating_sub(stats.calls),
+        stats.throttles
+    )
+}
+
+impl Intercept for ApiStats {
+    fn name(&self) -> &'static str {
+        "ApiStats"
+    }
+
+    // Operation metadata isn't in the config bag yet when `read_before_execution`
+    // runs, so calls are counted here: this also runs once per execution
+    fn read_before_serialization(
+        &self,
+        _context: &BeforeSerializationInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        self.update(cfg, |stats| stats.calls += 1);
+        Ok(())
+    }
+
+    fn read_before_attempt(
+        &self,
+        _context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        self.update(cfg, |stats| stats.attempts += 1);
+        Ok(())
+    }
+
+    fn read_after_attempt(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let too_many_requests = context
+            .response()
+            .is_some_and(|response| response.status().as_u16() == 429);
+        let throttled = match context.output_or_error() {
+            Some(Err(error)) => {
+                error_code(error).is_some_and(|code| THROTTLING_CODES.contains(&code))
+            }
+            _ => false,
+        };
+        if too_many_requests || throttled {
+            self.update(cfg, |stats| stats.throttles += 1);
+        }
+        Ok(())
+    }
+}
+
+/// The service error code, if `error` is a modeled or unhandled error from
+/// one of the Health operations.
+pub fn error_code(error: &OrchestratorError<Error>) -> Option<&str> {
+    let error = error.as_operation_error()?;
+    code::<DescribeEventsError>(error)
+        .or_else(|| code::<DescribeEventDetailsError>(error))
+        .or_else(|| code::<DescribeAffectedEntitiesError>(error))
+        .or_else(|| code::<DescribeEventsForOrganizationError>(error))
+        .or_else(|| code::<DescribeEventDetailsForOrganizationError>(error))
+        .or_else(|| code::<DescribeAffectedEntitiesForOrganizationError>(error))
+        .or_else(|| code::<DescribeAffectedAccountsForOrganizationError>(error))
+}
+
+fn code<E>(error: &Error) -> Option<&str>
+where
+    E: ProvideErrorMetadata + StdError + Send + Sync + 'static,
+{
+    error.downcast_ref::<E>()?.code()
+}
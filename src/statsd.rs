@@ -0,0 +1,128 @@
+//! Sends event counts to a StatsD server over UDP (`--statsd-address`): a
+//! gauge per service and status, e.g. `aws_health.events.ec2.open:2|g`, and
+//! a counter of new events per service.
+
+use crate::HealthEvent;
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use tokio::net::{UdpSocket, lookup_host};
+
+/// Metrics are packed into datagrams of at most this many bytes, which fits
+/// the usual MTU once headers are added.
+const MAX_DATAGRAM_BYTES: usize = 1432;
+
+pub struct StatsdSender {
+    address: String,
+    prefix: String,
+}
+
+impl StatsdSender {
+    pub fn new(address: String, prefix: String) -> Self {
+        StatsdSender { address, prefix }
+    }
+
+    /// Sends the counts for `events`, returning how many metrics were sent.
+    pub async fn send(&self, events: &[HealthEvent]) -> Result<usize, Box<dyn StdError>> {
+        let target = lookup_host(&self.address)
+            .await
+            .map_err(|err| format!("{}: {}", self.address, err))?
+            .next()
+            .ok_or_else(|| format!("{}: no address found", self.address))?;
+        let local = if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(local).await?;
+        let lines = self.lines(events);
+        for datagram in datagrams(&lines) {
+            socket
+                .send_to(datagram.as_bytes(), target)
+                .await
+                .map_err(|err| format!("{}: {}", self.address, err))?;
+        }
+        Ok(lines.len())
+    }
+
+    fn lines(&self, events: &[HealthEvent]) -> Vec<String> {
+        let mut by_status = BTreeMap::new();
+        let mut by_service = BTreeMap::new();
+        for event in events {
+            let service = name(&event.service);
+            *by_status
+                .entry((service.clone(), name(&event.status)))
+                .or_insert(0) += 1;
+            *by_service.entry(service).or_insert(0) += 1;
+        }
+        let gauges = by_status.iter().map(|((service, status), count)| {
+            format!("{}.events.{}.{}:{}|g", self.prefix, service, status, count)
+        });
+        let counters = by_service
+            .iter()
+            .map(|(service, count)| format!("{}.new_events.{}:{}|c", self.prefix, service, count));
+        gauges.chain(counters).collect()
+    }
+}
+
+/// A metric name segment: lowercase, with anything but letters, digits and
+/// `_` replaced, since `.`, `:` and `|` mean something to StatsD.
+fn name(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '_' => c,
+            'A'..='Z' => c.to_ascii_lowercase(),
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Joins `lines` with newlines into as few datagrams as fit.
+fn datagrams(lines: &[String]) -> Vec<String> {
+    let mut datagrams = Vec::new();
+    let mut current = String::new();
+    for line in lines {
+        if !current.is_empty() && current.len() + 1 + line.len() > MAX_DATAGRAM_BYTES {
+            datagrams.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        datagrams.push(current);
+    }
+    datagrams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_and_packs_metrics() {
+        let event = |service: &str, status: &str| HealthEvent {
+            service: service.to_string(),
+            status: status.to_string(),
+            ..Default::default()
+        };
+        let sender = StatsdSender::new("localhost:8125".to_string(), "aws_health".to_string());
+        let lines = sender.lines(&[
+            event("EC2", "open"),
+            event("EC2", "open"),
+            event("ELASTICLOADBALANCING", "closed"),
+        ]);
+        assert_eq!(
+            lines,
+            [
+                "aws_health.events.ec2.open:2|g",
+                "aws_health.events.elasticloadbalancing.closed:1|g",
+                "aws_health.new_events.ec2:2|c",
+                "aws_health.new_events.elasticloadbalancing:1|c",
+            ]
+        );
+        assert_eq!(datagrams(&lines).len(), 1);
+        assert_eq!(datagrams(&vec!["x".repeat(1000); 3]).len(), 3);
+    }
+}
@@ -0,0 +1,197 @@
+//! Posts an Adaptive Card per event to a Microsoft Teams incoming webhook or
+//! Workflows webhook (`--teams-webhook`).
+
+use crate::HealthEvent;
+use crate::http_client::HttpClient;
+use crate::notify::{self, console_url, excerpt, title};
+use aws_smithy_json::serialize::{JsonArrayWriter, JsonObjectWriter};
+use std::error::Error as StdError;
+
+/// Descriptions are cut to this many characters; the link has the rest.
+const MAX_DESCRIPTION_CHARS: usize = 600;
+
+/// Fact values are cut to this many characters, as Discord's fields are:
+/// Teams rejects cards over 28 KB, which long account or entity lists reach.
+const MAX_FACT_CHARS: usize = 1024;
+
+pub struct TeamsNotifier {
+    http: HttpClient,
+    webhook: String,
+}
+
+impl TeamsNotifier {
+    pub fn new(http: HttpClient, webhook: String) -> Self {
+        TeamsNotifier { http, webhook }
+    }
+
+    /// Posts one card per event, in order.
+    pub async fn notify(&self, events: &[HealthEvent]) -> Result<(), Box<dyn StdError>> {
+        for event in events {
+            notify::post(
+                &self.http,
+                "Teams",
+                &self.webhook,
+                &[("content-type", "application/json")],
+                message(event),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// The event as a message with one Adaptive Card attached.
+fn message(event: &HealthEvent) -> String {
+    let mut message = String::new();
+    let mut object = JsonObjectWriter::new(&mut message);
+    object.key("type").string("message");
+    let mut attachments = object.key("attachments").start_array();
+    let mut attachment = attachments.value().start_object();
+    attachment
+        .key("contentType")
+        .string("application/vnd.microsoft.card.adaptive");
+    let mut card = attachment.key("content").start_object();
+    card.key("$schema")
+        .string("http://adaptivecards.io/schemas/adaptive-card.json");
+    card.key("type").string("AdaptiveCard");
+    card.key("version").string("1.4");
+
+    let mut body = card.key("body").start_array();
+    let mut heading = body.value().start_object();
+    heading.key("type").string("TextBlock");
+    heading.key("size").string("Medium");
+    heading.key("weight").string("Bolder");
+    heading.key("text").string(&title(event));
+    heading.key("wrap").boolean(true);
+    heading.finish();
+
+    let mut fact_set = body.value().start_object();
+    fact_set.key("type").string("FactSet");
+    let mut facts = fact_set.key("facts").start_array();
+    fact(&mut facts, "Service", &event.service);
+    fact(&mut facts, "Region", &event.region);
+    fact(&mut facts, "Status", &event.status);
+    fact(&mut facts, "Category", &event.event_type_category);
+    fact(&mut facts, "Start", &event.timestamp);
+    if !event.affected_accounts.is_empty() {
+        fact(&mut facts, "Accounts", &event.accounts_text());
+    }
+    if !event.affected_entities.is_empty() {
+        fact(&mut facts, "Entities", &event.entities_text());
+    }
+    facts.finish();
+    fact_set.finish();
+
+    let mut description = body.value().start_object();
+    description.key("type").string("TextBlock");
+    description
+        .key("text")
+        .string(&excerpt(&event.detail, MAX_DESCRIPTION_CHARS));
+    description.key("wrap").boolean(true);
+    description.finish();
+    body.finish();
+
+    let mut actions = card.key("actions").start_array();
+    let mut open = actions.value().start_object();
+    open.key("type").string("Action.OpenUrl");
+    open.key("title").string("View in the AWS Health Dashboard");
+    open.key("url").string(&console_url(event));
+    open.finish();
+    actions.finish();
+
+    let mut msteams = card.key("msteams").start_object();
+    msteams.key("width").string("Full");
+    msteams.finish();
+    card.finish();
+    attachment.finish();
+    attachments.finish();
+    object.finish();
+    message
+}
+
+fn fact(facts: &mut JsonArrayWriter<'_>, title: &str, value: &str) {
+    let mut object = facts.value().start_object();
+    object.key("title").string(title);
+    let value = if value.is_empty() { "-" } else { value };
+    object.key("value").string(&excerpt(value, MAX_FACT_CHARS));
+    object.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AffectedEntity;
+    use aws_smithy_json::deserialize::json_token_iter;
+    use aws_smithy_json::deserialize::token::expect_document;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn builds_adaptive_cards() {
+        let event = HealthEvent {
+            timestamp: "2024-03-06 09:00:00".to_string(),
+            arn: "arn:aws:health:us-east-1::event/EC2/AWS_EC2_OPERATIONAL_ISSUE/1".to_string(),
+            service: "EC2".to_string(),
+            region: "us-east-1".to_string(),
+            status: "open".to_string(),
+            event_type_code: "AWS_EC2_OPERATIONAL_ISSUE".to_string(),
+            event_type_category: "issue".to_string(),
+            detail: "Elevated errors".to_string(),
+            affected_entities: (0..100)
+                .map(|i| AffectedEntity {
+                    value: format!("i-{:017x}", i),
+                    account_id: None,
+                    arn: None,
+                    status: None,
+                    last_updated_time: None,
+                    tags: BTreeMap::new(),
+                })
+                .collect(),
+            affected_accounts: vec!["111111111111".to_string()],
+            ..Default::default()
+        };
+        let message = message(&event);
+        let document =
+            expect_document(&mut json_token_iter(message.as_bytes()).peekable()).unwrap();
+        let attachment = &document.as_object().unwrap()["attachments"]
+            .as_array()
+            .unwrap()[0];
+        let card = attachment.as_object().unwrap()["content"]
+            .as_object()
+            .unwrap();
+        assert_eq!(card["type"].as_string(), Some("AdaptiveCard"));
+        let body = card["body"].as_array().unwrap();
+        assert_eq!(
+            body[0].as_object().unwrap()["text"].as_string(),
+            Some("EC2 operational issue (us-east-1)")
+        );
+        let facts: Vec<(&str, &str)> = body[1].as_object().unwrap()["facts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|fact| {
+                let fact = fact.as_object().unwrap();
+                (
+                    fact["title"].as_string().unwrap(),
+                    fact["value"].as_string().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            facts[..6],
+            [
+                ("Service", "EC2"),
+                ("Region", "us-east-1"),
+                ("Status", "open"),
+                ("Category", "issue"),
+                ("Start", "2024-03-06 09:00:00"),
+                ("Accounts", "111111111111"),
+            ]
+        );
+        // 100 instance IDs are 2,198 characters
+        let (title, entities) = facts[6];
+        assert_eq!(title, "Entities");
+        assert!(entities.starts_with("i-00000000000000000, i-00000000000000001"));
+        assert!(entities.ends_with('…'));
+        assert!(entities.chars().count() <= MAX_FACT_CHARS);
+    }
+}
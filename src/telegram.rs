@@ -0,0 +1,110 @@
+//! Sends a Markdown message per new open event to a Telegram chat through a
+//! bot (`--telegram-bot-token`, `--telegram-chat-id`).
+
+use crate::HealthEvent;
+use crate::http_client::HttpClient;
+use crate::notify::{self, console_url, excerpt, title};
+use aws_smithy_json::serialize::JsonObjectWriter;
+use std::error::Error as StdError;
+
+/// Descriptions are cut to this many characters; the link has the rest.
+const MAX_DESCRIPTION_CHARS: usize = 600;
+
+pub struct TelegramNotifier {
+    http: HttpClient,
+    /// The bot's `sendMessage` method, whose URL holds the token
+    send_message_url: String,
+    chat_id: String,
+}
+
+impl TelegramNotifier {
+    pub fn new(http: HttpClient, api_url: &str, bot_token: &str, chat_id: String) -> Self {
+        TelegramNotifier {
+            http,
+            send_message_url: format!(
+                "{}/bot{}/sendMessage",
+                api_url.trim_end_matches('/'),
+                bot_token
+            ),
+            chat_id,
+        }
+    }
+
+    /// Sends one message per open event, in order, returning how many.
+    pub async fn notify(&self, events: &[HealthEvent]) -> Result<usize, Box<dyn StdError>> {
+        let mut sent = 0;
+        for event in events.iter().filter(|event| event.status == "open") {
+            notify::post(
+                &self.http,
+                "Telegram",
+                &self.send_message_url,
+                &[("content-type", "application/json")],
+                self.body(event),
+            )
+            .await?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    fn body(&self, event: &HealthEvent) -> String {
+        let mut body = String::new();
+        let mut object = JsonObjectWriter::new(&mut body);
+        object.key("chat_id").string(&self.chat_id);
+        object.key("text").string(&message(event));
+        object.key("parse_mode").string("MarkdownV2");
+        let mut preview = object.key("link_preview_options").start_object();
+        preview.key("is_disabled").boolean(true);
+        preview.finish();
+        object.finish();
+        body
+    }
+}
+
+/// The event as MarkdownV2 text.
+fn message(event: &HealthEvent) -> String {
+    let mut facts = vec![
+        ("Service", event.service.clone()),
+        ("Region", event.region.clone()),
+        ("Category", event.event_type_category.clone()),
+        ("Start", event.timestamp.clone()),
+    ];
+    if !event.affected_accounts.is_empty() {
+        facts.push(("Accounts", event.accounts_text()));
+    }
+    let mut text = format!("*{}*\n", escape(&title(event)));
+    for (label, value) in facts {
+        text.push_str(&format!("*{}:* {}\n", label, escape(&value)));
+    }
+    text.push_str(&format!(
+        "\n{}\n\n[View in the AWS Health Dashboard]({})",
+        escape(&excerpt(&event.detail, MAX_DESCRIPTION_CHARS)),
+        console_url(event).replace('\\', "\\\\").replace(')', "\\)")
+    ));
+    text
+}
+
+/// Escapes the characters MarkdownV2 reserves, so `text` shows as it is.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\_*[]()~`>#+-=|{}.!".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markdown() {
+        assert_eq!(
+            escape("EC2 issue (us-east-1). See *docs*!"),
+            "EC2 issue \\(us\\-east\\-1\\)\\. See \\*docs\\*\\!"
+        );
+    }
+}
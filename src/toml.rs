@@ -0,0 +1,328 @@
+//! Just enough TOML for aws9man's config files: tables, arrays of tables,
+//! dotted keys, strings, integers, booleans and arrays. Inline tables,
+//! floats and dates aren't supported.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub type Table = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            Value::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Integer(_) => "integer",
+            Value::Boolean(_) => "boolean",
+            Value::Array(_) => "array",
+            Value::Table(_) => "table",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    line: usize,
+    message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a TOML document into its root table.
+pub fn parse(input: &str) -> Result<Table, ParseError> {
+    let mut root = Table::new();
+    // Path of the table that key/value lines currently go into
+    let mut current: Vec<String> = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let error = |message: String| ParseError {
+            line: index + 1,
+            message,
+        };
+        let mut cursor = Cursor::new(line);
+        cursor.skip_whitespace();
+        if cursor.at_end_of_line() {
+            continue;
+        }
+
+        if cursor.eat("[[") {
+            let path = cursor.key_path().map_err(error)?;
+            if !cursor.eat("]]") {
+                return Err(error("expected `]]`".to_string()));
+            }
+            let (last, parents) = path.split_last().expect("key paths are non-empty");
+            let parent = table_at(&mut root, parents).map_err(error)?;
+            match parent
+                .entry(last.clone())
+                .or_insert_with(|| Value::Array(Vec::new()))
+            {
+                Value::Array(tables) => tables.push(Value::Table(Table::new())),
+                other => {
+                    return Err(error(format!(
+                        "`{}` is already a {}",
+                        last,
+                        other.type_name()
+                    )));
+                }
+            }
+            current = path;
+        } else if cursor.eat("[") {
+            let path = cursor.key_path().map_err(error)?;
+            if !cursor.eat("]") {
+                return Err(error("expected `]`".to_string()));
+            }
+            table_at(&mut root, &path).map_err(error)?;
+            current = path;
+        } else {
+            let path = cursor.key_path().map_err(error)?;
+            if !cursor.eat("=") {
+                return Err(error("expected `=` after key".to_string()));
+            }
+            cursor.skip_whitespace();
+            let value = cursor.value().map_err(error)?;
+            let (last, parents) = path.split_last().expect("key paths are non-empty");
+            let mut full_path = current.clone();
+            full_path.extend_from_slice(parents);
+            let table = table_at(&mut root, &full_path).map_err(error)?;
+            if table.insert(last.clone(), value).is_some() {
+                return Err(error(format!("duplicate key `{}`", last)));
+            }
+        }
+
+        cursor.skip_whitespace();
+        if !cursor.at_end_of_line() {
+            return Err(error("unexpected characters after value".to_string()));
+        }
+    }
+
+    Ok(root)
+}
+
+/// Finds (creating as needed) the table at `path`. For arrays of tables the
+/// last element is used, as TOML specifies.
+fn table_at<'a>(root: &'a mut Table, path: &[String]) -> Result<&'a mut Table, String> {
+    let mut table = root;
+    for key in path {
+        let value = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Table(Table::new()));
+        table = match value {
+            Value::Table(table) => table,
+            Value::Array(values) => match values.last_mut() {
+                Some(Value::Table(table)) => table,
+                _ => return Err(format!("`{}` is not an array of tables", key)),
+            },
+            other => return Err(format!("`{}` is already a {}", key, other.type_name())),
+        };
+    }
+    Ok(table)
+}
+
+struct Cursor<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(line: &'a str) -> Self {
+        Cursor { rest: line }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start_matches([' ', '\t']);
+    }
+
+    fn at_end_of_line(&self) -> bool {
+        self.rest.is_empty() || self.rest.starts_with('#')
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        match self.rest.strip_prefix(token) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Parses a (possibly dotted) key.
+    fn key_path(&mut self) -> Result<Vec<String>, String> {
+        let mut path = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let key = if self.rest.starts_with('"') || self.rest.starts_with('\'') {
+                self.string()?
+            } else {
+                let end = self
+                    .rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                    .unwrap_or(self.rest.len());
+                if end == 0 {
+                    return Err("expected a key".to_string());
+                }
+                let (key, rest) = self.rest.split_at(end);
+                self.rest = rest;
+                key.to_string()
+            };
+            path.push(key);
+            if !self.eat(".") {
+                return Ok(path);
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        if self.rest.starts_with('"') || self.rest.starts_with('\'') {
+            return self.string().map(Value::String);
+        }
+        if self.eat("[") {
+            let mut values = Vec::new();
+            loop {
+                self.skip_whitespace();
+                if self.eat("]") {
+                    return Ok(Value::Array(values));
+                }
+                values.push(self.value()?);
+                self.skip_whitespace();
+                if !self.eat(",") {
+                    if self.eat("]") {
+                        return Ok(Value::Array(values));
+                    }
+                    return Err("expected `,` or `]` in array".to_string());
+                }
+            }
+        }
+        for (word, value) in [("true", true), ("false", false)] {
+            if self.eat(word) {
+                return Ok(Value::Boolean(value));
+            }
+        }
+
+        let end = self
+            .rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '-' || c == '+' || c == '_'))
+            .unwrap_or(self.rest.len());
+        let (number, rest) = self.rest.split_at(end);
+        match number.replace('_', "").parse() {
+            Ok(number) if end > 0 => {
+                self.rest = rest;
+                Ok(Value::Integer(number))
+            }
+            _ => Err("expected a string, integer, boolean or array".to_string()),
+        }
+    }
+
+    /// Parses a basic (`"..."`) or literal (`'...'`) string.
+    fn string(&mut self) -> Result<String, String> {
+        let mut chars = self.rest.char_indices();
+        let quote = chars.next().map(|(_, c)| c);
+        let mut value = String::new();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                c if Some(c) == quote => {
+                    self.rest = &self.rest[i + 1..];
+                    return Ok(value);
+                }
+                '\\' if quote == Some('"') => match chars.next().map(|(_, c)| c) {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('r') => value.push('\r'),
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some(other) => return Err(format!("unsupported escape `\\{}`", other)),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        Err("unterminated string".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_arrays_of_tables_and_dotted_keys() {
+        let table = parse(
+            r#"
+# Accounts to report on
+[[accounts]]
+id = "123456789012"
+name = 'prod' # trailing comment
+
+[[accounts]]
+id = "210987654321"
+regions = ["us-east-1", "eu-west-1",]
+
+[defaults]
+retry.max_attempts = 3
+verbose = true
+"#,
+        )
+        .unwrap();
+
+        let accounts = table["accounts"].as_array().unwrap();
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(
+            accounts[0].as_table().unwrap()["name"].as_str(),
+            Some("prod")
+        );
+        assert_eq!(
+            accounts[1].as_table().unwrap()["regions"],
+            Value::Array(vec![
+                Value::String("us-east-1".to_string()),
+                Value::String("eu-west-1".to_string()),
+            ])
+        );
+        let defaults = table["defaults"].as_table().unwrap();
+        assert_eq!(
+            defaults["retry"].as_table().unwrap()["max_attempts"],
+            Value::Integer(3)
+        );
+        assert_eq!(defaults["verbose"], Value::Boolean(true));
+    }
+
+    #[test]
+    fn reports_the_line_of_an_error() {
+        let err = parse("a = 1\nb = \"unterminated\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: unterminated string");
+    }
+}
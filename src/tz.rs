@@ -0,0 +1,380 @@
+//! How timestamps are displayed: the time zone (`--tz`), read from the
+//! system's zoneinfo database (`$TZDIR`, or `/usr/share/zoneinfo`), and the
+//! format (`--time-format`).
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, SecondsFormat, Utc, Weekday};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const DEFAULT_TZDIR: &str = "/usr/share/zoneinfo";
+
+/// A named time zone, e.g. `Asia/Ho_Chi_Minh`.
+#[derive(Debug, Clone)]
+pub struct Zone {
+    /// UTC offsets (in seconds) in effect from each transition onwards
+    transitions: Vec<(i64, i32)>,
+    /// Offset before the first transition
+    initial_offset: i32,
+    /// Rule for times after the last transition
+    rule: Option<Rule>,
+}
+
+impl Zone {
+    /// Loads `name` from the zoneinfo database. `UTC` is always available.
+    pub fn load(name: &str) -> Result<Self, String> {
+        if name.eq_ignore_ascii_case("utc") || name == "Z" {
+            return Ok(Zone::fixed(0));
+        }
+        if name.is_empty() || name.starts_with('/') || name.split('/').any(|part| part == "..") {
+            return Err(format!("invalid time zone name {:?}", name));
+        }
+        let dir = env::var_os("TZDIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_TZDIR));
+        let data = fs::read(dir.join(name))
+            .map_err(|err| format!("unknown time zone {:?}: {}", name, err))?;
+        Zone::parse(&data).ok_or_else(|| format!("{}: not a valid zoneinfo file", name))
+    }
+
+    pub(crate) fn fixed(offset: i32) -> Self {
+        Zone {
+            transitions: Vec::new(),
+            initial_offset: offset,
+            rule: None,
+        }
+    }
+
+    /// Parses a TZif file, using its 64-bit data where present.
+    fn parse(data: &[u8]) -> Option<Self> {
+        let header = Header::parse(data)?;
+        let (header, body, time_size) = if header.version >= b'2' {
+            let v2 = &data[header.end(4)..];
+            (Header::parse(v2)?, v2, 8)
+        } else {
+            (header, data, 4)
+        };
+
+        let mut offset = 44;
+        let times = body.get(offset..offset + header.time_count * time_size)?;
+        offset += times.len();
+        let indexes = body.get(offset..offset + header.time_count)?;
+        offset += indexes.len();
+        let types = body.get(offset..offset + header.type_count * 6)?;
+        let utc_offset = |index: usize| {
+            let record = types.get(index * 6..index * 6 + 4)?;
+            Some(i32::from_be_bytes(record.try_into().ok()?))
+        };
+
+        let mut transitions = Vec::with_capacity(header.time_count);
+        for (time, &index) in times.chunks(time_size).zip(indexes) {
+            let time = match time_size {
+                8 => i64::from_be_bytes(time.try_into().ok()?),
+                _ => i32::from_be_bytes(time.try_into().ok()?) as i64,
+            };
+            transitions.push((time, utc_offset(index as usize)?));
+        }
+        // The footer holds a POSIX TZ string covering times after the last
+        // transition
+        let footer = match time_size {
+            8 => std::str::from_utf8(&body[header.end(8)..]).ok(),
+            _ => None,
+        };
+        Some(Zone {
+            transitions,
+            initial_offset: utc_offset(0)?,
+            rule: footer.and_then(|footer| Rule::parse(footer.trim())),
+        })
+    }
+
+    /// The UTC offset in effect at `time`.
+    pub fn offset_at(&self, time: DateTime<Utc>) -> FixedOffset {
+        let seconds = time.timestamp();
+        let after_transitions = self
+            .transitions
+            .last()
+            .is_none_or(|&(last, _)| seconds >= last);
+        let offset = match &self.rule {
+            Some(rule) if after_transitions => rule.offset_at(time),
+            _ => match self
+                .transitions
+                .partition_point(|&(start, _)| start <= seconds)
+            {
+                0 => self.initial_offset,
+                index => self.transitions[index - 1].1,
+            },
+        };
+        FixedOffset::east_opt(offset).unwrap_or(FixedOffset::east_opt(0).unwrap())
+    }
+}
+
+/// A timestamp format: a preset or a strftime string.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum TimeFormat {
+    /// RFC 3339, e.g. `2024-05-01T14:30:00Z`
+    #[default]
+    Iso8601,
+    /// Seconds since the Unix epoch
+    Epoch,
+    /// Milliseconds since the Unix epoch
+    EpochMillis,
+    Strftime(String),
+}
+
+impl TimeFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "iso8601" => Ok(TimeFormat::Iso8601),
+            "epoch" => Ok(TimeFormat::Epoch),
+            "epoch-millis" => Ok(TimeFormat::EpochMillis),
+            _ if StrftimeItems::new(value).any(|item| item == Item::Error) => Err(format!(
+                "expected iso8601, epoch, epoch-millis or a strftime format, got {:?}",
+                value
+            )),
+            _ => Ok(TimeFormat::Strftime(value.to_string())),
+        }
+    }
+
+    /// Formats `time`, in `zone` if given and UTC otherwise. Epoch formats
+    /// don't depend on the zone.
+    pub fn format(&self, time: DateTime<Utc>, zone: Option<&Zone>) -> String {
+        let offset = zone.map_or(FixedOffset::east_opt(0).unwrap(), |zone| {
+            zone.offset_at(time)
+        });
+        let local = time.with_timezone(&offset);
+        match self {
+            TimeFormat::Iso8601 => local.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            TimeFormat::Epoch => time.timestamp().to_string(),
+            TimeFormat::EpochMillis => time.timestamp_millis().to_string(),
+            TimeFormat::Strftime(format) => local.format(format).to_string(),
+        }
+    }
+}
+
+struct Header {
+    version: u8,
+    utc_count: usize,
+    std_count: usize,
+    leap_count: usize,
+    time_count: usize,
+    type_count: usize,
+    char_count: usize,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.get(..4)? != b"TZif" {
+            return None;
+        }
+        let count = |index: usize| {
+            let start = 20 + index * 4;
+            Some(u32::from_be_bytes(data.get(start..start + 4)?.try_into().ok()?) as usize)
+        };
+        Some(Header {
+            version: *data.get(4)?,
+            utc_count: count(0)?,
+            std_count: count(1)?,
+            leap_count: count(2)?,
+            time_count: count(3)?,
+            type_count: count(4)?,
+            char_count: count(5)?,
+        })
+    }
+
+    /// Offset of the end of the data block this header describes, for
+    /// `time_size`-byte times.
+    fn end(&self, time_size: usize) -> usize {
+        44 + self.time_count * (time_size + 1)
+            + self.type_count * 6
+            + self.char_count
+            + self.leap_count * (time_size + 4)
+            + self.std_count
+            + self.utc_count
+    }
+}
+
+/// A POSIX TZ rule such as `EST5EDT,M3.2.0,M11.1.0`. Only the `Mm.w.d`
+/// transition form is supported, which is what zoneinfo footers use.
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    std_offset: i32,
+    dst: Option<Dst>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Dst {
+    offset: i32,
+    start: Transition,
+    end: Transition,
+}
+
+/// The `week`th `weekday` of `month` (week 5 meaning the last), at `time`
+/// seconds past local midnight.
+#[derive(Debug, Clone, PartialEq)]
+struct Transition {
+    month: u32,
+    week: u8,
+    weekday: u32,
+    time: i32,
+}
+
+impl Rule {
+    fn parse(tz: &str) -> Option<Self> {
+        let mut rest = tz;
+        skip_name(&mut rest)?;
+        // POSIX offsets count hours west of UTC
+        let std_offset = -parse_time(&mut rest)?;
+        if rest.is_empty() {
+            return Some(Rule {
+                std_offset,
+                dst: None,
+            });
+        }
+        skip_name(&mut rest)?;
+        let offset = if rest.starts_with(',') {
+            std_offset + 3600
+        } else {
+            -parse_time(&mut rest)?
+        };
+        let mut transitions = rest.strip_prefix(',')?.split(',');
+        let start = Transition::parse(transitions.next()?)?;
+        let end = Transition::parse(transitions.next()?)?;
+        Some(Rule {
+            std_offset,
+            dst: Some(Dst { offset, start, end }),
+        })
+    }
+
+    fn offset_at(&self, time: DateTime<Utc>) -> i32 {
+        let Some(dst) = &self.dst else {
+            return self.std_offset;
+        };
+        let year = time.year();
+        // Transitions happen at local time: DST starts in standard time and
+        // ends in daylight time
+        let (Some(start), Some(end)) = (
+            dst.start.utc_seconds(year, self.std_offset),
+            dst.end.utc_seconds(year, dst.offset),
+        ) else {
+            return self.std_offset;
+        };
+        let seconds = time.timestamp();
+        let in_dst = if start < end {
+            start <= seconds && seconds < end
+        } else {
+            // Southern hemisphere: DST spans the new year
+            !(end <= seconds && seconds < start)
+        };
+        if in_dst { dst.offset } else { self.std_offset }
+    }
+}
+
+impl Transition {
+    fn parse(spec: &str) -> Option<Self> {
+        let (date, time) = match spec.split_once('/') {
+            Some((date, time)) => {
+                let mut time = time;
+                let seconds = parse_time(&mut time)?;
+                (date, time.is_empty().then_some(seconds)?)
+            }
+            None => (spec, 2 * 3600),
+        };
+        let mut fields = date.strip_prefix('M')?.split('.');
+        let transition = Transition {
+            month: fields
+                .next()?
+                .parse()
+                .ok()
+                .filter(|m| (1..=12).contains(m))?,
+            week: fields
+                .next()?
+                .parse()
+                .ok()
+                .filter(|w| (1..=5).contains(w))?,
+            weekday: fields.next()?.parse().ok().filter(|d| *d <= 6)?,
+            time,
+        };
+        fields.next().is_none().then_some(transition)
+    }
+
+    fn utc_seconds(&self, year: i32, utc_offset: i32) -> Option<i64> {
+        let weekday = Weekday::try_from(((self.weekday + 6) % 7) as u8).ok()?;
+        let day = (1..=self.week).rev().find_map(|week| {
+            NaiveDate::from_weekday_of_month_opt(year, self.month, weekday, week)
+        })?;
+        let midnight = day.and_hms_opt(0, 0, 0)?.and_utc().timestamp();
+        Some(midnight + self.time as i64 - utc_offset as i64)
+    }
+}
+
+/// Skips a zone abbreviation, either alphabetic (`EST`) or quoted (`<+07>`).
+fn skip_name(rest: &mut &str) -> Option<()> {
+    let len = match rest.strip_prefix('<') {
+        Some(quoted) => quoted.find('>')? + 2,
+        None => rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len()),
+    };
+    if len < 3 {
+        return None;
+    }
+    *rest = &rest[len..];
+    Some(())
+}
+
+/// Parses `[+-]hh[:mm[:ss]]` into seconds.
+fn parse_time(rest: &mut &str) -> Option<i32> {
+    let len = rest
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '+' | '-' | ':')))
+        .unwrap_or(rest.len());
+    let (value, remainder) = rest.split_at(len);
+    *rest = remainder;
+    let (sign, value) = match value.strip_prefix('-') {
+        Some(value) => (-1, value),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let mut seconds = 0;
+    for (index, part) in value.split(':').enumerate() {
+        if index > 2 {
+            return None;
+        }
+        seconds += part.parse::<i32>().ok()? * [3600, 60, 1][index];
+    }
+    Some(sign * seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_daylight_saving_rules() {
+        let zone = Zone {
+            transitions: Vec::new(),
+            initial_offset: 0,
+            rule: Rule::parse("EST5EDT,M3.2.0,M11.1.0"),
+        };
+        let at = |time: &str| TimeFormat::Iso8601.format(time.parse().unwrap(), Some(&zone));
+
+        assert_eq!(at("2024-01-15T12:00:00Z"), "2024-01-15T07:00:00-05:00");
+        // DST starts at 02:00 EST on 10 March 2024
+        assert_eq!(at("2024-03-10T06:59:59Z"), "2024-03-10T01:59:59-05:00");
+        assert_eq!(at("2024-03-10T07:00:00Z"), "2024-03-10T03:00:00-04:00");
+        assert_eq!(at("2024-11-03T06:00:00Z"), "2024-11-03T01:00:00-05:00");
+        assert_eq!(
+            TimeFormat::parse("%d/%m/%Y %H:%M %Z")
+                .unwrap()
+                .format("2024-01-15T12:00:00Z".parse().unwrap(), Some(&zone)),
+            "15/01/2024 07:00 -05:00"
+        );
+        assert!(TimeFormat::parse("%Q").is_err());
+        assert_eq!(
+            Rule::parse("<+07>-7"),
+            Some(Rule {
+                std_offset: 7 * 3600,
+                dst: None
+            })
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! POSTs each event to an arbitrary webhook (`--webhook-url`), as the JSON
+//! object of JSON reports or in the shape a `--webhook-template` gives.
+//!
+//! Templates use Handlebars-style placeholders: `{{detail}}` is replaced by
+//! the field with JSON string escaping (so it goes between quotes), and
+//! `{{{event}}}` by a raw JSON value. For example:
+//!
+//! ```text
+//! {"summary": "{{title}}", "link": "{{url}}", "health": {{{event}}}}
+//! ```
+
+use crate::HealthEvent;
+use crate::http_client::HttpClient;
+use crate::notify::{self, console_url, title};
+use crate::output::event_to_json;
+use aws_smithy_json::serialize::JsonValueWriter;
+use std::error::Error as StdError;
+use std::fs;
+use std::path::Path;
+
+/// The fields a template can use.
+const FIELDS: &[&str] = &[
+    "arn",
+    "service",
+    "region",
+    "status",
+    "event_type_code",
+    "event_type_category",
+    "start_time",
+    "detail",
+    "affected_entities",
+    "affected_accounts",
+    "title",
+    "url",
+    "event",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    /// A field, and whether it goes in raw rather than string-escaped
+    Field(&'static str, bool),
+}
+
+#[derive(Debug, Clone)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn StdError>> {
+        let source =
+            fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Template::parse(&source).map_err(|err| format!("{}: {}", path.display(), err).into())
+    }
+
+    fn parse(source: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find("{{") {
+            parts.push(Part::Text(rest[..start].to_string()));
+            let raw = rest[start..].starts_with("{{{");
+            let (open, close) = if raw { (3, "}}}") } else { (2, "}}") };
+            let inner = &rest[start + open..];
+            let end = inner
+                .find(close)
+                .ok_or_else(|| format!("unclosed placeholder at {:?}", excerpt(inner)))?;
+            let name = inner[..end].trim();
+            let field = FIELDS.iter().find(|field| **field == name).ok_or_else(|| {
+                format!(
+                    "unknown placeholder {:?}; expected one of {}",
+                    name,
+                    FIELDS.join(", ")
+                )
+            })?;
+            parts.push(Part::Field(field, raw));
+            rest = &inner[end + close.len()..];
+        }
+        parts.push(Part::Text(rest.to_string()));
+        Ok(Template { parts })
+    }
+
+    fn render(&self, event: &HealthEvent) -> String {
+        let mut output = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => output.push_str(text),
+                Part::Field(field, true) => output.push_str(&raw_value(event, field)),
+                Part::Field(field, false) => {
+                    // The quoted JSON string, without its quotes
+                    let mut quoted = String::new();
+                    JsonValueWriter::new(&mut quoted).string(&text_value(event, field));
+                    output.push_str(&quoted[1..quoted.len() - 1]);
+                }
+            }
+        }
+        output
+    }
+}
+
+fn text_value(event: &HealthEvent, field: &str) -> String {
+    match field {
+        "arn" => event.arn.clone(),
+        "service" => event.service.clone(),
+        "region" => event.region.clone(),
+        "status" => event.status.clone(),
+        "event_type_code" => event.event_type_code.clone(),
+        "event_type_category" => event.event_type_category.clone(),
+        "start_time" => event.timestamp.clone(),
+        "detail" => event.detail.clone(),
+        "affected_entities" => event.entities_text(),
+        "affected_accounts" => event.accounts_text(),
+        "title" => title(event),
+        "url" => console_url(event),
+        _ => event_to_json(event),
+    }
+}
+
+/// A field as a JSON value: the event as an object, others as strings.
+fn raw_value(event: &HealthEvent, field: &str) -> String {
+    if field == "event" {
+        return event_to_json(event);
+    }
+    let mut quoted = String::new();
+    JsonValueWriter::new(&mut quoted).string(&text_value(event, field));
+    quoted
+}
+
+fn excerpt(text: &str) -> String {
+    text.chars().take(20).collect()
+}
+
+/// Parses a `--webhook-header` such as `X-Team: ops`.
+pub fn parse_header(value: &str) -> Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((name, header_value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), header_value.trim().to_string()))
+        }
+        _ => Err("expected NAME: VALUE".to_string()),
+    }
+}
+
+pub struct WebhookSender {
+    http: HttpClient,
+    url: String,
+    headers: Vec<(String, String)>,
+    template: Option<Template>,
+}
+
+impl WebhookSender {
+    /// Posts to `url` with `headers`, plus a bearer `token` if given.
+    pub fn new(
+        http: HttpClient,
+        url: String,
+        mut headers: Vec<(String, String)>,
+        token: Option<&str>,
+        template: Option<Template>,
+    ) -> Self {
+        if let Some(token) = token {
+            headers.push(("authorization".to_string(), format!("Bearer {}", token)));
+        }
+        if !headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        {
+            headers.push(("content-type".to_string(), "application/json".to_string()));
+        }
+        WebhookSender {
+            http,
+            url,
+            headers,
+            template,
+        }
+    }
+
+    /// Posts one request per event, in order.
+    pub async fn send_events(&self, events: &[HealthEvent]) -> Result<(), Box<dyn StdError>> {
+        let headers: Vec<(&str, &str)> = self
+            .headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        for event in events {
+            let body = match &self.template {
+                Some(template) => template.render(event),
+                None => event_to_json(event),
+            };
+            notify::post(&self.http, "The webhook", &self.url, &headers, body).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_escaped_and_raw_fields() {
+        let template =
+            Template::parse(r#"{"text": "{{ service }}: {{detail}}", "arn": {{{arn}}}}"#).unwrap();
+        let event = HealthEvent {
+            arn: "arn:1".to_string(),
+            service: "EC2".to_string(),
+            detail: "Errors \"elevated\"\nsince 10:00".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            template.render(&event),
+            r#"{"text": "EC2: Errors \"elevated\"\nsince 10:00", "arn": "arn:1"}"#
+        );
+        assert!(Template::parse("{{nope}}").is_err());
+        assert!(Template::parse("{{detail").is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Sends event counts to a Zabbix server or proxy with the sender protocol
+//! (`--zabbix-server`), as trapper items of `--zabbix-host`:
+//!
+//! - `aws.health.services.discovery`, a low-level discovery rule's value
+//!   listing the services as `{#SERVICE}`
+//! - `aws.health.open_issues`, `aws.health.open_events` and
+//!   `aws.health.upcoming_scheduled_changes`, in all and per service, e.g.
+//!   `aws.health.open_issues[EC2]`
+//!
+//! Zabbix creates the per-service items from prototypes some time after
+//! discovery, so values for a new service are only taken from the next run.
+
+use crate::HealthEvent;
+use aws_smithy_json::deserialize::json_token_iter;
+use aws_smithy_json::deserialize::token::expect_document;
+use aws_smithy_json::serialize::JsonObjectWriter;
+use aws_smithy_types::Document;
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+const DEFAULT_PORT: u16 = 10051;
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// Every packet starts with this, then the protocol flags
+const HEADER: &[u8] = b"ZBXD\x01";
+/// Replies are short; anything longer isn't from a Zabbix server.
+const MAX_RESPONSE_BYTES: u64 = 1024 * 1024;
+
+/// Adds the default port to a `HOST[:PORT]` address without one.
+pub fn parse_server(value: &str) -> Result<String, String> {
+    if value.is_empty() {
+        return Err("expected HOST[:PORT]".to_string());
+    }
+    let has_port = value.rsplit_once(':').is_some_and(|(host, port)| {
+        port.parse::<u16>().is_ok() && (!host.contains(':') || host.starts_with('['))
+    });
+    Ok(match (has_port, value.contains(':')) {
+        (true, _) => value.to_string(),
+        // A bare IPv6 address
+        (false, true) => format!("[{}]:{}", value, DEFAULT_PORT),
+        (false, false) => format!("{}:{}", value, DEFAULT_PORT),
+    })
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    open_issues: u64,
+    open_events: u64,
+    upcoming_scheduled_changes: u64,
+}
+
+impl Counts {
+    fn add(&mut self, event: &HealthEvent) {
+        if event.status == "open" {
+            self.open_events += 1;
+            if event.event_type_category == "issue" {
+                self.open_issues += 1;
+            }
+        }
+        if event.status == "upcoming" && event.event_type_category == "scheduledChange" {
+            self.upcoming_scheduled_changes += 1;
+        }
+    }
+
+    fn items(&self, parameter: &str) -> [(String, u64); 3] {
+        [
+            ("open_issues", self.open_issues),
+            ("open_events", self.open_events),
+            (
+                "upcoming_scheduled_changes",
+                self.upcoming_scheduled_changes,
+            ),
+        ]
+        .map(|(key, value)| (format!("aws.health.{}{}", key, parameter), value))
+    }
+}
+
+pub struct ZabbixSender {
+    server: String,
+    host: String,
+}
+
+impl ZabbixSender {
+    pub fn new(server: String, host: String) -> Self {
+        ZabbixSender { server, host }
+    }
+
+    /// Sends the discovery value, then the counts, returning what the server
+    /// said it processed of the counts.
+    pub async fn send(&self, events: &[HealthEvent]) -> Result<String, Box<dyn StdError>> {
+        let (discovery, values) = self.items(events);
+        self.request(&[discovery]).await?;
+        self.request(&values).await
+    }
+
+    /// The discovery item, and the count items.
+    fn items(&self, events: &[HealthEvent]) -> ((String, String), Vec<(String, String)>) {
+        let mut total = Counts::default();
+        let mut by_service: BTreeMap<&str, Counts> = BTreeMap::new();
+        for event in events {
+            total.add(event);
+            if !event.service.is_empty() {
+                by_service.entry(&event.service).or_default().add(event);
+            }
+        }
+
+        let mut discovery = String::from("[");
+        for (index, service) in by_service.keys().enumerate() {
+            if index > 0 {
+                discovery.push(',');
+            }
+            let mut object = JsonObjectWriter::new(&mut discovery);
+            object.key("{#SERVICE}").string(service);
+            object.finish();
+        }
+        discovery.push(']');
+
+        let mut values: Vec<_> = total.items("").into_iter().collect();
+        for (service, counts) in &by_service {
+            values.extend(counts.items(&format!("[{}]", key_parameter(service))));
+        }
+        let values = values
+            .into_iter()
+            .map(|(key, value)| (key, value.to_string()))
+            .collect();
+        (
+            ("aws.health.services.discovery".to_string(), discovery),
+            values,
+        )
+    }
+
+    /// Sends one `sender data` request for `items`, returning the server's
+    /// `info`, e.g. "processed: 3; failed: 0; total: 3; ...".
+    async fn request(&self, items: &[(String, String)]) -> Result<String, Box<dyn StdError>> {
+        let mut stream = timeout(TIMEOUT, TcpStream::connect(&self.server))
+            .await
+            .map_err(|_| format!("timed out connecting to {}", self.server))?
+            .map_err(|err| format!("could not connect to {}: {}", self.server, err))?;
+        let body = request_body(&self.host, items);
+        timeout(TIMEOUT, stream.write_all(&packet(&body)))
+            .await
+            .map_err(|_| format!("timed out sending to {}", self.server))??;
+
+        let mut response = Vec::new();
+        timeout(
+            TIMEOUT,
+            (&mut stream)
+                .take(MAX_RESPONSE_BYTES)
+                .read_to_end(&mut response),
+        )
+        .await
+        .map_err(|_| format!("timed out waiting for {}", self.server))??;
+        parse_response(&response).map_err(|err| format!("{}: {}", self.server, err).into())
+    }
+}
+
+/// Quotes an item key parameter if it has characters that mean something
+/// in item keys.
+fn key_parameter(value: &str) -> String {
+    if value.contains([',', '[', ']', '"', ' ']) {
+        format!("\"{}\"", value.replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn request_body(host: &str, items: &[(String, String)]) -> String {
+    let mut body = String::new();
+    let mut request = JsonObjectWriter::new(&mut body);
+    request.key("request").string("sender data");
+    let mut data = request.key("data").start_array();
+    for (key, value) in items {
+        let mut item = data.value().start_object();
+        item.key("host").string(host);
+        item.key("key").string(key);
+        item.key("value").string(value);
+        item.finish();
+    }
+    data.finish();
+    request.finish();
+    body
+}
+
+/// A packet: the header, the body's length as 4 bytes and 4 reserved ones,
+/// little-endian, then the body.
+fn packet(body: &str) -> Vec<u8> {
+    let mut packet = HEADER.to_vec();
+    packet.extend_from_slice(&(body.len() as u64).to_le_bytes());
+    packet.extend_from_slice(body.as_bytes());
+    packet
+}
+
+fn parse_response(response: &[u8]) -> Result<String, Box<dyn StdError>> {
+    let body = response
+        .strip_prefix(HEADER)
+        .and_then(|rest| rest.get(8..))
+        .ok_or("unexpected response")?;
+    let document = expect_document(&mut json_token_iter(body).peekable())?;
+    let Document::Object(response) = document else {
+        return Err("unexpected response".into());
+    };
+    let info = match response.get("info") {
+        Some(Document::String(info)) => info.clone(),
+        _ => String::new(),
+    };
+    match response.get("response") {
+        Some(Document::String(status)) if status == "success" => Ok(info),
+        _ => Err(format!("request failed: {}", info).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_items_and_packets() {
+        assert_eq!(parse_server("zabbix").unwrap(), "zabbix:10051");
+        assert_eq!(parse_server("zabbix:10052").unwrap(), "zabbix:10052");
+        assert_eq!(parse_server("::1").unwrap(), "[::1]:10051");
+
+        let event = |service: &str, status: &str| HealthEvent {
+            service: service.to_string(),
+            event_type_category: "issue".to_string(),
+            status: status.to_string(),
+            ..Default::default()
+        };
+        let sender = ZabbixSender::new("zabbix:10051".to_string(), "aws".to_string());
+        let (discovery, values) = sender.items(&[event("EC2", "open"), event("S3", "closed")]);
+        assert_eq!(discovery.1, r#"[{"{#SERVICE}":"EC2"},{"{#SERVICE}":"S3"}]"#);
+        assert_eq!(
+            values[0],
+            ("aws.health.open_issues".to_string(), "1".to_string())
+        );
+        assert_eq!(
+            values[3],
+            ("aws.health.open_issues[EC2]".to_string(), "1".to_string())
+        );
+        assert_eq!(values.len(), 9);
+
+        let packet = packet("{}");
+        assert_eq!(packet, b"ZBXD\x01\x02\0\0\0\0\0\0\0{}");
+        let mut response = b"ZBXD\x01\0\0\0\0\0\0\0\0".to_vec();
+        response.extend_from_slice(br#"{"response":"success","info":"processed: 9; failed: 0"}"#);
+        assert_eq!(
+            parse_response(&response).unwrap(),
+            "processed: 9; failed: 0"
+        );
+    }
+}